@@ -0,0 +1,5 @@
+-- When the user proved control of their current email address; NULL means unverified
+ALTER TABLE users ADD COLUMN email_verified_at TEXT;
+
+-- Accounts created before verification existed are treated as verified
+UPDATE users SET email_verified_at = created_at;
@@ -0,0 +1,15 @@
+-- Every password sign-in attempt, used for per-account and per-IP throttling.
+-- `email` is stored lower-cased; `user_id` is set when it matched an account.
+CREATE TABLE login_attempts (
+    id TEXT PRIMARY KEY,
+    email TEXT NOT NULL,
+    user_id TEXT,
+    ip_address TEXT,
+    succeeded BOOLEAN NOT NULL,
+    attempted_at TEXT NOT NULL,
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_login_attempts_email ON login_attempts(email, attempted_at);
+CREATE INDEX idx_login_attempts_ip ON login_attempts(ip_address, attempted_at);
+CREATE INDEX idx_login_attempts_user_id ON login_attempts(user_id, attempted_at);
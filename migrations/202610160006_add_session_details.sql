@@ -0,0 +1,8 @@
+-- Where and when each session was last used, and whether it was a "remember me" sign-in
+ALTER TABLE sessions ADD COLUMN last_seen_at TEXT;
+ALTER TABLE sessions ADD COLUMN user_agent TEXT;
+ALTER TABLE sessions ADD COLUMN ip_address TEXT;
+ALTER TABLE sessions ADD COLUMN remember_me BOOLEAN NOT NULL DEFAULT 0;
+
+-- Carried through the two-factor step so the resulting session gets the right lifetime
+ALTER TABLE login_challenges ADD COLUMN remember_me BOOLEAN NOT NULL DEFAULT 0;
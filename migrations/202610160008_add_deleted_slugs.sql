@@ -0,0 +1,6 @@
+-- Slugs of deleted frogols, so their public page answers 410 Gone rather than 404.
+-- A slug leaves this table when a new frogol claims it.
+CREATE TABLE deleted_slugs (
+    slug TEXT PRIMARY KEY,
+    deleted_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now'))
+);
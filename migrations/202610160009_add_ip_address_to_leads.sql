@@ -0,0 +1,2 @@
+-- Address of the visitor who filled in the lead form, as seen past trusted proxies.
+ALTER TABLE leads ADD COLUMN ip_address TEXT;
@@ -0,0 +1,111 @@
+-- Clicks keep a daily visitor hash and a truncated address instead of the raw
+-- address and user agent. Each day's salt is deleted once the day is over.
+ALTER TABLE clicks ADD COLUMN visitor_id TEXT;
+
+CREATE TABLE visitor_salts (
+    day TEXT PRIMARY KEY,
+    salt TEXT NOT NULL
+);
+
+-- Existing clicks get a random id per address, user agent and day, so daily
+-- unique visitors still add up, and the mapping is thrown away.
+CREATE TEMP TABLE legacy_visitors AS
+SELECT day, ip_address, user_agent, lower(hex(randomblob(16))) AS visitor_id
+FROM (
+    SELECT DISTINCT substr(created_at, 1, 10) AS day, ip_address, user_agent
+    FROM clicks
+    WHERE ip_address IS NOT NULL
+);
+
+UPDATE clicks
+SET visitor_id = (
+    SELECT v.visitor_id
+    FROM legacy_visitors v
+    WHERE v.day = substr(clicks.created_at, 1, 10)
+      AND v.ip_address = clicks.ip_address
+      AND v.user_agent IS clicks.user_agent
+)
+WHERE ip_address IS NOT NULL;
+
+DROP TABLE legacy_visitors;
+
+-- IPv6 addresses keep their /48: the first three groups, then `::`, written
+-- as `visitor_service::truncate_ip` writes them. Mapped IPv4 addresses become
+-- IPv4 ones.
+CREATE TEMP TABLE ipv6_networks AS
+WITH addresses AS (
+    SELECT ip_address AS address FROM clicks WHERE instr(ip_address, ':') > 0
+    UNION
+    SELECT ip_address FROM leads WHERE instr(ip_address, ':') > 0
+),
+halves AS (
+    -- The groups before and after a `::`
+    SELECT address,
+        CASE WHEN instr(lower(address), '::') > 0
+            THEN substr(lower(address), 1, instr(address, '::') - 1)
+            ELSE lower(address)
+        END AS head,
+        CASE WHEN instr(address, '::') > 0 THEN substr(lower(address), instr(address, '::') + 2) ELSE '' END AS tail
+    FROM addresses
+),
+counted AS (
+    SELECT address, head, tail,
+        CASE WHEN head = '' THEN 0 ELSE length(head) - length(replace(head, ':', '')) + 1 END AS head_len,
+        -- An embedded IPv4 address fills two groups
+        CASE WHEN tail = '' THEN 0 ELSE length(tail) - length(replace(tail, ':', '')) + 1 + (instr(tail, '.') > 0) END AS tail_len,
+        substr(head, instr(head || ':', ':') + 1) AS head_rest,
+        substr(tail, instr(tail || ':', ':') + 1) AS tail_rest
+    FROM halves
+),
+split AS (
+    SELECT address, head_len, 8 - tail_len AS tail_at,
+        substr(head, 1, instr(head || ':', ':') - 1) AS h0,
+        substr(head_rest, 1, instr(head_rest || ':', ':') - 1) AS h1,
+        substr(substr(head_rest, instr(head_rest || ':', ':') + 1), 1,
+            instr(substr(head_rest, instr(head_rest || ':', ':') + 1) || ':', ':') - 1) AS h2,
+        substr(tail, 1, instr(tail || ':', ':') - 1) AS t0,
+        substr(tail_rest, 1, instr(tail_rest || ':', ':') - 1) AS t1,
+        substr(substr(tail_rest, instr(tail_rest || ':', ':') + 1), 1,
+            instr(substr(tail_rest, instr(tail_rest || ':', ':') + 1) || ':', ':') - 1) AS t2
+    FROM counted
+),
+placed AS (
+    -- Each of the first three groups comes from the head, the zeros `::`
+    -- stands for, or the tail
+    SELECT address,
+        ltrim(CASE WHEN head_len > 0 THEN h0 WHEN tail_at > 0 THEN '0' ELSE t0 END, '0') AS g0,
+        ltrim(CASE WHEN head_len > 1 THEN h1 WHEN tail_at > 1 THEN '0'
+            WHEN tail_at = 1 THEN t0 ELSE t1 END, '0') AS g1,
+        ltrim(CASE WHEN head_len > 2 THEN h2 WHEN tail_at > 2 THEN '0'
+            WHEN tail_at = 2 THEN t0 WHEN tail_at = 1 THEN t1 ELSE t2 END, '0') AS g2
+    FROM split
+)
+SELECT address,
+    CASE
+        WHEN address LIKE '::ffff:%.%' THEN rtrim(substr(address, 8), '0123456789') || '0'
+        WHEN address LIKE '0:0:0:0:0:ffff:%.%' THEN rtrim(substr(address, 16), '0123456789') || '0'
+        WHEN g2 <> '' THEN coalesce(nullif(g0, ''), '0') || ':' || coalesce(nullif(g1, ''), '0') || ':' || g2 || '::'
+        WHEN g1 <> '' THEN coalesce(nullif(g0, ''), '0') || ':' || g1 || '::'
+        WHEN g0 <> '' THEN g0 || '::'
+        ELSE '::'
+    END AS network
+FROM placed;
+
+-- IPv4 addresses keep their /24
+UPDATE clicks
+SET ip_address = CASE
+        WHEN instr(ip_address, ':') = 0 THEN rtrim(ip_address, '0123456789') || '0'
+        ELSE (SELECT network FROM ipv6_networks WHERE address = clicks.ip_address)
+    END,
+    user_agent = NULL
+WHERE ip_address IS NOT NULL OR user_agent IS NOT NULL;
+
+-- Lead addresses are truncated the same way
+UPDATE leads
+SET ip_address = CASE
+        WHEN instr(ip_address, ':') = 0 THEN rtrim(ip_address, '0123456789') || '0'
+        ELSE (SELECT network FROM ipv6_networks WHERE address = leads.ip_address)
+    END
+WHERE ip_address IS NOT NULL;
+
+DROP TABLE ipv6_networks;
@@ -0,0 +1,13 @@
+-- Clicks per link per UTC day, kept up to date as clicks are recorded so
+-- charts over long ranges need not scan every click.
+CREATE TABLE click_daily_rollups (
+    link_id TEXT NOT NULL,
+    day TEXT NOT NULL,
+    clicks INTEGER NOT NULL,
+    PRIMARY KEY (link_id, day)
+);
+
+INSERT INTO click_daily_rollups (link_id, day, clicks)
+SELECT link_id, substr(created_at, 1, 10), COUNT(*)
+FROM clicks
+GROUP BY link_id, substr(created_at, 1, 10);
@@ -0,0 +1,7 @@
+-- What the visitor's user agent said about them, worked out when the click is
+-- recorded. Earlier clicks no longer have a user agent and stay unclassified.
+ALTER TABLE clicks ADD COLUMN device_class TEXT;
+ALTER TABLE clicks ADD COLUMN browser TEXT;
+ALTER TABLE clicks ADD COLUMN os TEXT;
+-- Bot clicks are kept but left out of counts and rollups
+ALTER TABLE clicks ADD COLUMN is_bot BOOLEAN NOT NULL DEFAULT 0;
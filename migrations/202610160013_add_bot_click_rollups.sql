@@ -0,0 +1,12 @@
+-- Bot clicks per link per day, so charts can include them when asked.
+ALTER TABLE click_daily_rollups ADD COLUMN bot_clicks INTEGER NOT NULL DEFAULT 0;
+
+INSERT INTO click_daily_rollups (link_id, day, clicks, bot_clicks)
+SELECT link_id, substr(created_at, 1, 10), 0, COUNT(*)
+FROM clicks
+WHERE is_bot = 1
+GROUP BY link_id, substr(created_at, 1, 10)
+ON CONFLICT (link_id, day) DO UPDATE SET bot_clicks = excluded.bot_clicks;
+
+-- A visitor's recent clicks, for burst detection
+CREATE INDEX IF NOT EXISTS idx_clicks_visitor_created ON clicks(visitor_id, created_at);
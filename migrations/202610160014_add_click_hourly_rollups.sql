@@ -0,0 +1,14 @@
+-- Clicks per link per UTC hour, so hourly charts and day charts in any
+-- whole-hour time zone need not scan every click.
+CREATE TABLE click_hourly_rollups (
+    link_id TEXT NOT NULL,
+    hour TEXT NOT NULL,
+    clicks INTEGER NOT NULL,
+    bot_clicks INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (link_id, hour)
+);
+
+INSERT INTO click_hourly_rollups (link_id, hour, clicks, bot_clicks)
+SELECT link_id, substr(created_at, 1, 13), SUM(is_bot = 0), SUM(is_bot = 1)
+FROM clicks
+GROUP BY link_id, substr(created_at, 1, 13);
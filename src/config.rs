@@ -0,0 +1,623 @@
+//! Typed application settings.
+//!
+//! Settings are layered: built-in defaults, then the TOML file named by
+//! `FROGOLIO_CONFIG` (or `frogolio.toml` when present), then environment
+//! variables. Everything is checked before the server starts, and every
+//! problem is reported at once rather than one per restart.
+
+use ipnet::IpNet;
+use serde::Deserialize;
+use std::fmt::Display;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::logging::LogFormat;
+use crate::services::{
+    auth_service::{AuthConfig, EmailVerificationPolicy},
+    jwt_keys::JwtKeyring,
+    maintenance::MaintenanceConfig,
+};
+
+/// Read when `FROGOLIO_CONFIG` is not set, if it exists.
+const DEFAULT_CONFIG_FILE: &str = "frogolio.toml";
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("cannot read {path}: {source}")]
+    Read { path: PathBuf, source: std::io::Error },
+
+    #[error("cannot parse {path}: {source}")]
+    Parse { path: PathBuf, source: toml::de::Error },
+
+    #[error("invalid configuration:\n  - {}", .0.join("\n  - "))]
+    Invalid(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database_url: String,
+    pub auth: AuthConfig,
+    pub mail: MailConfig,
+    pub avatars: AvatarConfig,
+    pub compression: CompressionConfig,
+    pub maintenance: MaintenanceConfig,
+    pub metrics: MetricsConfig,
+    pub logging: LoggingConfig,
+    pub csrf: CsrfConfig,
+    pub security_headers: SecurityHeadersConfig,
+    pub proxy: ProxyConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub bind: SocketAddr,
+    /// Served under `/static`.
+    pub static_dir: PathBuf,
+    /// How long `/readyz` fails after a shutdown signal before the listener
+    /// closes, so load balancers stop routing here first.
+    pub drain_delay: Duration,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: SocketAddr::from(([0, 0, 0, 0], 3000)),
+            static_dir: PathBuf::from("static"),
+            drain_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MailConfig {
+    /// Where the file mailer writes outgoing `.eml` files.
+    pub outbox_dir: PathBuf,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self { outbox_dir: PathBuf::from("outbox") }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AvatarConfig {
+    /// Where uploads are stored; served under `/static/avatars`.
+    pub dir: PathBuf,
+    pub max_upload_bytes: usize,
+}
+
+impl Default for AvatarConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("static/avatars"),
+            max_upload_bytes: 5 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CompressionConfig {
+    pub enabled: bool,
+    /// Smaller responses are sent as they are.
+    pub min_size_bytes: u16,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self { enabled: true, min_size_bytes: 1024 }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MetricsConfig {
+    /// Required by `/metrics` as a bearer token when set.
+    pub bearer_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+}
+
+/// Routes whose writes need no CSRF token unless configured otherwise.
+pub const DEFAULT_CSRF_EXEMPT_ROUTES: &[&str] = &["/api/lead/:frogol_id"];
+
+#[derive(Clone)]
+pub struct CsrfConfig {
+    /// Signs the per-session tokens.
+    pub secret: Vec<u8>,
+    /// Route templates, such as `/api/lead/:frogol_id`, that accept writes without a token.
+    pub exempt_routes: Vec<String>,
+    /// Requests with an `Authorization: Bearer` header need no token.
+    pub exempt_bearer_tokens: bool,
+}
+
+/// Leaves the secret out.
+impl std::fmt::Debug for CsrfConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CsrfConfig")
+            .field("exempt_routes", &self.exempt_routes)
+            .field("exempt_bearer_tokens", &self.exempt_bearer_tokens)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProxyConfig {
+    /// Peers whose forwarding headers are believed. Empty: nobody's are.
+    pub trusted_proxies: Vec<IpNet>,
+}
+
+/// Browser headers for one group of routes.
+#[derive(Debug, Clone)]
+pub struct HeaderPolicy {
+    /// CSP `frame-ancestors` sources; `'none'` forbids framing anywhere.
+    pub frame_ancestors: Vec<String>,
+    /// CSP `img-src` sources. Avatars may be any https URL.
+    pub img_src: Vec<String>,
+    pub referrer_policy: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SecurityHeadersConfig {
+    /// `Strict-Transport-Security` lifetime, sent when `server.base_url` is
+    /// https; 0 leaves the header out.
+    pub hsts_max_age_secs: u64,
+    /// Published frogol pages and what visitors do on them.
+    pub public: HeaderPolicy,
+    /// Sign-in, the dashboard and everything else.
+    pub dashboard: HeaderPolicy,
+}
+
+impl Default for SecurityHeadersConfig {
+    fn default() -> Self {
+        let sources = |list: &[&str]| list.iter().map(|s| s.to_string()).collect();
+        Self {
+            hsts_max_age_secs: 365 * 24 * 60 * 60,
+            public: HeaderPolicy {
+                frame_ancestors: sources(&["'self'"]),
+                img_src: sources(&["'self'", "data:", "https:"]),
+                referrer_policy: "strict-origin-when-cross-origin".to_string(),
+            },
+            // Dashboard URLs can carry reset tokens, so they stay on this site
+            dashboard: HeaderPolicy {
+                frame_ancestors: sources(&["'none'"]),
+                img_src: sources(&["'self'", "data:", "https:"]),
+                referrer_policy: "same-origin".to_string(),
+            },
+        }
+    }
+}
+
+/// Values browsers accept in `Referrer-Policy`.
+const REFERRER_POLICIES: &[&str] = &[
+    "no-referrer",
+    "no-referrer-when-downgrade",
+    "origin",
+    "origin-when-cross-origin",
+    "same-origin",
+    "strict-origin",
+    "strict-origin-when-cross-origin",
+    "unsafe-url",
+];
+
+#[derive(Default)]
+struct Defaults {
+    server: ServerConfig,
+    mail: MailConfig,
+    avatars: AvatarConfig,
+    compression: CompressionConfig,
+    maintenance: MaintenanceConfig,
+    security_headers: SecurityHeadersConfig,
+}
+
+/// The TOML file as written, every key optional. Environment variables are
+/// applied on top before it is resolved into a `Config`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    server: ServerFile,
+    database: DatabaseFile,
+    auth: AuthFile,
+    mail: MailFile,
+    avatars: AvatarsFile,
+    compression: CompressionFile,
+    maintenance: MaintenanceFile,
+    metrics: MetricsFile,
+    logging: LoggingFile,
+    csrf: CsrfFile,
+    security_headers: SecurityHeadersFile,
+    proxy: ProxyFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ServerFile {
+    bind: Option<SocketAddr>,
+    base_url: Option<String>,
+    static_dir: Option<PathBuf>,
+    drain_delay_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DatabaseFile {
+    url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AuthFile {
+    jwt_secret: Option<String>,
+    jwt_keys: Option<String>,
+    jwt_active_key_id: Option<String>,
+    email_verification: Option<String>,
+    session_ttl_hours: Option<i64>,
+    remember_me_ttl_days: Option<i64>,
+    session_max_age_days: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MailFile {
+    outbox_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AvatarsFile {
+    dir: Option<PathBuf>,
+    max_upload_bytes: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CompressionFile {
+    enabled: Option<bool>,
+    min_size_bytes: Option<u16>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MaintenanceFile {
+    interval_secs: Option<u64>,
+    avatar_sweep_interval_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MetricsFile {
+    bearer_token: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LoggingFile {
+    format: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CsrfFile {
+    secret: Option<String>,
+    exempt_routes: Option<Vec<String>>,
+    exempt_bearer_tokens: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SecurityHeadersFile {
+    hsts_max_age_secs: Option<u64>,
+    public: HeaderPolicyFile,
+    dashboard: HeaderPolicyFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct HeaderPolicyFile {
+    frame_ancestors: Option<Vec<String>>,
+    img_src: Option<Vec<String>>,
+    referrer_policy: Option<String>,
+}
+
+impl HeaderPolicyFile {
+    /// Fills in `defaults`, reporting problems under `[security_headers.<group>]`.
+    fn resolve(self, group: &str, defaults: HeaderPolicy, problems: &mut Vec<String>) -> HeaderPolicy {
+        let frame_ancestors = self.frame_ancestors.unwrap_or(defaults.frame_ancestors);
+        let img_src = self.img_src.unwrap_or(defaults.img_src);
+        let referrer_policy = self.referrer_policy.unwrap_or(defaults.referrer_policy);
+        for (key, sources) in [("frame_ancestors", &frame_ancestors), ("img_src", &img_src)] {
+            if sources.is_empty() {
+                problems.push(format!("security_headers.{}.{} must list at least one source", group, key));
+            }
+            // Each source ends up in a header; a `;` would start a new directive
+            let malformed = |s: &&String| s.is_empty() || s.contains(|c: char| c == ';' || c == ',' || c.is_whitespace());
+            for source in sources.iter().filter(malformed) {
+                problems.push(format!("security_headers.{}.{}: {:?} is not a CSP source", group, key, source));
+            }
+        }
+        if !REFERRER_POLICIES.contains(&referrer_policy.as_str()) {
+            problems.push(format!(
+                "security_headers.{}.referrer_policy: {:?} is not a referrer policy",
+                group, referrer_policy
+            ));
+        }
+        HeaderPolicy { frame_ancestors, img_src, referrer_policy }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ProxyFile {
+    trusted_proxies: Option<Vec<String>>,
+}
+
+/// Parses CIDR ranges, taking a bare address as a range of one.
+fn parse_networks(entries: &[String], name: &str, problems: &mut Vec<String>) -> Vec<IpNet> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let parsed = entry.parse::<IpNet>().or_else(|_| entry.parse::<IpAddr>().map(IpNet::from));
+            parsed.map_err(|_| problems.push(format!("{}: {:?} is not an address or CIDR range", name, entry))).ok()
+        })
+        .collect()
+}
+
+/// Space-separated CSP sources, as they are written in the header.
+fn sources_from(name: &str, env: &dyn Fn(&str) -> Option<String>) -> Option<Vec<String>> {
+    env(name).map(|value| value.split_whitespace().map(str::to_string).collect())
+}
+
+/// Replaces `slot` with environment variable `name`, if set and parseable.
+fn override_from<T>(slot: &mut Option<T>, name: &str, env: &dyn Fn(&str) -> Option<String>, problems: &mut Vec<String>)
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let Some(value) = env(name) else { return };
+    match value.parse() {
+        Ok(value) => *slot = Some(value),
+        Err(e) => problems.push(format!("{}: {}", name, e)),
+    }
+}
+
+impl FileConfig {
+    fn apply_env(&mut self, env: &dyn Fn(&str) -> Option<String>, problems: &mut Vec<String>) {
+        override_from(&mut self.server.bind, "BIND_ADDRESS", env, problems);
+        override_from(&mut self.server.base_url, "APP_BASE_URL", env, problems);
+        override_from(&mut self.server.static_dir, "STATIC_DIR", env, problems);
+        override_from(&mut self.server.drain_delay_secs, "DRAIN_DELAY_SECS", env, problems);
+        override_from(&mut self.database.url, "DATABASE_URL", env, problems);
+        override_from(&mut self.auth.jwt_secret, "JWT_SECRET", env, problems);
+        override_from(&mut self.auth.jwt_keys, "JWT_KEYS", env, problems);
+        override_from(&mut self.auth.jwt_active_key_id, "JWT_ACTIVE_KEY_ID", env, problems);
+        override_from(&mut self.auth.email_verification, "EMAIL_VERIFICATION_POLICY", env, problems);
+        override_from(&mut self.auth.session_ttl_hours, "SESSION_TTL_HOURS", env, problems);
+        override_from(&mut self.auth.remember_me_ttl_days, "REMEMBER_ME_TTL_DAYS", env, problems);
+        override_from(&mut self.auth.session_max_age_days, "SESSION_MAX_AGE_DAYS", env, problems);
+        override_from(&mut self.mail.outbox_dir, "MAIL_OUTBOX_DIR", env, problems);
+        override_from(&mut self.avatars.dir, "AVATAR_DIR", env, problems);
+        override_from(&mut self.avatars.max_upload_bytes, "AVATAR_MAX_UPLOAD_BYTES", env, problems);
+        override_from(&mut self.compression.enabled, "COMPRESSION_ENABLED", env, problems);
+        override_from(&mut self.compression.min_size_bytes, "COMPRESSION_MIN_SIZE_BYTES", env, problems);
+        override_from(&mut self.maintenance.interval_secs, "MAINTENANCE_INTERVAL_SECS", env, problems);
+        override_from(&mut self.maintenance.avatar_sweep_interval_secs, "AVATAR_SWEEP_INTERVAL_SECS", env, problems);
+        override_from(&mut self.metrics.bearer_token, "METRICS_TOKEN", env, problems);
+        override_from(&mut self.logging.format, "LOG_FORMAT", env, problems);
+        override_from(&mut self.csrf.secret, "CSRF_SECRET", env, problems);
+        if let Some(routes) = env("CSRF_EXEMPT_ROUTES") {
+            self.csrf.exempt_routes = Some(
+                routes.split(',').map(str::trim).filter(|r| !r.is_empty()).map(str::to_string).collect(),
+            );
+        }
+        override_from(&mut self.csrf.exempt_bearer_tokens, "CSRF_EXEMPT_BEARER_TOKENS", env, problems);
+        override_from(&mut self.security_headers.hsts_max_age_secs, "HSTS_MAX_AGE_SECS", env, problems);
+        if let Some(sources) = sources_from("PUBLIC_FRAME_ANCESTORS", env) {
+            self.security_headers.public.frame_ancestors = Some(sources);
+        }
+        if let Some(sources) = sources_from("DASHBOARD_FRAME_ANCESTORS", env) {
+            self.security_headers.dashboard.frame_ancestors = Some(sources);
+        }
+        if let Some(proxies) = env("TRUSTED_PROXIES") {
+            self.proxy.trusted_proxies = Some(
+                proxies.split(',').map(str::trim).filter(|p| !p.is_empty()).map(str::to_string).collect(),
+            );
+        }
+    }
+
+    fn jwt_keyring(&self) -> Result<JwtKeyring, String> {
+        match (&self.auth.jwt_keys, &self.auth.jwt_secret) {
+            (Some(spec), _) => {
+                let active = self
+                    .auth
+                    .jwt_active_key_id
+                    .as_deref()
+                    .ok_or("auth.jwt_active_key_id (JWT_ACTIVE_KEY_ID) must be set with auth.jwt_keys")?;
+                JwtKeyring::parse(spec, active).map_err(|e| format!("auth.jwt_keys (JWT_KEYS): {}", e))
+            }
+            (None, Some(secret)) if !secret.is_empty() => Ok(JwtKeyring::single(secret.clone())),
+            _ => Err("auth.jwt_secret (JWT_SECRET) or auth.jwt_keys (JWT_KEYS) must be set".to_string()),
+        }
+    }
+
+    /// Fills in defaults and checks every value, collecting all problems.
+    fn resolve(self, mut problems: Vec<String>) -> Result<Config, ConfigError> {
+        let database_url = self.database.url.clone().unwrap_or_else(|| {
+            problems.push("database.url (DATABASE_URL) must be set".to_string());
+            String::new()
+        });
+
+        let jwt_keys = self.jwt_keyring().map_err(|e| problems.push(e)).ok();
+
+        let base_url = self.server.base_url.clone().unwrap_or_else(|| "http://localhost:3000".to_string());
+        if !(base_url.starts_with("http://") || base_url.starts_with("https://")) {
+            problems.push(format!("server.base_url (APP_BASE_URL) must be an http(s) URL, got {:?}", base_url));
+        }
+
+        let verification_policy = self
+            .auth
+            .email_verification
+            .as_deref()
+            .unwrap_or("publish")
+            .parse::<EmailVerificationPolicy>()
+            .map_err(|e| problems.push(format!("auth.email_verification (EMAIL_VERIFICATION_POLICY): {}", e)))
+            .ok();
+
+        let log_format = match self.logging.format.as_deref() {
+            None => LogFormat::default(),
+            Some(format) => format
+                .parse()
+                .map_err(|e| problems.push(format!("logging.format (LOG_FORMAT): {}", e)))
+                .unwrap_or_default(),
+        };
+
+        let csrf_exempt_routes = self
+            .csrf
+            .exempt_routes
+            .clone()
+            .unwrap_or_else(|| DEFAULT_CSRF_EXEMPT_ROUTES.iter().map(|r| r.to_string()).collect());
+        for route in csrf_exempt_routes.iter().filter(|r| !r.starts_with('/')) {
+            problems.push(format!("csrf.exempt_routes (CSRF_EXEMPT_ROUTES): {:?} is not a route path", route));
+        }
+
+        let trusted_proxies = parse_networks(
+            self.proxy.trusted_proxies.as_deref().unwrap_or_default(),
+            "proxy.trusted_proxies (TRUSTED_PROXIES)",
+            &mut problems,
+        );
+
+        let defaults = Defaults::default();
+        let security_headers = SecurityHeadersConfig {
+            hsts_max_age_secs: self
+                .security_headers
+                .hsts_max_age_secs
+                .unwrap_or(defaults.security_headers.hsts_max_age_secs),
+            public: self.security_headers.public.resolve("public", defaults.security_headers.public, &mut problems),
+            dashboard: self.security_headers.dashboard.resolve(
+                "dashboard",
+                defaults.security_headers.dashboard,
+                &mut problems,
+            ),
+        };
+        let mut positive = |value: i64, name: &str| {
+            if value <= 0 {
+                problems.push(format!("{} must be positive", name));
+            }
+            value
+        };
+        let session_ttl = chrono::Duration::hours(positive(
+            self.auth.session_ttl_hours.unwrap_or(24),
+            "auth.session_ttl_hours (SESSION_TTL_HOURS)",
+        ));
+        let remember_me_ttl = chrono::Duration::days(positive(
+            self.auth.remember_me_ttl_days.unwrap_or(30),
+            "auth.remember_me_ttl_days (REMEMBER_ME_TTL_DAYS)",
+        ));
+        let session_max_age = chrono::Duration::days(positive(
+            self.auth.session_max_age_days.unwrap_or(90),
+            "auth.session_max_age_days (SESSION_MAX_AGE_DAYS)",
+        ));
+        let purge_interval = positive(
+            self.maintenance.interval_secs.unwrap_or(defaults.maintenance.purge_interval.as_secs()) as i64,
+            "maintenance.interval_secs (MAINTENANCE_INTERVAL_SECS)",
+        );
+        let avatar_sweep_interval = positive(
+            self.maintenance.avatar_sweep_interval_secs.unwrap_or(defaults.maintenance.avatar_sweep_interval.as_secs()) as i64,
+            "maintenance.avatar_sweep_interval_secs (AVATAR_SWEEP_INTERVAL_SECS)",
+        );
+        let max_upload_bytes = positive(
+            self.avatars.max_upload_bytes.unwrap_or(defaults.avatars.max_upload_bytes) as i64,
+            "avatars.max_upload_bytes (AVATAR_MAX_UPLOAD_BYTES)",
+        );
+        if session_ttl > session_max_age || remember_me_ttl > session_max_age {
+            problems.push("session lifetimes must not exceed auth.session_max_age_days (SESSION_MAX_AGE_DAYS)".to_string());
+        }
+
+        let (Some(jwt_keys), Some(verification_policy), true) = (jwt_keys, verification_policy, problems.is_empty()) else {
+            return Err(ConfigError::Invalid(problems));
+        };
+        // Without its own secret, CSRF signing follows the active JWT key
+        let csrf_secret = match self.csrf.secret.filter(|s| !s.is_empty()) {
+            Some(secret) => secret.into_bytes(),
+            None => jwt_keys.derive_key("csrf"),
+        };
+
+        Ok(Config {
+            server: ServerConfig {
+                bind: self.server.bind.unwrap_or(defaults.server.bind),
+                static_dir: self.server.static_dir.unwrap_or(defaults.server.static_dir),
+                drain_delay: self.server.drain_delay_secs.map(Duration::from_secs).unwrap_or(defaults.server.drain_delay),
+            },
+            database_url,
+            auth: AuthConfig {
+                jwt_keys,
+                base_url,
+                verification_policy,
+                session_ttl,
+                remember_me_ttl,
+                session_max_age,
+            },
+            mail: MailConfig {
+                outbox_dir: self.mail.outbox_dir.unwrap_or(defaults.mail.outbox_dir),
+            },
+            avatars: AvatarConfig {
+                dir: self.avatars.dir.unwrap_or(defaults.avatars.dir),
+                max_upload_bytes: max_upload_bytes as usize,
+            },
+            compression: CompressionConfig {
+                enabled: self.compression.enabled.unwrap_or(defaults.compression.enabled),
+                min_size_bytes: self.compression.min_size_bytes.unwrap_or(defaults.compression.min_size_bytes),
+            },
+            maintenance: MaintenanceConfig {
+                purge_interval: Duration::from_secs(purge_interval as u64),
+                avatar_sweep_interval: Duration::from_secs(avatar_sweep_interval as u64),
+            },
+            metrics: MetricsConfig {
+                bearer_token: self.metrics.bearer_token.filter(|token| !token.is_empty()),
+            },
+            logging: LoggingConfig { format: log_format },
+            csrf: CsrfConfig {
+                secret: csrf_secret,
+                exempt_routes: csrf_exempt_routes,
+                exempt_bearer_tokens: self.csrf.exempt_bearer_tokens.unwrap_or(true),
+            },
+            security_headers,
+            proxy: ProxyConfig { trusted_proxies },
+        })
+    }
+}
+
+impl Config {
+    /// Loads the config file, if any, and the process environment.
+    pub fn load() -> Result<Self, ConfigError> {
+        let (path, required) = match std::env::var("FROGOLIO_CONFIG") {
+            Ok(path) => (PathBuf::from(path), true),
+            Err(_) => (PathBuf::from(DEFAULT_CONFIG_FILE), false),
+        };
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => Some(contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !required => None,
+            Err(source) => return Err(ConfigError::Read { path, source }),
+        };
+        Self::from_sources(contents.as_deref().map(|c| (path.as_path(), c)), &|name| std::env::var(name).ok())
+    }
+
+    /// Layers `env` over the TOML `file` (path and contents) over the defaults.
+    pub fn from_sources(file: Option<(&Path, &str)>, env: &dyn Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let mut layered = match file {
+            Some((path, contents)) => toml::from_str::<FileConfig>(contents).map_err(|source| ConfigError::Parse {
+                path: path.to_path_buf(),
+                source,
+            })?,
+            None => FileConfig::default(),
+        };
+        let mut problems = Vec::new();
+        layered.apply_env(env, &mut problems);
+        layered.resolve(problems)
+    }
+}
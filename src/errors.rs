@@ -1,42 +1,213 @@
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
 };
+use schemars::JsonSchema;
+use serde::Serialize;
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use thiserror::Error;
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+/// JSON body of every error response.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ErrorBody {
+    /// Stable, machine-readable error code such as `not_found`.
+    pub code: String,
+    /// Human-readable message; may change between releases.
+    pub error: String,
+    /// Problems with individual fields, keyed by field name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, Vec<FieldError>>,
+}
+
+/// One failed check on a field, e.g. `{"code": "email"}`.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct FieldError {
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
 
 #[derive(Error, Debug)]
 pub enum AppError {
+    /// A failed query. `RowNotFound` is answered as a 404 and UNIQUE
+    /// violations as a 409.
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
 
+    /// A malformed request or a request the current state does not allow.
     #[error("Invalid input: {0}")]
     InvalidInput(String),
 
-    #[error("Internal error: {0}")]
-    InternalError(String),
-
+    /// A well-formed request whose content is not acceptable.
     #[error("Validation error: {0}")]
+    #[allow(dead_code)]
     ValidationError(String),
 
+    /// Field checks from `validator` failed.
+    #[error("Invalid fields: {0}")]
+    InvalidFields(#[from] ValidationErrors),
+
     #[error("Internal server error: {0}")]
     Internal(String),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// The resource existed but was deleted for good.
+    #[error("Gone: {0}")]
+    Gone(String),
+
+    /// The request clashes with existing data, such as a taken slug.
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    #[error("Unsupported media type: {0}")]
+    UnsupportedMediaType(String),
+
+    /// Too many attempts; the client may retry after `retry_after_secs`.
+    #[error("Rate limited: {message}")]
+    RateLimited { message: String, retry_after_secs: u64 },
+}
+
+impl AppError {
+    /// Turns a missing-row database error into `NotFound` with the given message.
+    pub fn not_found_if_missing(self, message: &str) -> Self {
+        match self {
+            AppError::Database(sqlx::Error::RowNotFound) => AppError::NotFound(message.to_string()),
+            other => other,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::Database(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND,
+            AppError::Database(e) if is_unique_violation(e) => StatusCode::CONFLICT,
+            AppError::Database(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            AppError::ValidationError(_) | AppError::InvalidFields(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Gone(_) => StatusCode::GONE,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    /// Stable code clients can match on. Never change an existing one.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Database(sqlx::Error::RowNotFound) => "not_found",
+            AppError::Database(e) if is_unique_violation(e) => "conflict",
+            AppError::Database(_) => "database_error",
+            AppError::InvalidInput(_) => "invalid_input",
+            AppError::ValidationError(_) | AppError::InvalidFields(_) => "validation_failed",
+            AppError::Internal(_) => "internal_error",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::NotFound(_) => "not_found",
+            AppError::Gone(_) => "gone",
+            AppError::Conflict(_) => "conflict",
+            AppError::PayloadTooLarge(_) => "payload_too_large",
+            AppError::UnsupportedMediaType(_) => "unsupported_media_type",
+            AppError::RateLimited { .. } => "rate_limited",
+        }
+    }
+
+    /// The message shown to clients. Database details stay in the logs.
+    fn public_message(&self) -> Cow<'_, str> {
+        match self {
+            AppError::Database(sqlx::Error::RowNotFound) => Cow::Borrowed("Not found"),
+            AppError::Database(e) if is_unique_violation(e) => Cow::Borrowed("Already exists"),
+            AppError::Database(_) => Cow::Borrowed("Database error"),
+            AppError::InvalidFields(_) => Cow::Borrowed("Some fields are invalid"),
+            AppError::InvalidInput(msg)
+            | AppError::ValidationError(msg)
+            | AppError::Internal(msg)
+            | AppError::Unauthorized(msg)
+            | AppError::Forbidden(msg)
+            | AppError::NotFound(msg)
+            | AppError::Gone(msg)
+            | AppError::Conflict(msg)
+            | AppError::PayloadTooLarge(msg)
+            | AppError::UnsupportedMediaType(msg) => Cow::Borrowed(msg.as_str()),
+            AppError::RateLimited { message, .. } => Cow::Borrowed(message.as_str()),
+        }
+    }
+
+    pub fn body(&self) -> ErrorBody {
+        let fields = match self {
+            AppError::InvalidFields(errors) => field_errors(errors),
+            _ => BTreeMap::new(),
+        };
+        ErrorBody {
+            code: self.code().to_string(),
+            error: self.public_message().into_owned(),
+            fields,
+        }
+    }
+}
+
+/// A UNIQUE constraint rejected the write, e.g. two requests racing for one slug.
+fn is_unique_violation(error: &sqlx::Error) -> bool {
+    matches!(error, sqlx::Error::Database(e) if e.is_unique_violation())
+}
+
+/// Flattens `validator`'s nested errors, naming nested fields `outer.inner`
+/// and list items `list[0].inner`.
+fn field_errors(errors: &ValidationErrors) -> BTreeMap<String, Vec<FieldError>> {
+    fn collect(prefix: &str, errors: &ValidationErrors, out: &mut BTreeMap<String, Vec<FieldError>>) {
+        for (field, kind) in errors.errors() {
+            let name = if prefix.is_empty() { field.to_string() } else { format!("{}.{}", prefix, field) };
+            match kind {
+                ValidationErrorsKind::Field(failures) => {
+                    out.entry(name).or_default().extend(failures.iter().map(|failure| FieldError {
+                        code: failure.code.to_string(),
+                        message: failure.message.as_ref().map(|m| m.to_string()),
+                    }));
+                }
+                ValidationErrorsKind::Struct(inner) => collect(&name, inner, out),
+                ValidationErrorsKind::List(items) => {
+                    for (index, inner) in items {
+                        collect(&format!("{}[{}]", name, index), inner, out);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut out = BTreeMap::new();
+    collect("", errors, &mut out);
+    out
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-            let (status, error_message) = match &self {
-        AppError::Database(_) => (StatusCode::INTERNAL_SERVER_ERROR, Cow::Borrowed("Database error")),
-        AppError::InvalidInput(msg) => (StatusCode::BAD_REQUEST, Cow::Borrowed(msg.as_str())),
-        AppError::InternalError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, Cow::Borrowed(msg.as_str())),
-        AppError::ValidationError(msg) => (StatusCode::BAD_REQUEST, Cow::Borrowed(msg.as_str())),
-        AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, Cow::Borrowed(msg.as_str())),
-    };
-
-        let body = axum::Json(serde_json::json!({
-            "error": error_message
-        }));
-
-        (status, body).into_response()
+        let status = self.status();
+        if status.is_server_error() {
+            tracing::error!("{}", self);
+        }
+
+        let body = self.body();
+        let mut res = (status, axum::Json(body.clone())).into_response();
+        if let AppError::RateLimited { retry_after_secs, .. } = &self {
+            res.headers_mut().insert(header::RETRY_AFTER, retry_after_secs.to_string().parse().expect("digits are a valid header value"));
+        }
+        // Lets `middleware::error_format` re-render the error for browsers
+        res.extensions_mut().insert(body);
+        res
     }
 }
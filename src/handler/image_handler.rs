@@ -3,7 +3,7 @@ use tempfile::NamedTempFile;
 use tokio::fs;
 use tokio::io::AsyncReadExt;
 use uuid::Uuid;
-use std::path::PathBuf;
+use std::path::Path;
 use sqlx::SqlitePool;
 use infer;
 use futures::stream::{self, StreamExt};
@@ -11,9 +11,11 @@ use futures::stream::{self, StreamExt};
 use crate::errors::AppError;
 use crate::models::avatar_image::FrogolAvatarImage;
 
+#[allow(dead_code)]
 pub const ALLOWED_IMAGE_TYPES: [&str; 4] = ["image/jpeg", "image/png", "image/gif", "image/webp"];
 
 // Struct to hold processed image data before saving to DB
+#[allow(dead_code)]
 pub struct ProcessedImageData {
     pub new_image_id: String,
     pub unique_filename: String,
@@ -23,13 +25,13 @@ pub struct ProcessedImageData {
 /// Handles processing and saving a single uploaded image.
 /// Validates MIME type, generates a unique filename, saves the file,
 /// and prepares data for database insertion.
+#[allow(dead_code)]
 pub async fn process_and_save_image(
     image_field: FieldData<NamedTempFile>,
-    image_save_dir: &PathBuf,
+    image_save_dir: &Path,
     image_order: i64, // Used to determine the order if multiple images are uploaded
 ) -> Result<ProcessedImageData, AppError> {
-    let original_file_name = image_field.metadata.file_name.as_ref()
-        .map(|name| name.as_str())
+    let original_file_name = image_field.metadata.file_name.as_deref()
         .unwrap_or("unknown_image.bin");
     tracing::debug!("Processing image: {}", original_file_name);
 
@@ -67,7 +69,7 @@ pub async fn process_and_save_image(
     if let Some(mime_to_check) = &effective_mime_type {
         if !ALLOWED_IMAGE_TYPES.contains(&mime_to_check.as_str()) {
             tracing::warn!("Uploaded image {} has unsupported type: {} (Client: {:?})", original_file_name, mime_to_check, client_content_type.map(|c|c.to_string()));
-            return Err(AppError::ValidationError(format!("Unsupported image type: {}. Only JPEG, PNG, GIF, and WebP are allowed.", mime_to_check)));
+            return Err(AppError::UnsupportedMediaType(format!("Unsupported image type: {}. Only JPEG, PNG, GIF, and WebP are allowed.", mime_to_check)));
         }
     } else {
         tracing::warn!("Image type for {} remains undetermined after checks. Client type: {:?}", original_file_name, client_content_type.map(|c|c.to_string()));
@@ -102,7 +104,7 @@ pub async fn process_and_save_image(
 }
 
 /// Deletes an image file from the filesystem.
-pub async fn delete_image_file(image_filename: &str, image_save_dir: &PathBuf) -> Result<(), AppError> {
+pub async fn delete_image_file(image_filename: &str, image_save_dir: &Path) -> Result<(), AppError> {
     let image_path_to_delete = image_save_dir.join(image_filename);
     if image_path_to_delete.exists() {
         tokio::fs::remove_file(&image_path_to_delete).await.map_err(|e| {
@@ -118,6 +120,7 @@ pub async fn delete_image_file(image_filename: &str, image_save_dir: &PathBuf) -
 }
 
 /// Deletes image metadata from the database for a specific avatar image of a frogol.
+#[allow(dead_code)]
 pub async fn delete_avatar_image_metadata_from_db(
     pool: &SqlitePool,
     frogol_id: &str,
@@ -133,10 +136,11 @@ pub async fn delete_avatar_image_metadata_from_db(
 }
 
 /// Deletes all avatar images associated with a frogol from the filesystem and database.
+#[allow(dead_code)]
 pub async fn delete_all_avatar_images_for_frogol(
     pool: &SqlitePool,
     frogol_id: &str,
-    image_save_dir: &PathBuf,
+    image_save_dir: &Path,
 ) -> Result<(), AppError> {
     let images_to_delete: Vec<FrogolAvatarImage> = sqlx::query_as!(
         FrogolAvatarImage,
@@ -188,9 +192,10 @@ pub async fn delete_all_avatar_images_for_frogol(
 
 /// Batch processes multiple images in parallel for better performance
 /// Returns a vector of successfully processed images and any errors encountered
+#[allow(dead_code)]
 pub async fn process_images_batch(
     image_fields: Vec<FieldData<NamedTempFile>>,
-    image_save_dir: &PathBuf,
+    image_save_dir: &Path,
     starting_order: i64,
 ) -> (Vec<ProcessedImageData>, Vec<AppError>) {
     let mut processed_images = Vec::new();
@@ -198,7 +203,7 @@ pub async fn process_images_batch(
     
     // Create a stream of futures for parallel processing
     let futures = image_fields.into_iter().enumerate().map(|(index, image_field)| {
-        let image_save_dir = image_save_dir.clone();
+        let image_save_dir = image_save_dir.to_path_buf();
         let image_order = starting_order + index as i64;
         
         async move {
@@ -207,8 +212,7 @@ pub async fn process_images_batch(
                 return Err(AppError::ValidationError(format!("Image at index {} has no filename", index)));
             }
             
-            let original_file_name = image_field.metadata.file_name.as_ref()
-                .map(|name| name.as_str())
+            let original_file_name = image_field.metadata.file_name.as_deref()
                 .unwrap_or("unknown");
                 
             if original_file_name.is_empty() && image_field.metadata.content_type.is_none() {
@@ -247,6 +251,7 @@ pub async fn process_images_batch(
 }
 
 /// Saves avatar image metadata to database
+#[allow(dead_code)]
 pub async fn save_avatar_image_metadata(
     pool: &SqlitePool,
     frogol_id: &str,
@@ -264,6 +269,7 @@ pub async fn save_avatar_image_metadata(
 }
 
 /// Gets the current avatar image for a frogol
+#[allow(dead_code)]
 pub async fn get_frogol_avatar_image(
     pool: &SqlitePool,
     frogol_id: &str,
@@ -279,13 +285,33 @@ pub async fn get_frogol_avatar_image(
     Ok(avatar_image)
 }
 
+/// Whether any frogol still points at `image_filename`, either through its
+/// avatar image records or its `avatar_url`.
+pub async fn avatar_file_in_use(pool: &SqlitePool, image_filename: &str) -> Result<bool, AppError> {
+    let url = format!("/static/avatars/{}", image_filename);
+    let references = sqlx::query_scalar!(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM frogols WHERE avatar_url = ?1) +
+            (SELECT COUNT(*) FROM frogol_avatar_images WHERE image_filename = ?2)
+            as "count!: i64"
+        "#,
+        url,
+        image_filename
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(references > 0)
+}
+
 /// Batch deletes multiple image files in parallel
 pub async fn delete_images_batch(
     image_filenames: Vec<String>,
-    image_save_dir: &PathBuf,
+    image_save_dir: &Path,
 ) -> Vec<AppError> {
     let futures = image_filenames.into_iter().map(|filename| {
-        let image_save_dir = image_save_dir.clone();
+        let image_save_dir = image_save_dir.to_path_buf();
         
         async move {
             delete_image_file(&filename, &image_save_dir).await
@@ -0,0 +1,58 @@
+//! Log output. `text` is meant for a terminal; `json` writes one object per
+//! line for log shippers. Either way, lines logged while handling a request
+//! carry the fields of its span from `middleware::request_id`.
+
+use std::str::FromStr;
+use tracing::Subscriber;
+use tracing_subscriber::{
+    fmt::{self, MakeWriter},
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("expected \"text\" or \"json\", got {:?}", other)),
+        }
+    }
+}
+
+/// Formats events as `format` into `writer`.
+pub fn fmt_layer<S, W>(format: LogFormat, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    match format {
+        LogFormat::Text => fmt::layer().with_writer(writer).boxed(),
+        // The request span's fields go on every line; outer spans add nothing
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .with_writer(writer)
+            .boxed(),
+    }
+}
+
+/// Installs the global subscriber, filtered by `RUST_LOG`.
+pub fn init(format: LogFormat) {
+    tracing_subscriber::registry()
+        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| "frogolio=debug,tower_http=debug".into()))
+        .with(fmt_layer(format, std::io::stdout))
+        .init();
+}
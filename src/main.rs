@@ -4,8 +4,12 @@ use axum::{
 };
 use sqlx::SqlitePool;
 use std::net::SocketAddr;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use std::sync::Arc;
+use tokio::sync::watch;
 
+mod config;
+mod logging;
+mod metrics;
 mod errors;
 mod routes;
 mod state;
@@ -14,27 +18,33 @@ mod repo;
 mod middleware;
 mod handler;
 mod models;
+#[cfg(test)]
+mod tests;
 
 use crate::routes::frogol::frogol_routes;
 use crate::routes::auth::auth_routes;
 use crate::routes::dashboard::dashboard_routes;
 use crate::routes::avatar::avatar_routes;
+use crate::routes::settings::settings_routes;
+use crate::config::Config;
+use crate::services::clock::SystemClock;
+use crate::services::mailer::FileMailer;
 use crate::state::AppState;
 
 #[tokio::main]
 async fn main() {
     // Load environment variables from .env if present
     let _ = dotenvy::dotenv();
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "frogolio=debug,tower_http=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
-    let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let pool = SqlitePool::connect(&db_url)
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    logging::init(config.logging.format);
+
+    let pool = SqlitePool::connect(&config.database_url)
         .await
         .expect("Failed to connect to database");
 
@@ -44,32 +54,108 @@ async fn main() {
         .await
         .expect("Failed to run migrations");
 
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .expect("JWT_SECRET must be set for production");
+    tracing::info!("Signing sessions with JWT key {:?}", config.auth.jwt_keys.active_kid());
+
+    let mailer = Arc::new(FileMailer::new(config.mail.outbox_dir.clone()));
+    let maintenance_config = config.maintenance.clone();
+    let addr = config.server.bind;
+    let drain_delay = config.server.drain_delay;
+    let app_state = AppState::new(pool.clone(), config, mailer, Arc::new(SystemClock));
+
+    // Background cleanup runs until the server stops
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let maintenance = app_state
+        .services
+        .maintenance
+        .clone()
+        .spawn(maintenance_config, shutdown_rx);
+
+    let health = app_state.services.health.clone();
+    let app = build_router(app_state);
+
+    tracing::info!("Frogolio server starting on {}", addr);
+    let listener = tokio::net::TcpListener::bind(addr).await.expect("Failed to bind to address");
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            // Fail readiness while still serving, so the load balancer has
+            // seen it by the time the listener closes
+            health.begin_draining();
+            tracing::info!("Draining for {:?} before closing the listener", drain_delay);
+            tokio::time::sleep(drain_delay).await;
+        })
+        .await
+        .expect("Failed to start server");
+
+    tracing::info!("Server stopped, waiting for background tasks");
+    let _ = shutdown_tx.send(true);
+    if let Err(e) = maintenance.await {
+        tracing::error!("Maintenance task panicked: {}", e);
+    }
+    pool.close().await;
+    tracing::info!("Shutdown complete");
+}
+
+/// Resolves on Ctrl+C, or SIGTERM on Unix.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
 
-    let app_state = AppState::new(pool, jwt_secret);
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
 
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    tracing::info!("Shutdown signal received");
+}
+
+/// Assembles every route group with its state and global layers.
+fn build_router(app_state: AppState) -> Router {
     use tower_http::services::ServeDir;
     use tower_cookies::CookieManagerLayer;
     use crate::middleware::compression::create_compression_layer;
+    use crate::middleware::security_headers::security_headers;
+    use crate::errors::AppError;
 
-    let app = Router::new()
-        .route("/", get(|| async { axum::response::Redirect::to("/login") }))
+    let config = app_state.config.clone();
+    let metrics = app_state.metrics.clone();
+    let csrf_state = app_state.clone();
+    let headers_state = app_state.clone();
+    // Routes that are part of the HTTP API, and the OpenAPI document built from them
+    let api = routes::openapi::ApiRouter::new()
         .merge(frogol_routes())
-        .merge(auth_routes())
         .merge(dashboard_routes())
         .merge(routes::lead::lead_routes())
-        .merge(avatar_routes())
-        .nest_service("/static", ServeDir::new("static"))
-        .with_state(app_state.clone())
+        .merge(avatar_routes(&config.avatars))
+        .merge(routes::api::api_v1_routes());
+    Router::new()
+        .route("/", get(|| async { axum::response::Redirect::to("/login") }))
+        .merge(api.into_router())
+        .merge(auth_routes())
+        .merge(settings_routes())
+        .merge(routes::health::health_routes())
+        .merge(routes::metrics::metrics_routes())
+        .nest_service("/static/avatars", ServeDir::new(&config.avatars.dir))
+        .nest_service("/static", ServeDir::new(&config.server.static_dir))
+        .fallback(|| async { AppError::NotFound("Page not found".to_string()) })
+        .with_state(app_state)
+        .layer(axum::middleware::from_fn_with_state(csrf_state, crate::middleware::csrf::csrf_protect))
+        .layer(axum::middleware::from_fn_with_state(metrics, crate::middleware::metrics::track_requests))
+        .layer(axum::middleware::from_fn(crate::middleware::error_format::negotiate_errors))
+        .layer(axum::middleware::from_fn_with_state(headers_state, security_headers))
+        .layer(axum::middleware::from_fn(crate::middleware::request_id::assign_request_id))
         .layer(CookieManagerLayer::new())
-        .layer(create_compression_layer());
-
-    // CSRF middleware is available but not globally wired to avoid breaking behavior.
-    // HTMX is already configured to include X-CSRF-Token in requests; wire middleware per-route later if needed.
-
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
-    tracing::info!("Frogolio server starting on {}", addr);
-    let listener = tokio::net::TcpListener::bind(addr).await.expect("Failed to bind to address");
-    axum::serve(listener, app).await.expect("Failed to start server");
+        .layer(create_compression_layer(&config.compression))
 }
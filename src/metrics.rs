@@ -0,0 +1,93 @@
+//! Prometheus metrics, rendered in the text exposition format at `/metrics`.
+//!
+//! Each `AppState` owns its own registry rather than using the global one, so
+//! parallel tests never see each other's counts.
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use sqlx::SqlitePool;
+
+pub struct Metrics {
+    registry: Registry,
+    pool: SqlitePool,
+    pub http_requests: IntCounterVec,
+    pub http_request_duration: HistogramVec,
+    pool_connections: IntGauge,
+    pool_idle_connections: IntGauge,
+    pool_max_connections: IntGauge,
+    pub frogols_created: IntCounter,
+    pub link_clicks: IntCounter,
+    pub bot_clicks: IntCounterVec,
+    pub leads_captured: IntCounter,
+    pub avatar_uploads: IntCounter,
+}
+
+impl Metrics {
+    pub fn new(pool: SqlitePool) -> Self {
+        let registry = Registry::new_custom(Some("frogolio".to_string()), None).expect("valid metric prefix");
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled, by matched route and status"),
+            &["method", "route", "status"],
+        )
+        .expect("valid metric");
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "Time to produce a response, by matched route"),
+            &["method", "route"],
+        )
+        .expect("valid metric");
+        let bot_clicks = IntCounterVec::new(
+            Opts::new("bot_clicks_total", "Link clicks flagged as bots, by detection signal"),
+            &["signal"],
+        )
+        .expect("valid metric");
+        let gauge = |name: &str, help: &str| IntGauge::new(name, help).expect("valid metric");
+        let counter = |name: &str, help: &str| IntCounter::new(name, help).expect("valid metric");
+
+        let metrics = Self {
+            registry,
+            pool,
+            http_requests,
+            http_request_duration,
+            pool_connections: gauge("db_pool_connections", "Open SQLite connections, idle or in use"),
+            pool_idle_connections: gauge("db_pool_idle_connections", "Open SQLite connections not in use"),
+            pool_max_connections: gauge("db_pool_max_connections", "Most SQLite connections the pool will open"),
+            frogols_created: counter("frogols_created_total", "Frogols created"),
+            link_clicks: counter("link_clicks_total", "Link clicks tracked"),
+            bot_clicks,
+            leads_captured: counter("leads_captured_total", "Leads captured"),
+            avatar_uploads: counter("avatar_uploads_total", "Avatar images uploaded"),
+        };
+
+        let collectors: [Box<dyn prometheus::core::Collector>; 10] = [
+            Box::new(metrics.http_requests.clone()),
+            Box::new(metrics.http_request_duration.clone()),
+            Box::new(metrics.pool_connections.clone()),
+            Box::new(metrics.pool_idle_connections.clone()),
+            Box::new(metrics.pool_max_connections.clone()),
+            Box::new(metrics.frogols_created.clone()),
+            Box::new(metrics.link_clicks.clone()),
+            Box::new(metrics.bot_clicks.clone()),
+            Box::new(metrics.leads_captured.clone()),
+            Box::new(metrics.avatar_uploads.clone()),
+        ];
+        for collector in collectors {
+            metrics.registry.register(collector).expect("metric registered once");
+        }
+        metrics
+    }
+
+    /// Every metric in the Prometheus text format, with pool gauges read now.
+    pub fn render(&self) -> String {
+        self.pool_connections.set(i64::from(self.pool.size()));
+        self.pool_idle_connections.set(self.pool.num_idle() as i64);
+        self.pool_max_connections.set(i64::from(self.pool.options().get_max_connections()));
+
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding cannot fail");
+        String::from_utf8(buffer).expect("text format is UTF-8")
+    }
+}
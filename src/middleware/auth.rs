@@ -0,0 +1,94 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderMap},
+};
+use tower_cookies::Cookies;
+
+use crate::{
+    errors::AppError,
+    middleware::request_id::record_user_id,
+    repo::user_repo::User,
+    services::api_token_service::ApiScope,
+    state::AppState,
+};
+
+/// Name of the cookie carrying the session JWT set by `routes::auth::login`.
+pub const AUTH_COOKIE: &str = "auth_token";
+
+/// Extractor for the signed-in user, resolved from the `auth_token` cookie via
+/// `AuthService::validate_token`.
+///
+/// Rejects with `AppError::Unauthorized` (401) when the cookie is missing or the
+/// session is invalid. Page handlers that should redirect to `/login` instead can
+/// take `Option<AuthUser>`.
+pub struct AuthUser(pub User);
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let cookies = Cookies::from_request_parts(parts, state)
+            .await
+            .map_err(|(_, msg)| AppError::Internal(msg.to_string()))?;
+
+        let token = cookies
+            .get(AUTH_COOKIE)
+            .map(|c| c.value().to_string())
+            .ok_or_else(|| AppError::Unauthorized("Authentication required".to_string()))?;
+
+        let user = state.services.auth.validate_token(&token).await?;
+        record_user_id(&user.id);
+        Ok(AuthUser(user))
+    }
+}
+
+/// The token from an `Authorization: Bearer` header, if the request has one.
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+}
+
+/// Extractor for JSON API callers: a personal access token in an
+/// `Authorization: Bearer` header, or else the `auth_token` cookie.
+///
+/// Handlers call `require` with the scope they need. A request that sends an
+/// `Authorization` header is judged on that header alone, never on cookies.
+pub struct ApiUser {
+    pub user: User,
+    /// `None` for cookie sessions, which may use every scope.
+    scopes: Option<Vec<ApiScope>>,
+}
+
+impl ApiUser {
+    /// The caller, if allowed to use `scope`; `AppError::Forbidden` otherwise.
+    pub fn require(&self, scope: ApiScope) -> Result<&User, AppError> {
+        match &self.scopes {
+            Some(scopes) if !scopes.contains(&scope) => Err(AppError::Forbidden(format!(
+                "This token lacks the {} scope",
+                scope.as_str()
+            ))),
+            _ => Ok(&self.user),
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for ApiUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        if parts.headers.contains_key(header::AUTHORIZATION) {
+            let token = bearer_token(&parts.headers)
+                .ok_or_else(|| AppError::Unauthorized("Expected a Bearer token".to_string()))?;
+            let (user, scopes) = state.services.api_tokens.authenticate(token).await?;
+            record_user_id(&user.id);
+            return Ok(ApiUser { user, scopes: Some(scopes) });
+        }
+
+        let AuthUser(user) = AuthUser::from_request_parts(parts, state).await?;
+        Ok(ApiUser { user, scopes: None })
+    }
+}
@@ -0,0 +1,103 @@
+//! The address of the browser behind a request.
+//!
+//! The socket peer is the client unless it is one of `proxy.trusted_proxies`.
+//! Then the forwarding headers are believed: RFC 7239 `Forwarded` if present,
+//! else `X-Forwarded-For`, else `X-Real-IP`. Their hops are read from the
+//! nearest back, skipping further trusted proxies, so a client cannot pass
+//! for someone else by sending a header of its own.
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{request::Parts, HeaderMap},
+};
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::{errors::AppError, state::AppState};
+
+/// The client's address, if known. Unknown when the server was not started
+/// with connect info and no trusted proxy named the client.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+#[async_trait]
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        Ok(ClientIp(client_ip(peer, &parts.headers, &state.config.proxy.trusted_proxies)))
+    }
+}
+
+/// Reads an address as proxies write it: `192.0.2.1`, `192.0.2.1:443`,
+/// `2001:db8::1` or `[2001:db8::1]:443`, possibly quoted.
+fn parse_hop(value: &str) -> Option<IpAddr> {
+    let value = value.trim().trim_matches('"');
+    if let Ok(ip) = value.parse::<IpAddr>() {
+        return Some(ip);
+    }
+    if let Ok(addr) = value.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+    // Bracketed IPv6 without a port
+    value.strip_prefix('[')?.strip_suffix(']')?.parse().ok()
+}
+
+/// Hops in the order they were added, client first. `None` marks one the
+/// proxy could not or would not name, such as `for=unknown`.
+fn forwarded_hops(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let values = |name: &str| -> Vec<String> {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(str::to_string)
+            .collect()
+    };
+
+    let forwarded = values("forwarded");
+    if !forwarded.is_empty() {
+        return forwarded
+            .iter()
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find(|(key, _)| key.trim().eq_ignore_ascii_case("for"))
+                    .and_then(|(_, value)| parse_hop(value))
+            })
+            .collect();
+    }
+    let forwarded_for = values("x-forwarded-for");
+    if !forwarded_for.is_empty() {
+        return forwarded_for.iter().map(|hop| parse_hop(hop)).collect();
+    }
+    values("x-real-ip").iter().take(1).map(|hop| parse_hop(hop)).collect()
+}
+
+/// The client behind `peer`, trusting the forwarding headers of `trusted`
+/// proxies only.
+pub fn client_ip(peer: Option<IpAddr>, headers: &HeaderMap, trusted: &[IpNet]) -> Option<IpAddr> {
+    // A dual-stack listener reports IPv4 peers as `::ffff:a.b.c.d`
+    let is_trusted = |ip: &IpAddr| trusted.iter().any(|net| net.contains(&ip.to_canonical()));
+
+    let mut client = peer.map(|ip| ip.to_canonical());
+    if !client.as_ref().is_some_and(is_trusted) {
+        return client;
+    }
+    for hop in forwarded_hops(headers).into_iter().rev() {
+        // An unnamed hop ends what we can know; the proxy that reported it stands in
+        let Some(hop) = hop else { break };
+        client = Some(hop.to_canonical());
+        if !is_trusted(&hop) {
+            break;
+        }
+    }
+    client
+}
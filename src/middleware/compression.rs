@@ -1,3 +1,4 @@
+use crate::config::CompressionConfig;
 use tower_http::compression::{
     CompressionLayer, 
     predicate::{SizeAbove, DefaultPredicate, NotForContentType, Predicate}
@@ -6,16 +7,18 @@ use tower_http::compression::{
 /// Configure response compression with intelligent filtering
 /// 
 /// This middleware applies gzip, deflate, and br (brotli) compression to:
-/// - Responses larger than 1KB to avoid overhead for small responses
+/// - Responses larger than `min_size_bytes` (1KB by default) to avoid overhead for small responses
 /// - Text-based content types (HTML, CSS, JS, JSON, XML, SVG)
 /// - Excludes already-compressed formats (images, videos, archives)
 /// - Client requests that support compression via Accept-Encoding header
-pub fn create_compression_layer() -> CompressionLayer<impl Predicate> {
-    CompressionLayer::new()
+///
+/// With `enabled` off every encoding is switched off and responses pass through.
+pub fn create_compression_layer(config: &CompressionConfig) -> CompressionLayer<impl Predicate> {
+    with_encodings(CompressionLayer::new(), config.enabled)
         .compress_when(
             DefaultPredicate::new()
-                // Only compress responses larger than 1KB to avoid overhead
-                .and(SizeAbove::new(1024))
+                // Only compress responses above the threshold to avoid overhead
+                .and(SizeAbove::new(config.min_size_bytes))
                 // Don't compress already-compressed formats
                 .and(NotForContentType::new("image/"))
                 .and(NotForContentType::new("video/"))
@@ -33,18 +36,23 @@ pub fn create_compression_layer() -> CompressionLayer<impl Predicate> {
 
 /// Create a more aggressive compression layer for static assets
 /// where we know the content types and can afford slightly higher CPU usage
-pub fn create_static_compression_layer() -> CompressionLayer<SizeAbove> {
-    CompressionLayer::new()
+#[allow(dead_code)]
+pub fn create_static_compression_layer(config: &CompressionConfig) -> CompressionLayer<SizeAbove> {
+    with_encodings(CompressionLayer::new(), config.enabled)
         // For static files, use lower threshold since we know they're compressible
         .compress_when(SizeAbove::new(512))
 }
 
 /// Create a lightweight compression layer for API responses
 /// Optimized for JSON and small text responses
-pub fn create_api_compression_layer() -> CompressionLayer<SizeAbove> {
-    CompressionLayer::new()
+#[allow(dead_code)]
+pub fn create_api_compression_layer(config: &CompressionConfig) -> CompressionLayer<SizeAbove> {
+    with_encodings(CompressionLayer::new(), config.enabled)
         // Higher threshold for API responses to avoid overhead
         .compress_when(SizeAbove::new(2048))
 }
 
- 
\ No newline at end of file
+/// Turns every supported encoding on or off together.
+fn with_encodings(layer: CompressionLayer, enabled: bool) -> CompressionLayer {
+    layer.gzip(enabled).deflate(enabled).br(enabled).zstd(enabled)
+}
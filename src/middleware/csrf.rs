@@ -1,56 +1,165 @@
+//! Cross-site request forgery protection for every state-changing request.
+//!
+//! Each browser gets a token bound to its session: an HMAC of the
+//! `auth_token` cookie once signed in, or of a random HttpOnly `csrf_seed`
+//! cookie before then. A forged request carries the victim's cookies but
+//! cannot read them, so it cannot produce the token. Signing in or out
+//! changes the binding, and with it the token.
+//!
+//! Unsafe requests must send the token in an `X-CSRF-Token` header (HTMX does
+//! this from the `csrf_token` cookie) or, for plain HTML forms, a `csrf_token`
+//! field; templates get the value through the `CsrfToken` extractor. Routes
+//! listed in `csrf.exempt_routes` and bearer-token requests are exempt.
+
 use axum::{
-    http::{header, HeaderValue, Request, StatusCode},
+    async_trait,
+    body::Body,
+    extract::{FromRequestParts, MatchedPath, Request, State},
+    http::{header, request::Parts, HeaderMap},
     middleware::Next,
     response::Response,
 };
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use tower_cookies::{cookie::SameSite, Cookie, Cookies};
 
-// Deprecated variant kept for reference; not used in router wiring
-// pub async fn csrf_middleware(cookies: Cookies, req: Request<axum::body::Body>, next: Next) -> Result<Response, StatusCode> { ... }
+use crate::{
+    config::CsrfConfig,
+    errors::AppError,
+    middleware::auth::{bearer_token, AUTH_COOKIE},
+    state::AppState,
+};
 
-// Axum 0.7-friendly CSRF verifier for use with `axum::middleware::from_fn`
-pub async fn csrf_verify(
-    req: Request<axum::body::Body>,
-    next: Next,
-) -> Result<Response, StatusCode> {
-    let method = req.method();
-
-    // Parse csrf_token from Cookie header if present
-    let cookie_header = req.headers().get(header::COOKIE).and_then(|h| h.to_str().ok());
-    let mut cookie_token: Option<String> = None;
-    if let Some(cookies) = cookie_header {
-        for part in cookies.split(';') {
-            let trimmed = part.trim();
-            if let Some(rest) = trimmed.strip_prefix("csrf_token=") {
-                cookie_token = Some(rest.to_string());
-                break;
-            }
-        }
+/// Holds the current token where page scripts can read it.
+pub const CSRF_COOKIE: &str = "csrf_token";
+/// Random value the token is bound to before sign-in.
+pub const CSRF_SEED_COOKIE: &str = "csrf_seed";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+pub const CSRF_FORM_FIELD: &str = "csrf_token";
+
+/// Urlencoded bodies larger than this are not searched for the form field.
+const MAX_FORM_BYTES: usize = 1024 * 1024;
+
+/// The token the current request's forms must submit, read from the cookie
+/// `csrf_protect` keeps up to date.
+#[derive(Debug, Clone)]
+pub struct CsrfToken(pub String);
+
+impl CsrfToken {
+    pub fn from_cookies(cookies: &Cookies) -> Self {
+        Self(cookies.get(CSRF_COOKIE).map(|c| c.value().to_string()).unwrap_or_default())
     }
+}
 
-    // Safe methods: ensure token cookie exists
-    if method.is_safe() {
-        let mut res = next.run(req).await;
-        if cookie_token.is_none() {
-            let token = uuid::Uuid::new_v4().to_string();
-            let set_cookie = format!("csrf_token={}; Path=/; SameSite=Lax", token);
-            res.headers_mut()
-                .append(header::SET_COOKIE, HeaderValue::from_str(&set_cookie).expect("Valid cookie string should be convertible to HeaderValue"));
-        }
-        return Ok(res);
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for CsrfToken {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let cookies = Cookies::from_request_parts(parts, state)
+            .await
+            .map_err(|(_, msg)| AppError::Internal(msg.to_string()))?;
+        Ok(Self::from_cookies(&cookies))
+    }
+}
+
+/// The token for a session whose auth cookie is `auth_token`, or for an
+/// anonymous browser whose seed cookie is `seed`.
+pub fn token_for(secret: &[u8], auth_token: Option<&str>, seed: &str) -> String {
+    let binding = match auth_token {
+        Some(token) => format!("session:{}", token),
+        None => format!("anon:{}", seed),
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(binding.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn is_exempt(config: &CsrfConfig, parts: &Parts) -> bool {
+    if config.exempt_bearer_tokens && bearer_token(&parts.headers).is_some() {
+        return true;
     }
+    parts
+        .extensions
+        .get::<MatchedPath>()
+        .is_some_and(|path| config.exempt_routes.iter().any(|route| route == path.as_str()))
+}
 
-    // Unsafe methods: require header token and match cookie token
-    let header_token = req
-        .headers()
-        .get("x-csrf-token")
+fn is_form(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
         .and_then(|v| v.to_str().ok())
-        .ok_or(StatusCode::BAD_REQUEST)?;
+        .is_some_and(|ct| ct.starts_with("application/x-www-form-urlencoded"))
+}
+
+/// Compares without stopping at the first difference.
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
 
-    if cookie_token.as_deref() != Some(header_token) {
-        return Err(StatusCode::BAD_REQUEST);
+/// Finds the token in the header or, failing that, the form body, which is
+/// buffered and handed back for the handler.
+async fn submitted_token(parts: &Parts, body: Body) -> Result<(Option<String>, Body), AppError> {
+    if let Some(token) = parts.headers.get(CSRF_HEADER).and_then(|v| v.to_str().ok()) {
+        return Ok((Some(token.to_string()), body));
+    }
+    if !is_form(&parts.headers) {
+        return Ok((None, body));
     }
 
-    Ok(next.run(req).await)
+    let bytes = axum::body::to_bytes(body, MAX_FORM_BYTES)
+        .await
+        .map_err(|_| AppError::PayloadTooLarge("Form is too large".to_string()))?;
+    let token = serde_urlencoded::from_bytes::<Vec<(String, String)>>(&bytes)
+        .ok()
+        .and_then(|fields| fields.into_iter().find(|(name, _)| name == CSRF_FORM_FIELD))
+        .map(|(_, value)| value);
+    Ok((token, Body::from(bytes)))
 }
 
-// Note: custom Header implementation removed; we directly read string headers
+pub async fn csrf_protect(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let config = &state.config.csrf;
+
+    let seed = match cookies.get(CSRF_SEED_COOKIE) {
+        Some(cookie) => cookie.value().to_string(),
+        None => {
+            let mut bytes = [0u8; 16];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            let seed = hex::encode(bytes);
+            let mut cookie = Cookie::new(CSRF_SEED_COOKIE, seed.clone());
+            cookie.set_http_only(true);
+            cookie.set_path("/");
+            cookie.set_same_site(SameSite::Lax);
+            cookies.add(cookie);
+            seed
+        }
+    };
+    let auth_token = cookies.get(AUTH_COOKIE).map(|c| c.value().to_string());
+    let expected = token_for(&config.secret, auth_token.as_deref(), &seed);
+
+    if cookies.get(CSRF_COOKIE).is_none_or(|c| c.value() != expected) {
+        // Readable by page scripts, which copy it into the header
+        let mut cookie = Cookie::new(CSRF_COOKIE, expected.clone());
+        cookie.set_path("/");
+        cookie.set_same_site(SameSite::Lax);
+        cookies.add(cookie);
+    }
+
+    let (parts, body) = req.into_parts();
+    if parts.method.is_safe() || is_exempt(config, &parts) {
+        return Ok(next.run(Request::from_parts(parts, body)).await);
+    }
+
+    let (given, body) = submitted_token(&parts, body).await?;
+    if !given.is_some_and(|given| tokens_match(&given, &expected)) {
+        return Err(AppError::Forbidden("Missing or invalid CSRF token".to_string()));
+    }
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
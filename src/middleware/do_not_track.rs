@@ -0,0 +1,28 @@
+//! The visitor's request not to be tracked: `DNT: 1` or the Global Privacy
+//! Control's `Sec-GPC: 1`.
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{request::Parts, HeaderMap},
+};
+use std::convert::Infallible;
+
+/// Whether the request asked not to be tracked.
+#[derive(Debug, Clone, Copy)]
+pub struct DoNotTrack(pub bool);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for DoNotTrack {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(DoNotTrack(opted_out(&parts.headers)))
+    }
+}
+
+pub fn opted_out(headers: &HeaderMap) -> bool {
+    ["dnt", "sec-gpc"]
+        .iter()
+        .any(|name| headers.get(*name).and_then(|v| v.to_str().ok()).is_some_and(|v| v.trim() == "1"))
+}
@@ -0,0 +1,136 @@
+//! Content negotiation for error responses.
+//!
+//! `AppError` always answers with a JSON `ErrorBody` and stashes a copy in the
+//! response extensions. This middleware swaps that body for an HTML fragment
+//! when HTMX made the request, or for a full error page when a browser asked
+//! for HTML. The versioned API under `/api/v1` always keeps JSON. Server
+//! errors show the request id so support can find the matching log line.
+
+use askama::Template;
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{
+    errors::ErrorBody,
+    middleware::{request_id::RequestId, security_headers::CspNonce},
+};
+
+#[derive(Template)]
+#[template(path = "errors/404.html")]
+struct NotFoundPageTemplate<'a> {
+    csp_nonce: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "errors/410.html")]
+struct GonePageTemplate<'a> {
+    csp_nonce: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "errors/500.html")]
+struct ServerErrorPageTemplate<'a> {
+    status: u16,
+    request_id: &'a str,
+    csp_nonce: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "errors/error.html")]
+struct ErrorPageTemplate<'a> {
+    status: u16,
+    title: &'a str,
+    error: &'a ErrorBody,
+    csp_nonce: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "partials/error.html")]
+struct ErrorFragmentTemplate<'a> {
+    error: &'a ErrorBody,
+    /// Only set for server errors, where support may need it.
+    request_id: &'a str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorFormat {
+    Json,
+    Page,
+    Fragment,
+}
+
+fn preferred_format(path: &str, headers: &HeaderMap) -> ErrorFormat {
+    if path.starts_with("/api/v1/") || path == "/api/openapi.json" {
+        return ErrorFormat::Json;
+    }
+    if headers.get("hx-request").is_some() {
+        return ErrorFormat::Fragment;
+    }
+    let accept = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if accept.contains("text/html") {
+        ErrorFormat::Page
+    } else {
+        ErrorFormat::Json
+    }
+}
+
+pub async fn negotiate_errors(req: Request, next: Next) -> Response {
+    let format = preferred_format(req.uri().path(), req.headers());
+    let request_id = req.extensions().get::<RequestId>().cloned();
+    let csp_nonce = req.extensions().get::<CspNonce>().map(|CspNonce(nonce)| nonce.clone()).unwrap_or_default();
+    let res = next.run(req).await;
+    if format == ErrorFormat::Json {
+        return res;
+    }
+    let Some(error) = res.extensions().get::<ErrorBody>().cloned() else {
+        return res;
+    };
+
+    let (mut parts, _) = res.into_parts();
+    let status = parts.status;
+    let request_id = match &request_id {
+        Some(RequestId(id)) if status.is_server_error() => id.as_str(),
+        _ => "",
+    };
+    let html = match format {
+        ErrorFormat::Page => match status {
+            StatusCode::NOT_FOUND => NotFoundPageTemplate { csp_nonce: &csp_nonce }.render(),
+            StatusCode::GONE => GonePageTemplate { csp_nonce: &csp_nonce }.render(),
+            _ if status.is_server_error() => {
+                ServerErrorPageTemplate { status: status.as_u16(), request_id, csp_nonce: &csp_nonce }.render()
+            }
+            _ => ErrorPageTemplate {
+                status: status.as_u16(),
+                title: status.canonical_reason().unwrap_or("Error"),
+                error: &error,
+                csp_nonce: &csp_nonce,
+            }
+            .render(),
+        },
+        _ => {
+            // HTMX swaps the fragment into the page-wide error slot in base.html
+            parts.headers.insert("hx-retarget", HeaderValue::from_static("#htmx-errors"));
+            parts.headers.insert("hx-reswap", HeaderValue::from_static("innerHTML"));
+            ErrorFragmentTemplate { error: &error, request_id }.render()
+        }
+    };
+    let (content_type, body) = match html {
+        Ok(html) => ("text/html; charset=utf-8", html),
+        Err(e) => {
+            tracing::error!("Failed to render error page: {}", e);
+            ("text/plain; charset=utf-8", error.error)
+        }
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    Response::from_parts(parts, Body::from(body))
+}
@@ -0,0 +1,50 @@
+//! Counts every request and times its response, labelled by the route
+//! template (`/api/v1/frogols/:id`) rather than the raw path so the number of
+//! series stays bounded. Requests no route matched share the `unmatched` label,
+//! and methods outside the standard set share `other`.
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::metrics::Metrics;
+
+/// The label for `method`. Clients may send any token as a method.
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::HEAD => "HEAD",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::PATCH => "PATCH",
+        Method::DELETE => "DELETE",
+        Method::OPTIONS => "OPTIONS",
+        _ => "other",
+    }
+}
+
+pub async fn track_requests(State(metrics): State<Arc<Metrics>>, req: Request, next: Next) -> Response {
+    let method = method_label(req.method());
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let started = Instant::now();
+    let res = next.run(req).await;
+    metrics
+        .http_request_duration
+        .with_label_values(&[method, &route])
+        .observe(started.elapsed().as_secs_f64());
+    metrics
+        .http_requests
+        .with_label_values(&[method, &route, res.status().as_str()])
+        .inc();
+    res
+}
@@ -1,2 +1,9 @@
 pub mod csrf;
 pub mod compression;
+pub mod auth;
+pub mod error_format;
+pub mod request_id;
+pub mod metrics;
+pub mod security_headers;
+pub mod client_ip;
+pub mod do_not_track;
@@ -0,0 +1,80 @@
+//! Gives every request an id, echoed back in `X-Request-Id` and recorded on a
+//! tracing span so every log line it causes can be found again. An id sent by
+//! a proxy in front of us is kept if it looks sane.
+//!
+//! The span also carries the method and matched route, and the user and
+//! frogol ids once something in the request has resolved them; see
+//! `record_user_id` and `record_frogol_id`.
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use std::time::Instant;
+use tracing::{field::Empty, Instrument, Span};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// The current request's id, available as a request extension.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// Ids from upstream are echoed into headers and logs, so keep them short and plain.
+fn is_acceptable(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Notes the signed-in user on the current request's span.
+pub fn record_user_id(user_id: &str) {
+    Span::current().record("user_id", user_id);
+}
+
+/// Notes the frogol the current request is about on its span.
+pub fn record_frogol_id(frogol_id: &str) {
+    Span::current().record("frogol_id", frogol_id);
+}
+
+pub async fn assign_request_id(mut req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| is_acceptable(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    req.extensions_mut().insert(RequestId(id.clone()));
+
+    // The route template rather than the raw path, so ids in URLs stay out of it
+    let path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str())
+        .unwrap_or("unmatched");
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        method = %req.method(),
+        path,
+        user_id = Empty,
+        frogol_id = Empty,
+    );
+
+    let started = Instant::now();
+    let mut res = next.run(req).instrument(span.clone()).await;
+    span.in_scope(|| {
+        tracing::debug!(
+            status = res.status().as_u16(),
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "Finished request"
+        )
+    });
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        res.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    res
+}
@@ -0,0 +1,111 @@
+//! Browser security headers for every response.
+//!
+//! Public frogol pages show names, bios, links and avatars their owners
+//! typed in, so the Content-Security-Policy only runs scripts from our CDNs
+//! and inline scripts carrying this request's nonce. Templates get the nonce
+//! through the `CspNonce` extractor and put it on each `<script>` tag.
+//!
+//! Routes are split into two groups with their own `HeaderPolicy`: the
+//! public pages visitors see, and everything else. Headers a handler has
+//! already set are left alone.
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, MatchedPath, Request, State},
+    http::{header, request::Parts, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use rand::RngCore;
+
+use crate::{
+    config::{HeaderPolicy, SecurityHeadersConfig},
+    errors::AppError,
+    state::AppState,
+};
+
+/// Routes visitors reach from a published page, under the `public` policy.
+const PUBLIC_ROUTES: &[&str] = &["/:slug", "/api/links/:id/click", "/api/lead/:frogol_id"];
+
+/// Where our own page scripts are loaded from, besides this site.
+const SCRIPT_HOSTS: &str = "https://cdn.tailwindcss.com https://unpkg.com https://cdn.jsdelivr.net";
+
+const PERMISSIONS_POLICY: &str = "camera=(), microphone=(), geolocation=(), payment=(), usb=()";
+
+/// This request's CSP nonce, for the `nonce` attribute of inline scripts.
+#[derive(Debug, Clone)]
+pub struct CspNonce(pub String);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for CspNonce {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<CspNonce>()
+            .cloned()
+            .ok_or_else(|| AppError::Internal("CSP nonce missing; is security_headers installed?".to_string()))
+    }
+}
+
+fn policy_for<'a>(config: &'a SecurityHeadersConfig, path: Option<&MatchedPath>) -> &'a HeaderPolicy {
+    match path {
+        Some(path) if PUBLIC_ROUTES.contains(&path.as_str()) => &config.public,
+        _ => &config.dashboard,
+    }
+}
+
+/// The Content-Security-Policy for a page rendered with `nonce`.
+fn content_security_policy(policy: &HeaderPolicy, nonce: &str) -> String {
+    format!(
+        "default-src 'self'; script-src 'self' 'nonce-{}' {}; style-src 'self' 'unsafe-inline'; img-src {}; \
+         connect-src 'self'; object-src 'none'; base-uri 'self'; form-action 'self'; frame-ancestors {}",
+        nonce,
+        SCRIPT_HOSTS,
+        policy.img_src.join(" "),
+        policy.frame_ancestors.join(" "),
+    )
+}
+
+/// The `X-Frame-Options` equivalent of `frame_ancestors`, for browsers
+/// without CSP. There is none for a list of other sites.
+fn frame_options(frame_ancestors: &[String]) -> Option<&'static str> {
+    match frame_ancestors {
+        [only] if only == "'none'" => Some("DENY"),
+        [only] if only == "'self'" => Some("SAMEORIGIN"),
+        _ => None,
+    }
+}
+
+pub async fn security_headers(State(state): State<AppState>, mut req: Request, next: Next) -> Response {
+    let config = &state.config.security_headers;
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let nonce = hex::encode(bytes);
+    let policy = policy_for(config, req.extensions().get::<MatchedPath>());
+    req.extensions_mut().insert(CspNonce(nonce.clone()));
+
+    let mut res = next.run(req).await;
+
+    let mut headers = vec![
+        (header::CONTENT_SECURITY_POLICY, content_security_policy(policy, &nonce)),
+        (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        (header::REFERRER_POLICY, policy.referrer_policy.clone()),
+        (HeaderName::from_static("permissions-policy"), PERMISSIONS_POLICY.to_string()),
+    ];
+    if let Some(value) = frame_options(&policy.frame_ancestors) {
+        headers.push((header::X_FRAME_OPTIONS, value.to_string()));
+    }
+    // Only promise HTTPS where we are actually served over it
+    if config.hsts_max_age_secs > 0 && state.config.auth.base_url.starts_with("https://") {
+        headers.push((header::STRICT_TRANSPORT_SECURITY, format!("max-age={}", config.hsts_max_age_secs)));
+    }
+
+    for (name, value) in headers {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            res.headers_mut().entry(name).or_insert(value);
+        }
+    }
+    res
+}
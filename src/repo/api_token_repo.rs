@@ -0,0 +1,161 @@
+use sqlx::SqlitePool;
+use crate::errors::AppError;
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub prefix: String,
+    /// Space-separated scope names.
+    pub scopes: String,
+    pub last_used_at: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug)]
+pub struct NewApiToken {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub prefix: String,
+    pub token_hash: String,
+    pub scopes: String,
+}
+
+#[derive(Debug)]
+pub struct ApiTokenRepo {
+    pool: SqlitePool,
+}
+
+impl ApiTokenRepo {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, new_token: NewApiToken) -> Result<ApiToken, AppError> {
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO api_tokens (id, user_id, name, prefix, token_hash, scopes)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            RETURNING
+                id         as "id!: String",
+                user_id    as "user_id!: String",
+                name       as "name!: String",
+                prefix     as "prefix!: String",
+                scopes     as "scopes!: String",
+                last_used_at,
+                created_at as "created_at!: String"
+            "#,
+            new_token.id,
+            new_token.user_id,
+            new_token.name,
+            new_token.prefix,
+            new_token.token_hash,
+            new_token.scopes
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(ApiToken {
+            id: row.id,
+            user_id: row.user_id,
+            name: row.name,
+            prefix: row.prefix,
+            scopes: row.scopes,
+            last_used_at: row.last_used_at,
+            created_at: row.created_at,
+        })
+    }
+
+    pub async fn get_by_hash(&self, token_hash: &str) -> Result<Option<ApiToken>, AppError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                id         as "id!: String",
+                user_id    as "user_id!: String",
+                name       as "name!: String",
+                prefix     as "prefix!: String",
+                scopes     as "scopes!: String",
+                last_used_at,
+                created_at as "created_at!: String"
+            FROM api_tokens
+            WHERE token_hash = ?1
+            "#,
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| ApiToken {
+            id: r.id,
+            user_id: r.user_id,
+            name: r.name,
+            prefix: r.prefix,
+            scopes: r.scopes,
+            last_used_at: r.last_used_at,
+            created_at: r.created_at,
+        }))
+    }
+
+    /// The user's tokens, newest first.
+    pub async fn list_for_user(&self, user_id: &str) -> Result<Vec<ApiToken>, AppError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                id         as "id!: String",
+                user_id    as "user_id!: String",
+                name       as "name!: String",
+                prefix     as "prefix!: String",
+                scopes     as "scopes!: String",
+                last_used_at,
+                created_at as "created_at!: String"
+            FROM api_tokens
+            WHERE user_id = ?1
+            ORDER BY created_at DESC
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| ApiToken {
+                id: r.id,
+                user_id: r.user_id,
+                name: r.name,
+                prefix: r.prefix,
+                scopes: r.scopes,
+                last_used_at: r.last_used_at,
+                created_at: r.created_at,
+            })
+            .collect())
+    }
+
+    pub async fn touch(&self, id: &str, last_used_at: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE api_tokens SET last_used_at = ?1 WHERE id = ?2",
+            last_used_at,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deletes one of the user's tokens; false if it was not theirs or is already gone.
+    pub async fn delete(&self, user_id: &str, id: &str) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            "DELETE FROM api_tokens WHERE id = ?1 AND user_id = ?2",
+            id,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -6,8 +6,15 @@ use sqlx::SqlitePool;
 pub struct Click {
     pub id: String,
     pub link_id: String,
+    /// The visitor's network, see `visitor_service`.
     pub ip_address: Option<String>,
-    pub user_agent: Option<String>,
+    pub visitor_id: Option<String>,
+    /// `mobile`, `tablet`, `desktop`, `bot` or `unknown`; see `user_agent`.
+    pub device_class: Option<String>,
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub is_bot: bool,
+    pub created_at: String,
 }
 
 #[derive(Debug)]
@@ -15,7 +22,21 @@ pub struct NewClick {
     pub id: String,
     pub link_id: String,
     pub ip_address: Option<String>,
-    pub user_agent: Option<String>,
+    pub visitor_id: Option<String>,
+    pub device_class: Option<String>,
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub is_bot: bool,
+    /// RFC 3339, see `auth_service::timestamp`.
+    pub created_at: String,
+}
+
+/// Whose clicks a series or breakdown counts.
+#[derive(Debug, Clone, Copy)]
+pub enum ClickScope<'a> {
+    User(&'a str),
+    Frogol(&'a str),
+    Link(&'a str),
 }
 
 #[derive(Debug)]
@@ -28,55 +49,91 @@ impl ClickRepo {
         Self { pool }
     }
 
+    /// Records a click and counts it in its day's rollup, with the bot clicks
+    /// if a bot made it.
     pub async fn record_click(&self, new_click: NewClick) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+
         sqlx::query!(
             r#"
-            INSERT INTO clicks (id, link_id, ip_address, user_agent)
-            VALUES (?1, ?2, ?3, ?4)
+            INSERT INTO clicks (id, link_id, ip_address, visitor_id, device_class, browser, os, is_bot, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
             "#,
             new_click.id,
             new_click.link_id,
             new_click.ip_address,
-            new_click.user_agent
+            new_click.visitor_id,
+            new_click.device_class,
+            new_click.browser,
+            new_click.os,
+            new_click.is_bot,
+            new_click.created_at
         )
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
-        Ok(())
-    }
+        let day = &new_click.created_at[..10];
+        let (clicks, bot_clicks) = if new_click.is_bot { (0, 1) } else { (1, 0) };
+        sqlx::query!(
+            r#"
+            INSERT INTO click_daily_rollups (link_id, day, clicks, bot_clicks)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (link_id, day) DO UPDATE
+            SET clicks = clicks + excluded.clicks, bot_clicks = bot_clicks + excluded.bot_clicks
+            "#,
+            new_click.link_id,
+            day,
+            clicks,
+            bot_clicks
+        )
+        .execute(&mut *tx)
+        .await?;
 
-    pub async fn track_click(&self, link_id: &str, ip_address: Option<String>, user_agent: Option<String>) -> Result<(), AppError> {
-        let click_id = uuid::Uuid::new_v4().to_string();
-        let new_click = NewClick {
-            id: click_id,
-            link_id: link_id.to_string(),
-            ip_address,
-            user_agent,
-        };
-        self.record_click(new_click).await
+        let hour = &new_click.created_at[..13];
+        sqlx::query!(
+            r#"
+            INSERT INTO click_hourly_rollups (link_id, hour, clicks, bot_clicks)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (link_id, hour) DO UPDATE
+            SET clicks = clicks + excluded.clicks, bot_clicks = bot_clicks + excluded.bot_clicks
+            "#,
+            new_click.link_id,
+            hour,
+            clicks,
+            bot_clicks
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
     }
 
-    pub async fn get_frogol_click_stats(&self, frogol_id: &str) -> Result<ClickStats, AppError> {
+    /// Bot clicks are left out unless `include_bots`, here and in every count below.
+    pub async fn get_frogol_click_stats(&self, frogol_id: &str, include_bots: bool) -> Result<ClickStats, AppError> {
         let total_clicks = sqlx::query_scalar!(
             r#"
             SELECT COUNT(*)
             FROM clicks c
             JOIN links l ON c.link_id = l.id
-            WHERE l.frogol_id = ?1
+            WHERE l.frogol_id = ?1 AND (?2 OR c.is_bot = 0)
             "#,
-            frogol_id
+            frogol_id,
+            include_bots
         )
         .fetch_one(&self.pool)
         .await?;
 
+        // Visitor ids change daily, so this sums each day's unique visitors
         let unique_clicks = sqlx::query_scalar!(
             r#"
-            SELECT COUNT(DISTINCT c.ip_address)
+            SELECT COUNT(DISTINCT c.visitor_id)
             FROM clicks c
             JOIN links l ON c.link_id = l.id
-            WHERE l.frogol_id = ?1 AND c.ip_address IS NOT NULL
+            WHERE l.frogol_id = ?1 AND c.visitor_id IS NOT NULL AND (?2 OR c.is_bot = 0)
             "#,
-            frogol_id
+            frogol_id,
+            include_bots
         )
         .fetch_one(&self.pool)
         .await?;
@@ -87,16 +144,17 @@ impl ClickRepo {
         })
     }
 
-    pub async fn get_user_total_clicks(&self, user_id: &str) -> Result<i64, AppError> {
+    pub async fn get_user_total_clicks(&self, user_id: &str, include_bots: bool) -> Result<i64, AppError> {
         let count = sqlx::query_scalar!(
             r#"
             SELECT COUNT(*)
             FROM clicks c
             JOIN links l ON c.link_id = l.id
             JOIN frogols f ON l.frogol_id = f.id
-            WHERE f.user_id = ?1
+            WHERE f.user_id = ?1 AND (?2 OR c.is_bot = 0)
             "#,
-            user_id
+            user_id,
+            include_bots
         )
         .fetch_one(&self.pool)
         .await?;
@@ -104,22 +162,446 @@ impl ClickRepo {
         Ok(count)
     }
 
-    pub async fn get_clicks_by_link(&self, frogol_id: &str) -> Result<Vec<(String, i64)>, AppError> {
+    /// One page of a link's clicks, newest first.
+    pub async fn list_link_clicks(
+        &self,
+        link_id: &str,
+        include_bots: bool,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Click>, AppError> {
+        let clicks = sqlx::query_as!(
+            Click,
+            r#"
+            SELECT
+                id         as "id!: String",
+                link_id    as "link_id!: String",
+                ip_address,
+                visitor_id,
+                device_class,
+                browser,
+                os,
+                is_bot     as "is_bot!: bool",
+                created_at as "created_at!: String"
+            FROM clicks
+            WHERE link_id = ?1 AND (?2 OR is_bot = 0)
+            ORDER BY created_at DESC, id
+            LIMIT ?3 OFFSET ?4
+            "#,
+            link_id,
+            include_bots,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(clicks)
+    }
+
+    pub async fn count_link_clicks(&self, link_id: &str, include_bots: bool) -> Result<i64, AppError> {
+        let count = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) FROM clicks WHERE link_id = ?1 AND (?2 OR is_bot = 0)"#,
+            link_id,
+            include_bots
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    pub async fn get_clicks_by_link(&self, frogol_id: &str, include_bots: bool) -> Result<Vec<(String, i64)>, AppError> {
         let rows = sqlx::query!(
             r#"
             SELECT l.id as "link_id!: String", COUNT(c.id) as "clicks!: i64"
             FROM links l
-            LEFT JOIN clicks c ON c.link_id = l.id
+            LEFT JOIN clicks c ON c.link_id = l.id AND (?2 OR c.is_bot = 0)
             WHERE l.frogol_id = ?1
             GROUP BY l.id
             "#,
-            frogol_id
+            frogol_id,
+            include_bots
         )
         .fetch_all(&self.pool)
         .await?;
 
         Ok(rows.into_iter().map(|r| (r.link_id, r.clicks)).collect())
     }
+
+    /// Clicks per UTC day (`YYYY-MM-DD`) from the rollups, for days `from` to
+    /// `to` inclusive. Days without clicks are left out.
+    pub async fn daily_click_counts(
+        &self,
+        scope: ClickScope<'_>,
+        include_bots: bool,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<(String, i64)>, AppError> {
+        let rows = match scope {
+            ClickScope::User(user_id) => sqlx::query!(
+                r#"
+                SELECT r.day as "day!: String", SUM(r.clicks + ?4 * r.bot_clicks) as "clicks!: i64"
+                FROM click_daily_rollups r
+                JOIN links l ON r.link_id = l.id
+                JOIN frogols f ON l.frogol_id = f.id
+                WHERE f.user_id = ?1 AND r.day BETWEEN ?2 AND ?3
+                GROUP BY r.day
+                "#,
+                user_id,
+                from,
+                to,
+                include_bots
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| (r.day, r.clicks))
+            .collect(),
+            ClickScope::Frogol(frogol_id) => sqlx::query!(
+                r#"
+                SELECT r.day as "day!: String", SUM(r.clicks + ?4 * r.bot_clicks) as "clicks!: i64"
+                FROM click_daily_rollups r
+                JOIN links l ON r.link_id = l.id
+                WHERE l.frogol_id = ?1 AND r.day BETWEEN ?2 AND ?3
+                GROUP BY r.day
+                "#,
+                frogol_id,
+                from,
+                to,
+                include_bots
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| (r.day, r.clicks))
+            .collect(),
+            ClickScope::Link(link_id) => sqlx::query!(
+                r#"
+                SELECT day as "day!: String", clicks + ?4 * bot_clicks as "clicks!: i64"
+                FROM click_daily_rollups
+                WHERE link_id = ?1 AND day BETWEEN ?2 AND ?3
+                "#,
+                link_id,
+                from,
+                to,
+                include_bots
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| (r.day, r.clicks))
+            .collect(),
+        };
+
+        Ok(rows)
+    }
+
+    /// Clicks from the hourly rollups for UTC hours (`YYYY-MM-DDTHH`) from
+    /// `start` up to but excluding `end`, grouped by the hour shifted by
+    /// `modifier` and formatted with `format`, as in `local_click_counts`.
+    pub async fn hourly_click_counts(
+        &self,
+        scope: ClickScope<'_>,
+        include_bots: bool,
+        format: &str,
+        modifier: &str,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<(String, i64)>, AppError> {
+        let rows = match scope {
+            ClickScope::User(user_id) => sqlx::query!(
+                r#"
+                SELECT strftime(?2, r.hour || ':00', ?3) as "bucket!: String", SUM(r.clicks + ?6 * r.bot_clicks) as "clicks!: i64"
+                FROM click_hourly_rollups r
+                JOIN links l ON r.link_id = l.id
+                JOIN frogols f ON l.frogol_id = f.id
+                WHERE f.user_id = ?1 AND r.hour >= ?4 AND r.hour < ?5
+                GROUP BY 1
+                "#,
+                user_id,
+                format,
+                modifier,
+                start,
+                end,
+                include_bots
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| (r.bucket, r.clicks))
+            .collect(),
+            ClickScope::Frogol(frogol_id) => sqlx::query!(
+                r#"
+                SELECT strftime(?2, r.hour || ':00', ?3) as "bucket!: String", SUM(r.clicks + ?6 * r.bot_clicks) as "clicks!: i64"
+                FROM click_hourly_rollups r
+                JOIN links l ON r.link_id = l.id
+                WHERE l.frogol_id = ?1 AND r.hour >= ?4 AND r.hour < ?5
+                GROUP BY 1
+                "#,
+                frogol_id,
+                format,
+                modifier,
+                start,
+                end,
+                include_bots
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| (r.bucket, r.clicks))
+            .collect(),
+            ClickScope::Link(link_id) => sqlx::query!(
+                r#"
+                SELECT strftime(?2, hour || ':00', ?3) as "bucket!: String", SUM(clicks + ?6 * bot_clicks) as "clicks!: i64"
+                FROM click_hourly_rollups
+                WHERE link_id = ?1 AND hour >= ?4 AND hour < ?5
+                GROUP BY 1
+                "#,
+                link_id,
+                format,
+                modifier,
+                start,
+                end,
+                include_bots
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| (r.bucket, r.clicks))
+            .collect(),
+        };
+
+        Ok(rows)
+    }
+
+    /// Clicks from `start` up to but excluding `end` (RFC 3339, UTC), grouped
+    /// by their time shifted by `modifier` (an SQLite date modifier such as
+    /// `+120 minutes`) and formatted with the `strftime` pattern `format`.
+    pub async fn local_click_counts(
+        &self,
+        scope: ClickScope<'_>,
+        include_bots: bool,
+        format: &str,
+        modifier: &str,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<(String, i64)>, AppError> {
+        let rows = match scope {
+            ClickScope::User(user_id) => sqlx::query!(
+                r#"
+                SELECT strftime(?2, c.created_at, ?3) as "bucket!: String", COUNT(*) as "clicks!: i64"
+                FROM clicks c
+                JOIN links l ON c.link_id = l.id
+                JOIN frogols f ON l.frogol_id = f.id
+                WHERE f.user_id = ?1 AND c.created_at >= ?4 AND c.created_at < ?5 AND (?6 OR c.is_bot = 0)
+                GROUP BY 1
+                "#,
+                user_id,
+                format,
+                modifier,
+                start,
+                end,
+                include_bots
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| (r.bucket, r.clicks))
+            .collect(),
+            ClickScope::Frogol(frogol_id) => sqlx::query!(
+                r#"
+                SELECT strftime(?2, c.created_at, ?3) as "bucket!: String", COUNT(*) as "clicks!: i64"
+                FROM clicks c
+                JOIN links l ON c.link_id = l.id
+                WHERE l.frogol_id = ?1 AND c.created_at >= ?4 AND c.created_at < ?5 AND (?6 OR c.is_bot = 0)
+                GROUP BY 1
+                "#,
+                frogol_id,
+                format,
+                modifier,
+                start,
+                end,
+                include_bots
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| (r.bucket, r.clicks))
+            .collect(),
+            ClickScope::Link(link_id) => sqlx::query!(
+                r#"
+                SELECT strftime(?2, created_at, ?3) as "bucket!: String", COUNT(*) as "clicks!: i64"
+                FROM clicks
+                WHERE link_id = ?1 AND created_at >= ?4 AND created_at < ?5 AND (?6 OR is_bot = 0)
+                GROUP BY 1
+                "#,
+                link_id,
+                format,
+                modifier,
+                start,
+                end,
+                include_bots
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| (r.bucket, r.clicks))
+            .collect(),
+        };
+
+        Ok(rows)
+    }
+
+    /// Clicks by `visitor_id` on any link since `since` (RFC 3339, UTC).
+    pub async fn count_recent_visitor_clicks(&self, visitor_id: &str, since: &str) -> Result<i64, AppError> {
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*)
+            FROM clicks
+            WHERE visitor_id = ?1 AND created_at >= ?2
+            "#,
+            visitor_id,
+            since
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Clicks by device class, browser and OS from `start` up to but
+    /// excluding `end` (RFC 3339, UTC), or over all time where `None`.
+    pub async fn click_breakdown(
+        &self,
+        scope: ClickScope<'_>,
+        start: Option<&str>,
+        end: Option<&str>,
+        include_bots: bool,
+    ) -> Result<Vec<ClickBreakdownRow>, AppError> {
+        let rows = match scope {
+            ClickScope::User(user_id) => sqlx::query_as!(
+                ClickBreakdownRow,
+                r#"
+                SELECT c.device_class, c.browser, c.os, COUNT(*) as "clicks!: i64"
+                FROM clicks c
+                JOIN links l ON c.link_id = l.id
+                JOIN frogols f ON l.frogol_id = f.id
+                WHERE f.user_id = ?1 AND (?2 IS NULL OR c.created_at >= ?2) AND (?3 IS NULL OR c.created_at < ?3)
+                    AND (?4 OR c.is_bot = 0)
+                GROUP BY c.device_class, c.browser, c.os
+                "#,
+                user_id,
+                start,
+                end,
+                include_bots
+            )
+            .fetch_all(&self.pool)
+            .await?,
+            ClickScope::Frogol(frogol_id) => sqlx::query_as!(
+                ClickBreakdownRow,
+                r#"
+                SELECT c.device_class, c.browser, c.os, COUNT(*) as "clicks!: i64"
+                FROM clicks c
+                JOIN links l ON c.link_id = l.id
+                WHERE l.frogol_id = ?1 AND (?2 IS NULL OR c.created_at >= ?2) AND (?3 IS NULL OR c.created_at < ?3)
+                    AND (?4 OR c.is_bot = 0)
+                GROUP BY c.device_class, c.browser, c.os
+                "#,
+                frogol_id,
+                start,
+                end,
+                include_bots
+            )
+            .fetch_all(&self.pool)
+            .await?,
+            ClickScope::Link(link_id) => sqlx::query_as!(
+                ClickBreakdownRow,
+                r#"
+                SELECT device_class, browser, os, COUNT(*) as "clicks!: i64"
+                FROM clicks
+                WHERE link_id = ?1 AND (?2 IS NULL OR created_at >= ?2) AND (?3 IS NULL OR created_at < ?3)
+                    AND (?4 OR is_bot = 0)
+                GROUP BY device_class, browser, os
+                "#,
+                link_id,
+                start,
+                end,
+                include_bots
+            )
+            .fetch_all(&self.pool)
+            .await?,
+        };
+
+        Ok(rows)
+    }
+
+    /// Bot clicks in `scope` from `start` up to but excluding `end`, or over
+    /// all time where `None`.
+    pub async fn count_bot_clicks(&self, scope: ClickScope<'_>, start: Option<&str>, end: Option<&str>) -> Result<i64, AppError> {
+        let count = match scope {
+            ClickScope::User(user_id) => sqlx::query_scalar!(
+                r#"
+                SELECT COUNT(*)
+                FROM clicks c
+                JOIN links l ON c.link_id = l.id
+                JOIN frogols f ON l.frogol_id = f.id
+                WHERE f.user_id = ?1 AND c.is_bot = 1 AND (?2 IS NULL OR c.created_at >= ?2) AND (?3 IS NULL OR c.created_at < ?3)
+                "#,
+                user_id,
+                start,
+                end
+            )
+            .fetch_one(&self.pool)
+            .await?,
+            ClickScope::Frogol(frogol_id) => sqlx::query_scalar!(
+                r#"
+                SELECT COUNT(*)
+                FROM clicks c
+                JOIN links l ON c.link_id = l.id
+                WHERE l.frogol_id = ?1 AND c.is_bot = 1 AND (?2 IS NULL OR c.created_at >= ?2) AND (?3 IS NULL OR c.created_at < ?3)
+                "#,
+                frogol_id,
+                start,
+                end
+            )
+            .fetch_one(&self.pool)
+            .await?,
+            ClickScope::Link(link_id) => sqlx::query_scalar!(
+                r#"
+                SELECT COUNT(*)
+                FROM clicks
+                WHERE link_id = ?1 AND is_bot = 1 AND (?2 IS NULL OR created_at >= ?2) AND (?3 IS NULL OR created_at < ?3)
+                "#,
+                link_id,
+                start,
+                end
+            )
+            .fetch_one(&self.pool)
+            .await?,
+        };
+
+        Ok(count)
+    }
+}
+
+/// Clicks sharing one device class, browser and OS.
+#[derive(Debug)]
+pub struct ClickBreakdownRow {
+    pub device_class: Option<String>,
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub clicks: i64,
+}
+
+/// Clicks by device class, browser and OS, most first, and how many bot
+/// clicks there were. The breakdowns count bots only when asked.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ClickBreakdown {
+    pub devices: Vec<(String, i64)>,
+    pub browsers: Vec<(String, i64)>,
+    pub operating_systems: Vec<(String, i64)>,
+    pub bot_clicks: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -46,7 +46,11 @@ impl FrogolRepo {
         &self.pool
     }
 
+    /// Inserts a frogol, releasing its slug from `deleted_slugs` if an earlier
+    /// frogol had it.
     pub async fn create_frogol(&self, new_frogol: NewFrogol) -> Result<Frogol, AppError> {
+        let mut tx = self.pool.begin().await?;
+
         let row = sqlx::query(
             r#"
             INSERT INTO frogols (id, user_id, slug, display_name)
@@ -58,8 +62,12 @@ impl FrogolRepo {
         .bind(&new_frogol.user_id)
         .bind(&new_frogol.slug)
         .bind(&new_frogol.display_name)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *tx)
         .await?;
+        sqlx::query!("DELETE FROM deleted_slugs WHERE slug = ?1", new_frogol.slug)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
 
         Ok(Frogol {
             id: row.try_get::<String, _>("id")?,
@@ -97,6 +105,17 @@ impl FrogolRepo {
         })
     }
 
+    /// Whether `slug` belonged to a frogol that has since been deleted.
+    pub async fn is_slug_deleted(&self, slug: &str) -> Result<bool, AppError> {
+        let deleted = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM deleted_slugs WHERE slug = ?1) as "deleted!: bool""#,
+            slug
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(deleted)
+    }
+
     pub async fn get_by_id(&self, id: &str) -> Result<Frogol, AppError> {
         let row = sqlx::query(
             r#"
@@ -122,6 +141,12 @@ impl FrogolRepo {
     }
 
     pub async fn get_user_frogols(&self, user_id: &str) -> Result<Vec<FrogolSummary>, AppError> {
+        // SQLite reads a negative LIMIT as no limit
+        self.list_user_frogols(user_id, -1, 0).await
+    }
+
+    /// One page of the user's frogols, newest first.
+    pub async fn list_user_frogols(&self, user_id: &str, limit: i64, offset: i64) -> Result<Vec<FrogolSummary>, AppError> {
         let rows = sqlx::query!(
             r#"
             SELECT 
@@ -135,12 +160,15 @@ impl FrogolRepo {
             FROM frogols f
             LEFT JOIN links l ON f.id = l.frogol_id
             LEFT JOIN leads ld ON f.id = ld.frogol_id
-            LEFT JOIN clicks c ON l.id = c.link_id
+            LEFT JOIN clicks c ON l.id = c.link_id AND c.is_bot = 0
             WHERE f.user_id = ?1
             GROUP BY f.id, f.slug, f.display_name, f.created_at
-            ORDER BY f.created_at DESC
+            ORDER BY f.created_at DESC, f.id
+            LIMIT ?2 OFFSET ?3
             "#,
-            user_id
+            user_id,
+            limit,
+            offset
         )
         .fetch_all(&self.pool)
         .await?;
@@ -157,11 +185,22 @@ impl FrogolRepo {
         }).collect())
     }
 
+    pub async fn count_user_frogols(&self, user_id: &str) -> Result<i64, AppError> {
+        let count = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) FROM frogols WHERE user_id = ?1"#,
+            user_id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
     pub async fn update_frogol(&self, id: &str, display_name: &str, theme: &str, avatar_url: Option<&str>, bio: Option<&str>) -> Result<Frogol, AppError> {
         let row = sqlx::query(
             r#"
             UPDATE frogols 
-            SET display_name = ?1, theme = ?2, avatar_url = COALESCE(?3, avatar_url), bio = COALESCE(?4, bio)
+            SET display_name = ?1, theme = ?2, avatar_url = ?3, bio = ?4
             WHERE id = ?5
             RETURNING id, user_id, slug, display_name, theme, avatar_url, bio, created_at
             "#
@@ -212,7 +251,45 @@ impl FrogolRepo {
         })
     }
 
+    /// Deletes a frogol with its links, their clicks and rollups, its leads and avatar
+    /// records in one transaction, and records its slug in `deleted_slugs`.
+    /// Avatar files are left for the maintenance sweep.
     pub async fn delete_frogol(&self, id: &str) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            "DELETE FROM clicks WHERE link_id IN (SELECT id FROM links WHERE frogol_id = ?1)",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM click_daily_rollups WHERE link_id IN (SELECT id FROM links WHERE frogol_id = ?1)",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM click_hourly_rollups WHERE link_id IN (SELECT id FROM links WHERE frogol_id = ?1)",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!("DELETE FROM links WHERE frogol_id = ?1", id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM leads WHERE frogol_id = ?1", id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM frogol_avatar_images WHERE frogol_id = ?1", id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(
+            "INSERT OR REPLACE INTO deleted_slugs (slug) SELECT slug FROM frogols WHERE id = ?1",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
         sqlx::query!(
             r#"
             DELETE FROM frogols
@@ -220,13 +297,15 @@ impl FrogolRepo {
             "#,
             id
         )
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
+        tx.commit().await?;
         Ok(())
     }
 
-    pub async fn get_user_analytics(&self, user_id: &str) -> Result<UserAnalytics, AppError> {
+    /// Click counts leave bots out unless `include_bots`.
+    pub async fn get_user_analytics(&self, user_id: &str, include_bots: bool) -> Result<UserAnalytics, AppError> {
         // Get total counts
         let total_frogols = sqlx::query_scalar!(
             r#"SELECT COUNT(*) FROM frogols WHERE user_id = ?1"#,
@@ -265,9 +344,10 @@ impl FrogolRepo {
             FROM clicks c
             JOIN links l ON c.link_id = l.id
             JOIN frogols f ON l.frogol_id = f.id
-            WHERE f.user_id = ?1
+            WHERE f.user_id = ?1 AND (?2 OR c.is_bot = 0)
             "#,
-            user_id
+            user_id,
+            include_bots
         )
         .fetch_one(&self.pool)
         .await?;
@@ -286,13 +366,14 @@ impl FrogolRepo {
             FROM frogols f
             LEFT JOIN links l ON f.id = l.frogol_id
             LEFT JOIN leads ld ON f.id = ld.frogol_id
-            LEFT JOIN clicks c ON l.id = c.link_id
+            LEFT JOIN clicks c ON l.id = c.link_id AND (?2 OR c.is_bot = 0)
             WHERE f.user_id = ?1
             GROUP BY f.id, f.slug, f.display_name, f.created_at
             ORDER BY COUNT(DISTINCT c.id) DESC, COUNT(DISTINCT ld.id) DESC
             LIMIT 5
             "#,
-            user_id
+            user_id,
+            include_bots
         )
         .fetch_all(&self.pool)
         .await?;
@@ -23,6 +23,7 @@ pub struct NewLead {
     pub source: Option<String>,
     pub score: Option<i64>,
     pub message: Option<String>,
+    pub ip_address: Option<String>,
 }
 
 #[derive(Debug)]
@@ -47,8 +48,8 @@ impl LeadRepo {
     pub async fn create_lead(&self, new_lead: NewLead) -> Result<Lead, AppError> {
         let row = sqlx::query!(
             r#"
-            INSERT INTO leads (id, frogol_id, email, source, score, message)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            INSERT INTO leads (id, frogol_id, email, source, score, message, ip_address)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
             RETURNING 
                 id         as "id!: String",
                 frogol_id  as "frogol_id!: String",
@@ -63,7 +64,8 @@ impl LeadRepo {
             new_lead.email,
             new_lead.source,
             new_lead.score,
-            new_lead.message
+            new_lead.message,
+            new_lead.ip_address
         )
         .fetch_one(&self.pool)
         .await?;
@@ -110,6 +112,53 @@ impl LeadRepo {
         }).collect())
     }
 
+    /// One page of a frogol's leads, newest first.
+    pub async fn list_frogol_leads(&self, frogol_id: &str, limit: i64, offset: i64) -> Result<Vec<Lead>, AppError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                id         as "id!: String",
+                frogol_id  as "frogol_id!: String",
+                email      as "email!: String",
+                source,
+                score,
+                message,
+                created_at as "created_at!: String"
+            FROM leads
+            WHERE frogol_id = ?1
+            ORDER BY created_at DESC, id
+            LIMIT ?2 OFFSET ?3
+            "#,
+            frogol_id,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| Lead {
+            id: row.id,
+            frogol_id: row.frogol_id,
+            email: row.email,
+            source: row.source,
+            score: row.score,
+            message: row.message,
+            created_at: row.created_at.clone(),
+            formatted_date: Self::format_date(&row.created_at),
+        }).collect())
+    }
+
+    pub async fn count_frogol_leads(&self, frogol_id: &str) -> Result<i64, AppError> {
+        let count = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) FROM leads WHERE frogol_id = ?1"#,
+            frogol_id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
     pub async fn get_user_total_leads(&self, user_id: &str) -> Result<i64, AppError> {
         let count = sqlx::query_scalar!(
             r#"
@@ -109,15 +109,24 @@ impl LinkRepo {
     }
 
     pub async fn get_links_all(&self, frogol_id: &str) -> Result<Vec<Link>, AppError> {
+        // SQLite reads a negative LIMIT as no limit
+        self.list_links(frogol_id, -1, 0).await
+    }
+
+    /// One page of the frogol's links, inactive ones included, in display order.
+    pub async fn list_links(&self, frogol_id: &str, limit: i64, offset: i64) -> Result<Vec<Link>, AppError> {
         let rows = sqlx::query(
             r#"
             SELECT id, frogol_id, url, label, sort_order, is_active, kind
             FROM links
             WHERE frogol_id = ?1
             ORDER BY sort_order, id
+            LIMIT ?2 OFFSET ?3
             "#
         )
         .bind(frogol_id)
+        .bind(limit)
+        .bind(offset)
         .fetch_all(&self.pool)
         .await?;
 
@@ -132,6 +141,17 @@ impl LinkRepo {
         }).collect())
     }
 
+    pub async fn count_links(&self, frogol_id: &str) -> Result<i64, AppError> {
+        let count = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) FROM links WHERE frogol_id = ?1"#,
+            frogol_id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
     pub async fn update_link_order(&self, link_ids: &[String]) -> Result<(), AppError> {
         if link_ids.is_empty() {
             return Ok(());
@@ -0,0 +1,152 @@
+use sqlx::SqlitePool;
+use crate::errors::AppError;
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginAttempt {
+    pub id: String,
+    pub ip_address: Option<String>,
+    pub attempted_at: String,
+}
+
+#[derive(Debug)]
+pub struct NewLoginAttempt {
+    pub id: String,
+    pub email: String,
+    pub user_id: Option<String>,
+    pub ip_address: Option<String>,
+    pub succeeded: bool,
+    pub attempted_at: String,
+}
+
+/// Failed attempts counted towards a lockout, and when the latest one happened.
+#[derive(Debug)]
+pub struct FailureStats {
+    pub failures: i64,
+    pub last_failure_at: Option<String>,
+}
+
+/// Timestamps are written by `AuthService` in a fixed-width RFC 3339 format so
+/// they compare correctly as strings.
+#[derive(Debug)]
+pub struct LoginAttemptRepo {
+    pool: SqlitePool,
+}
+
+impl LoginAttemptRepo {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn record(&self, attempt: NewLoginAttempt) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO login_attempts (id, email, user_id, ip_address, succeeded, attempted_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+            attempt.id,
+            attempt.email,
+            attempt.user_id,
+            attempt.ip_address,
+            attempt.succeeded,
+            attempt.attempted_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Failures for `email` after `since` that happened after its last successful sign-in.
+    pub async fn account_failures(&self, email: &str, since: &str) -> Result<FailureStats, AppError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                COUNT(*)          as "failures!: i64",
+                MAX(attempted_at) as "last_failure_at: String"
+            FROM login_attempts
+            WHERE email = ?1
+              AND succeeded = 0
+              AND attempted_at > ?2
+              AND attempted_at > COALESCE(
+                  (SELECT MAX(attempted_at) FROM login_attempts WHERE email = ?1 AND succeeded = 1),
+                  ''
+              )
+            "#,
+            email,
+            since
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(FailureStats {
+            failures: row.failures,
+            last_failure_at: row.last_failure_at,
+        })
+    }
+
+    /// Failures from `ip_address` after `since`, across all accounts. Successful
+    /// sign-ins do not reset this, so one valid account cannot unlock an address.
+    pub async fn ip_failures(&self, ip_address: &str, since: &str) -> Result<FailureStats, AppError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                COUNT(*)          as "failures!: i64",
+                MAX(attempted_at) as "last_failure_at: String"
+            FROM login_attempts
+            WHERE ip_address = ?1
+              AND succeeded = 0
+              AND attempted_at > ?2
+            "#,
+            ip_address,
+            since
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(FailureStats {
+            failures: row.failures,
+            last_failure_at: row.last_failure_at,
+        })
+    }
+
+    /// Most recent failed sign-ins against the user's account, newest first.
+    pub async fn recent_failures_for_user(&self, user_id: &str, since: &str, limit: i64) -> Result<Vec<LoginAttempt>, AppError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                id           as "id!: String",
+                ip_address,
+                attempted_at as "attempted_at!: String"
+            FROM login_attempts
+            WHERE user_id = ?1 AND succeeded = 0 AND attempted_at > ?2
+            ORDER BY attempted_at DESC
+            LIMIT ?3
+            "#,
+            user_id,
+            since,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| LoginAttempt {
+                id: r.id,
+                ip_address: r.ip_address,
+                attempted_at: r.attempted_at,
+            })
+            .collect())
+    }
+
+    /// Removes attempts made before `before`, once they no longer count towards
+    /// any lockout or the settings page.
+    pub async fn delete_before(&self, before: &str) -> Result<u64, AppError> {
+        let result = sqlx::query!("DELETE FROM login_attempts WHERE attempted_at < ?1", before)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -3,3 +3,6 @@ pub mod link_repo;
 pub mod lead_repo;
 pub mod click_repo;
 pub mod user_repo;
+pub mod login_attempt_repo;
+pub mod api_token_repo;
+pub mod visitor_salt_repo;
@@ -8,9 +8,16 @@ pub struct User {
     pub email: String,
     pub password_hash: Option<String>,
     pub is_active: bool,
+    pub email_verified_at: Option<String>,
     pub created_at: String,
 }
 
+impl User {
+    pub fn is_email_verified(&self) -> bool {
+        self.email_verified_at.is_some()
+    }
+}
+
 #[derive(Debug)]
 pub struct NewUser {
     pub id: String,
@@ -25,6 +32,10 @@ pub struct Session {
     pub token: String,
     pub expires_at: String,
     pub created_at: String,
+    pub last_seen_at: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub remember_me: bool,
 }
 
 #[derive(Debug)]
@@ -33,6 +44,69 @@ pub struct NewSession {
     pub user_id: String,
     pub token: String,
     pub expires_at: String,
+    pub last_seen_at: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub remember_me: bool,
+}
+
+#[derive(Debug)]
+pub struct PasswordResetToken {
+    pub id: String,
+    pub user_id: String,
+    pub expires_at: String,
+    pub used_at: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct NewPasswordResetToken {
+    pub id: String,
+    pub user_id: String,
+    pub token_hash: String,
+    pub expires_at: String,
+}
+
+#[derive(Debug)]
+pub struct EmailVerificationToken {
+    pub id: String,
+    pub user_id: String,
+    pub email: String,
+    pub expires_at: String,
+    pub used_at: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct NewEmailVerificationToken {
+    pub id: String,
+    pub user_id: String,
+    pub email: String,
+    pub token_hash: String,
+    pub expires_at: String,
+}
+
+#[derive(Debug)]
+pub struct UserTotp {
+    pub secret: String,
+    pub enabled_at: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct LoginChallenge {
+    pub id: String,
+    pub user_id: String,
+    pub expires_at: String,
+    pub failed_attempts: i64,
+    pub used_at: Option<String>,
+    pub remember_me: bool,
+}
+
+#[derive(Debug)]
+pub struct NewLoginChallenge {
+    pub id: String,
+    pub user_id: String,
+    pub token_hash: String,
+    pub expires_at: String,
+    pub remember_me: bool,
 }
 
 #[derive(Debug)]
@@ -55,6 +129,7 @@ impl UserRepo {
                 email         as "email!: String",
                 password_hash,
                 is_active     as "is_active!: bool",
+                email_verified_at,
                 created_at    as "created_at!: String"
             "#,
             new_user.id,
@@ -69,6 +144,7 @@ impl UserRepo {
             email: row.email,
             password_hash: row.password_hash,
             is_active: row.is_active,
+            email_verified_at: row.email_verified_at,
             created_at: row.created_at,
         })
     }
@@ -81,6 +157,7 @@ impl UserRepo {
                 email         as "email!: String",
                 password_hash,
                 is_active     as "is_active!: bool",
+                email_verified_at,
                 created_at    as "created_at!: String"
             FROM users
             WHERE email = ?1
@@ -95,6 +172,7 @@ impl UserRepo {
             email: r.email,
             password_hash: r.password_hash,
             is_active: r.is_active,
+            email_verified_at: r.email_verified_at,
             created_at: r.created_at,
         }))
     }
@@ -107,6 +185,7 @@ impl UserRepo {
                 email         as "email!: String",
                 password_hash,
                 is_active     as "is_active!: bool",
+                email_verified_at,
                 created_at    as "created_at!: String"
             FROM users
             WHERE id = ?1
@@ -121,6 +200,7 @@ impl UserRepo {
             email: r.email,
             password_hash: r.password_hash,
             is_active: r.is_active,
+            email_verified_at: r.email_verified_at,
             created_at: r.created_at,
         }))
     }
@@ -128,19 +208,27 @@ impl UserRepo {
     pub async fn create_session(&self, new_session: NewSession) -> Result<Session, AppError> {
         let row = sqlx::query!(
             r#"
-            INSERT INTO sessions (id, user_id, token, expires_at)
-            VALUES (?1, ?2, ?3, ?4)
+            INSERT INTO sessions (id, user_id, token, expires_at, last_seen_at, user_agent, ip_address, remember_me)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
             RETURNING 
-                id         as "id!: String",
-                user_id    as "user_id!: String",
-                token      as "token!: String",
-                expires_at as "expires_at!: String",
-                created_at as "created_at!: String"
+                id          as "id!: String",
+                user_id     as "user_id!: String",
+                token       as "token!: String",
+                expires_at  as "expires_at!: String",
+                created_at  as "created_at!: String",
+                last_seen_at,
+                user_agent,
+                ip_address,
+                remember_me as "remember_me!: bool"
             "#,
             new_session.id,
             new_session.user_id,
             new_session.token,
-            new_session.expires_at
+            new_session.expires_at,
+            new_session.last_seen_at,
+            new_session.user_agent,
+            new_session.ip_address,
+            new_session.remember_me
         )
         .fetch_one(&self.pool)
         .await?;
@@ -151,6 +239,10 @@ impl UserRepo {
             token: row.token,
             expires_at: row.expires_at,
             created_at: row.created_at,
+            last_seen_at: row.last_seen_at,
+            user_agent: row.user_agent,
+            ip_address: row.ip_address,
+            remember_me: row.remember_me,
         })
     }
 
@@ -158,11 +250,15 @@ impl UserRepo {
         let row = sqlx::query!(
             r#"
             SELECT 
-                id         as "id!: String",
-                user_id    as "user_id!: String",
-                token      as "token!: String",
-                expires_at as "expires_at!: String",
-                created_at as "created_at!: String"
+                id          as "id!: String",
+                user_id     as "user_id!: String",
+                token       as "token!: String",
+                expires_at  as "expires_at!: String",
+                created_at  as "created_at!: String",
+                last_seen_at,
+                user_agent,
+                ip_address,
+                remember_me as "remember_me!: bool"
             FROM sessions
             WHERE token = ?1
             "#,
@@ -177,9 +273,31 @@ impl UserRepo {
             token: r.token,
             expires_at: r.expires_at,
             created_at: r.created_at,
+            last_seen_at: r.last_seen_at,
+            user_agent: r.user_agent,
+            ip_address: r.ip_address,
+            remember_me: r.remember_me,
         }))
     }
 
+    /// Records use of a session and moves its expiry, for sliding renewal.
+    pub async fn touch_session(&self, id: &str, last_seen_at: &str, expires_at: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET last_seen_at = ?1, expires_at = ?2
+            WHERE id = ?3
+            "#,
+            last_seen_at,
+            expires_at,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     pub async fn delete_session(&self, token: &str) -> Result<(), AppError> {
         sqlx::query!(
             r#"
@@ -193,4 +311,755 @@ impl UserRepo {
 
         Ok(())
     }
-} 
\ No newline at end of file
+
+    pub async fn delete_user_sessions(&self, user_id: &str) -> Result<u64, AppError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM sessions
+            WHERE user_id = ?1
+            "#,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn update_password(&self, user_id: &str, password_hash: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = ?1
+            WHERE id = ?2
+            "#,
+            password_hash,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn create_password_reset_token(&self, new_token: NewPasswordResetToken) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO password_reset_tokens (id, user_id, token_hash, expires_at)
+            VALUES (?1, ?2, ?3, ?4)
+            "#,
+            new_token.id,
+            new_token.user_id,
+            new_token.token_hash,
+            new_token.expires_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_password_reset_token(&self, token_hash: &str) -> Result<Option<PasswordResetToken>, AppError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT 
+                id         as "id!: String",
+                user_id    as "user_id!: String",
+                expires_at as "expires_at!: String",
+                used_at
+            FROM password_reset_tokens
+            WHERE token_hash = ?1
+            "#,
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| PasswordResetToken {
+            id: r.id,
+            user_id: r.user_id,
+            expires_at: r.expires_at,
+            used_at: r.used_at,
+        }))
+    }
+
+    /// Marks a reset token as used. Returns false if it had already been consumed,
+    /// so two concurrent resets with the same link cannot both succeed.
+    pub async fn consume_password_reset_token(&self, id: &str, used_at: &str) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE password_reset_tokens
+            SET used_at = ?1
+            WHERE id = ?2 AND used_at IS NULL
+            "#,
+            used_at,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Invalidates every outstanding reset token for a user.
+    pub async fn invalidate_password_reset_tokens(&self, user_id: &str, used_at: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+            UPDATE password_reset_tokens
+            SET used_at = ?1
+            WHERE user_id = ?2 AND used_at IS NULL
+            "#,
+            used_at,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Sets the user's address to `email` and records it as verified.
+    pub async fn set_verified_email(&self, user_id: &str, email: &str, verified_at: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET email = ?1, email_verified_at = ?2
+            WHERE id = ?3
+            "#,
+            email,
+            verified_at,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Marks the current address verified, keeping the original timestamp if already set.
+    pub async fn mark_email_verified(&self, user_id: &str, verified_at: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET email_verified_at = COALESCE(email_verified_at, ?1)
+            WHERE id = ?2
+            "#,
+            verified_at,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn list_user_sessions(&self, user_id: &str) -> Result<Vec<Session>, AppError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT 
+                id          as "id!: String",
+                user_id     as "user_id!: String",
+                token       as "token!: String",
+                expires_at  as "expires_at!: String",
+                created_at  as "created_at!: String",
+                last_seen_at,
+                user_agent,
+                ip_address,
+                remember_me as "remember_me!: bool"
+            FROM sessions
+            WHERE user_id = ?1
+            ORDER BY created_at DESC
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| Session {
+            id: r.id,
+            user_id: r.user_id,
+            token: r.token,
+            expires_at: r.expires_at,
+            created_at: r.created_at,
+            last_seen_at: r.last_seen_at,
+            user_agent: r.user_agent,
+            ip_address: r.ip_address,
+            remember_me: r.remember_me,
+        }).collect())
+    }
+
+    /// Deletes one of the user's sessions. Returns false if no such session belongs to them.
+    pub async fn delete_user_session(&self, user_id: &str, session_id: &str) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM sessions
+            WHERE id = ?1 AND user_id = ?2
+            "#,
+            session_id,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn delete_user_sessions_except(&self, user_id: &str, keep_token: &str) -> Result<u64, AppError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM sessions
+            WHERE user_id = ?1 AND token != ?2
+            "#,
+            user_id,
+            keep_token
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn create_email_verification_token(&self, new_token: NewEmailVerificationToken) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO email_verification_tokens (id, user_id, email, token_hash, expires_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            "#,
+            new_token.id,
+            new_token.user_id,
+            new_token.email,
+            new_token.token_hash,
+            new_token.expires_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_email_verification_token(&self, token_hash: &str) -> Result<Option<EmailVerificationToken>, AppError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT 
+                id         as "id!: String",
+                user_id    as "user_id!: String",
+                email      as "email!: String",
+                expires_at as "expires_at!: String",
+                used_at
+            FROM email_verification_tokens
+            WHERE token_hash = ?1
+            "#,
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| EmailVerificationToken {
+            id: r.id,
+            user_id: r.user_id,
+            email: r.email,
+            expires_at: r.expires_at,
+            used_at: r.used_at,
+        }))
+    }
+
+    /// Marks a verification token as used. Returns false if it had already been consumed.
+    pub async fn consume_email_verification_token(&self, id: &str, used_at: &str) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE email_verification_tokens
+            SET used_at = ?1
+            WHERE id = ?2 AND used_at IS NULL
+            "#,
+            used_at,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Marks every outstanding verification token for the user as used.
+    pub async fn invalidate_email_verification_tokens(&self, user_id: &str, used_at: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+            UPDATE email_verification_tokens
+            SET used_at = ?1
+            WHERE user_id = ?2 AND used_at IS NULL
+            "#,
+            used_at,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_totp(&self, user_id: &str) -> Result<Option<UserTotp>, AppError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                secret     as "secret!: String",
+                enabled_at
+            FROM user_totp
+            WHERE user_id = ?1
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| UserTotp {
+            secret: r.secret,
+            enabled_at: r.enabled_at,
+        }))
+    }
+
+    /// Stores a new unconfirmed secret, replacing any earlier unconfirmed one.
+    /// Leaves an enabled enrollment untouched and returns false.
+    pub async fn set_pending_totp(&self, user_id: &str, secret: &str) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO user_totp (user_id, secret)
+            VALUES (?1, ?2)
+            ON CONFLICT(user_id) DO UPDATE
+            SET secret = excluded.secret, last_used_step = NULL
+            WHERE user_totp.enabled_at IS NULL
+            "#,
+            user_id,
+            secret
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Confirms the pending enrollment and replaces the user's recovery codes.
+    pub async fn enable_totp(
+        &self,
+        user_id: &str,
+        enabled_at: &str,
+        used_step: i64,
+        recovery_codes: &[(String, String)],
+    ) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE user_totp
+            SET enabled_at = ?1, last_used_step = ?2
+            WHERE user_id = ?3
+            "#,
+            enabled_at,
+            used_step,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        Self::insert_recovery_codes(&mut tx, user_id, recovery_codes).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Records `step` as used. Returns false if it, or a later step, was already used.
+    pub async fn use_totp_step(&self, user_id: &str, step: i64) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE user_totp
+            SET last_used_step = ?1
+            WHERE user_id = ?2 AND (last_used_step IS NULL OR last_used_step < ?1)
+            "#,
+            step,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn delete_totp(&self, user_id: &str) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!("DELETE FROM totp_recovery_codes WHERE user_id = ?1", user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM user_totp WHERE user_id = ?1", user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Replaces all recovery codes with `(id, code_hash)` pairs.
+    pub async fn replace_recovery_codes(&self, user_id: &str, recovery_codes: &[(String, String)]) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+        Self::insert_recovery_codes(&mut tx, user_id, recovery_codes).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Swaps the user's recovery codes for `recovery_codes` inside `tx`.
+    async fn insert_recovery_codes(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        user_id: &str,
+        recovery_codes: &[(String, String)],
+    ) -> Result<(), AppError> {
+        sqlx::query!("DELETE FROM totp_recovery_codes WHERE user_id = ?1", user_id)
+            .execute(&mut **tx)
+            .await?;
+        for (id, code_hash) in recovery_codes {
+            sqlx::query!(
+                r#"
+                INSERT INTO totp_recovery_codes (id, user_id, code_hash)
+                VALUES (?1, ?2, ?3)
+                "#,
+                id,
+                user_id,
+                code_hash
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Marks a matching unused recovery code as used. Returns false if none matched.
+    pub async fn consume_recovery_code(&self, user_id: &str, code_hash: &str, used_at: &str) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE totp_recovery_codes
+            SET used_at = ?1
+            WHERE id = (
+                SELECT id FROM totp_recovery_codes
+                WHERE user_id = ?2 AND code_hash = ?3 AND used_at IS NULL
+                LIMIT 1
+            )
+            "#,
+            used_at,
+            user_id,
+            code_hash
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn count_unused_recovery_codes(&self, user_id: &str) -> Result<i64, AppError> {
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) as "count!: i64"
+            FROM totp_recovery_codes
+            WHERE user_id = ?1 AND used_at IS NULL
+            "#,
+            user_id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    pub async fn create_login_challenge(&self, challenge: NewLoginChallenge) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO login_challenges (id, user_id, token_hash, expires_at, remember_me)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            "#,
+            challenge.id,
+            challenge.user_id,
+            challenge.token_hash,
+            challenge.expires_at,
+            challenge.remember_me
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_login_challenge(&self, token_hash: &str) -> Result<Option<LoginChallenge>, AppError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                id              as "id!: String",
+                user_id         as "user_id!: String",
+                expires_at      as "expires_at!: String",
+                failed_attempts as "failed_attempts!: i64",
+                used_at,
+                remember_me     as "remember_me!: bool"
+            FROM login_challenges
+            WHERE token_hash = ?1
+            "#,
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| LoginChallenge {
+            id: r.id,
+            user_id: r.user_id,
+            expires_at: r.expires_at,
+            failed_attempts: r.failed_attempts,
+            used_at: r.used_at,
+            remember_me: r.remember_me,
+        }))
+    }
+
+    pub async fn record_login_challenge_failure(&self, id: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE login_challenges SET failed_attempts = failed_attempts + 1 WHERE id = ?1",
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Marks a challenge as used. Returns false if it had already been consumed.
+    pub async fn consume_login_challenge(&self, id: &str, used_at: &str) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE login_challenges
+            SET used_at = ?1
+            WHERE id = ?2 AND used_at IS NULL
+            "#,
+            used_at,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Removes sessions that expired before `now`. Expiry times are not
+    /// fixed-width, so they are compared as dates rather than strings.
+    pub async fn delete_expired_sessions(&self, now: &str) -> Result<u64, AppError> {
+        let result = sqlx::query!(
+            "DELETE FROM sessions WHERE julianday(expires_at) < julianday(?1)",
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Removes password reset tokens that expired before `now`, used or not.
+    pub async fn delete_expired_password_reset_tokens(&self, now: &str) -> Result<u64, AppError> {
+        let result = sqlx::query!(
+            "DELETE FROM password_reset_tokens WHERE julianday(expires_at) < julianday(?1)",
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Removes email verification tokens that expired before `now`, used or not.
+    pub async fn delete_expired_email_verification_tokens(&self, now: &str) -> Result<u64, AppError> {
+        let result = sqlx::query!(
+            "DELETE FROM email_verification_tokens WHERE julianday(expires_at) < julianday(?1)",
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Removes second-step sign-in challenges that expired before `now`.
+    pub async fn delete_expired_login_challenges(&self, now: &str) -> Result<u64, AppError> {
+        let result = sqlx::query!(
+            "DELETE FROM login_challenges WHERE julianday(expires_at) < julianday(?1)",
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Deletes a user together with their frogols, links, clicks, leads, avatar
+    /// records, sessions, API tokens, other tokens and two-factor data in one
+    /// transaction.
+    ///
+    /// Returns the avatar filenames under `static/avatars` that belonged to the
+    /// deleted frogols and are not referenced by anyone else, so the caller can
+    /// remove them from disk.
+    pub async fn delete_user(&self, user_id: &str) -> Result<Vec<String>, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let mut avatar_files = sqlx::query_scalar!(
+            r#"
+            SELECT ai.image_filename as "image_filename!: String"
+            FROM frogol_avatar_images ai
+            JOIN frogols f ON ai.frogol_id = f.id
+            WHERE f.user_id = ?1
+            "#,
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let avatar_urls = sqlx::query_scalar!(
+            r#"
+            SELECT avatar_url as "avatar_url!: String"
+            FROM frogols
+            WHERE user_id = ?1 AND avatar_url LIKE '/static/avatars/%'
+            "#,
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        for url in avatar_urls {
+            // avatar_url is user-editable, so only accept names shaped like our uploads
+            let Some(filename) = url.strip_prefix("/static/avatars/") else { continue };
+            let is_upload_name = std::path::Path::new(filename)
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| uuid::Uuid::parse_str(stem).is_ok())
+                && !filename.contains(['/', '\\']);
+            if is_upload_name && !avatar_files.iter().any(|f| f == filename) {
+                avatar_files.push(filename.to_string());
+            }
+        }
+
+        sqlx::query!(
+            r#"
+            DELETE FROM clicks
+            WHERE link_id IN (
+                SELECT l.id FROM links l
+                JOIN frogols f ON l.frogol_id = f.id
+                WHERE f.user_id = ?1
+            )
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM click_daily_rollups
+            WHERE link_id IN (
+                SELECT l.id FROM links l
+                JOIN frogols f ON l.frogol_id = f.id
+                WHERE f.user_id = ?1
+            )
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM click_hourly_rollups
+            WHERE link_id IN (
+                SELECT l.id FROM links l
+                JOIN frogols f ON l.frogol_id = f.id
+                WHERE f.user_id = ?1
+            )
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM links
+            WHERE frogol_id IN (SELECT id FROM frogols WHERE user_id = ?1)
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM leads
+            WHERE frogol_id IN (SELECT id FROM frogols WHERE user_id = ?1)
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM frogol_avatar_images
+            WHERE frogol_id IN (SELECT id FROM frogols WHERE user_id = ?1)
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "INSERT OR REPLACE INTO deleted_slugs (slug) SELECT slug FROM frogols WHERE user_id = ?1",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!("DELETE FROM frogols WHERE user_id = ?1", user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM sessions WHERE user_id = ?1", user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM api_tokens WHERE user_id = ?1", user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM password_reset_tokens WHERE user_id = ?1", user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM email_verification_tokens WHERE user_id = ?1", user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM login_challenges WHERE user_id = ?1", user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM totp_recovery_codes WHERE user_id = ?1", user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM user_totp WHERE user_id = ?1", user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM users WHERE id = ?1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // Keep files another account still points at
+        let mut orphaned = Vec::with_capacity(avatar_files.len());
+        for filename in avatar_files {
+            let url = format!("/static/avatars/{}", filename);
+            let still_used = sqlx::query_scalar!(
+                r#"
+                SELECT
+                    (SELECT COUNT(*) FROM frogols WHERE avatar_url = ?1) +
+                    (SELECT COUNT(*) FROM frogol_avatar_images WHERE image_filename = ?2)
+                    as "count!: i64"
+                "#,
+                url,
+                filename
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            if still_used == 0 {
+                orphaned.push(filename);
+            }
+        }
+
+        tx.commit().await?;
+        Ok(orphaned)
+    }
+}
@@ -0,0 +1,44 @@
+use sqlx::SqlitePool;
+use crate::errors::AppError;
+
+/// One random salt per UTC day (`YYYY-MM-DD`), shared by every instance.
+#[derive(Debug)]
+pub struct VisitorSaltRepo {
+    pool: SqlitePool,
+}
+
+impl VisitorSaltRepo {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// The salt for `day`, storing `candidate` if there is none yet. When two
+    /// instances race, both end up with whichever was stored first.
+    pub async fn get_or_create(&self, day: &str, candidate: &str) -> Result<String, AppError> {
+        sqlx::query!(
+            r#"INSERT OR IGNORE INTO visitor_salts (day, salt) VALUES (?1, ?2)"#,
+            day,
+            candidate
+        )
+        .execute(&self.pool)
+        .await?;
+
+        let salt = sqlx::query_scalar!(
+            r#"SELECT salt as "salt!: String" FROM visitor_salts WHERE day = ?1"#,
+            day
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(salt)
+    }
+
+    /// Deletes the salts of days before `day`.
+    pub async fn delete_before(&self, day: &str) -> Result<u64, AppError> {
+        let result = sqlx::query!(r#"DELETE FROM visitor_salts WHERE day < ?1"#, day)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,126 @@
+use axum::{
+    extract::{Path, State},
+    http::Method,
+    Json,
+};
+
+use super::dto::{BotParams, BreakdownEntryDto, ClickSeriesDto, FrogolAnalyticsDto, LinkClicksDto, UserAnalyticsDto};
+use super::ApiQuery;
+use crate::{
+    errors::AppError,
+    middleware::auth::ApiUser,
+    repo::click_repo::ClickScope,
+    routes::openapi::{schema, ApiRouter, Operation, JSON},
+    services::{api_token_service::ApiScope, click_series::ClickSeriesParams},
+    state::AppState,
+};
+
+const SERIES_DESCRIPTION: &str = "One entry per hour, day or week in range, empty ones included. Weeks start on Monday.";
+
+pub(super) fn routes() -> ApiRouter {
+    ApiRouter::new()
+        .route(
+            Operation::new(Method::GET, "/api/v1/analytics", "Analytics", "Totals across your frogols")
+                .scope(ApiScope::AnalyticsRead)
+                .bots()
+                .responds_with("200", "Account analytics", JSON, schema::<UserAnalyticsDto>),
+            user_analytics,
+        )
+        .route(
+            Operation::new(Method::GET, "/api/v1/frogols/:id/analytics", "Analytics", "A frogol's analytics")
+                .scope(ApiScope::AnalyticsRead)
+                .bots()
+                .responds_with("200", "Frogol analytics", JSON, schema::<FrogolAnalyticsDto>),
+            frogol_analytics,
+        )
+        .route(
+            Operation::new(Method::GET, "/api/v1/frogols/:id/analytics/clicks", "Analytics", "A frogol's clicks over time")
+                .scope(ApiScope::AnalyticsRead)
+                .describe(SERIES_DESCRIPTION)
+                .series()
+                .bots()
+                .responds_with("200", "Click series", JSON, schema::<ClickSeriesDto>),
+            frogol_click_series,
+        )
+        .route(
+            Operation::new(Method::GET, "/api/v1/links/:id/analytics/clicks", "Analytics", "A link's clicks over time")
+                .scope(ApiScope::AnalyticsRead)
+                .describe(SERIES_DESCRIPTION)
+                .series()
+                .bots()
+                .responds_with("200", "Click series", JSON, schema::<ClickSeriesDto>),
+            link_click_series,
+        )
+}
+
+/// Totals across all of the caller's frogols.
+async fn user_analytics(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    ApiQuery(bots): ApiQuery<BotParams>,
+) -> Result<Json<UserAnalyticsDto>, AppError> {
+    let user = caller.require(ApiScope::AnalyticsRead)?;
+    let analytics = state.services.frogol.get_user_analytics(&user.id, bots.include_bots).await?;
+    Ok(Json(analytics.into()))
+}
+
+async fn frogol_analytics(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    Path(id): Path<String>,
+    ApiQuery(bots): ApiQuery<BotParams>,
+) -> Result<Json<FrogolAnalyticsDto>, AppError> {
+    let user = caller.require(ApiScope::AnalyticsRead)?;
+    state.services.frogol.get_owned_frogol(&user.id, &id).await?;
+
+    let stats = state.services.frogol.get_click_stats(&id, bots.include_bots).await?;
+    let breakdown = state.services.frogol.get_click_breakdown(ClickScope::Frogol(&id), None, bots.include_bots).await?;
+    let total_leads = state.services.lead.count_frogol_leads(&id).await?;
+    let mut clicks_by_link: Vec<LinkClicksDto> = state
+        .services
+        .frogol
+        .get_clicks_by_link(&id, bots.include_bots)
+        .await?
+        .into_iter()
+        .map(|(link_id, clicks)| LinkClicksDto { link_id, clicks })
+        .collect();
+    // Most-clicked first, with a stable order for ties
+    clicks_by_link.sort_by(|a, b| b.clicks.cmp(&a.clicks).then_with(|| a.link_id.cmp(&b.link_id)));
+
+    Ok(Json(FrogolAnalyticsDto {
+        total_clicks: stats.total_clicks,
+        unique_clicks: stats.unique_clicks,
+        total_leads,
+        clicks_by_link,
+        devices: BreakdownEntryDto::list(breakdown.devices),
+        browsers: BreakdownEntryDto::list(breakdown.browsers),
+        operating_systems: BreakdownEntryDto::list(breakdown.operating_systems),
+        bot_clicks: breakdown.bot_clicks,
+    }))
+}
+
+async fn frogol_click_series(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    Path(id): Path<String>,
+    ApiQuery(params): ApiQuery<ClickSeriesParams>,
+) -> Result<Json<ClickSeriesDto>, AppError> {
+    let user = caller.require(ApiScope::AnalyticsRead)?;
+    let query = state.services.frogol.click_series_query(&params)?;
+    state.services.frogol.get_owned_frogol(&user.id, &id).await?;
+    let buckets = state.services.frogol.get_click_series(ClickScope::Frogol(&id), &query).await?;
+    Ok(Json(ClickSeriesDto::new(&query, buckets)))
+}
+
+async fn link_click_series(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    Path(id): Path<String>,
+    ApiQuery(params): ApiQuery<ClickSeriesParams>,
+) -> Result<Json<ClickSeriesDto>, AppError> {
+    let user = caller.require(ApiScope::AnalyticsRead)?;
+    let query = state.services.frogol.click_series_query(&params)?;
+    state.services.frogol.get_owned_link(&user.id, &id).await?;
+    let buckets = state.services.frogol.get_click_series(ClickScope::Link(&id), &query).await?;
+    Ok(Json(ClickSeriesDto::new(&query, buckets)))
+}
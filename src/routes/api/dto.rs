@@ -0,0 +1,402 @@
+//! Request and response bodies for `/api/v1`. These are kept apart from the
+//! repo structs so the database schema can change without changing the API.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
+use validator::{Validate, ValidationError};
+
+use crate::errors::AppError;
+use crate::repo::{
+    click_repo::Click,
+    frogol_repo::{Frogol, FrogolSummary, UserAnalytics},
+    lead_repo::Lead,
+    link_repo::Link,
+};
+use crate::services::click_series::{Bucket, ClickBucket, ClickSeriesQuery};
+
+pub const DEFAULT_PER_PAGE: u32 = 20;
+pub const MAX_PER_PAGE: u32 = 100;
+
+/// `?page=&per_page=` on list endpoints. Pages start at 1.
+#[derive(Debug, Default, Deserialize)]
+pub struct PageParams {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+/// `?include_bots=true` on analytics endpoints counts bot clicks too.
+#[derive(Debug, Default, Deserialize)]
+pub struct BotParams {
+    #[serde(default)]
+    pub include_bots: bool,
+}
+
+/// A validated page request.
+#[derive(Debug, Clone, Copy)]
+pub struct Page {
+    pub page: u32,
+    pub per_page: u32,
+}
+
+impl PageParams {
+    pub fn resolve(&self) -> Result<Page, AppError> {
+        let page = self.page.unwrap_or(1);
+        let per_page = self.per_page.unwrap_or(DEFAULT_PER_PAGE);
+        if page == 0 {
+            return Err(AppError::InvalidInput("page must be at least 1".to_string()));
+        }
+        if per_page == 0 || per_page > MAX_PER_PAGE {
+            return Err(AppError::InvalidInput(format!(
+                "per_page must be between 1 and {}",
+                MAX_PER_PAGE
+            )));
+        }
+        Ok(Page { page, per_page })
+    }
+}
+
+impl Page {
+    pub fn limit(&self) -> i64 {
+        i64::from(self.per_page)
+    }
+
+    pub fn offset(&self) -> i64 {
+        i64::from(self.page - 1) * i64::from(self.per_page)
+    }
+
+    /// Wraps one page of already-fetched rows.
+    pub fn wrap<T, U: From<T>>(&self, rows: Vec<T>, total: i64) -> Paginated<U> {
+        Paginated {
+            data: rows.into_iter().map(U::from).collect(),
+            pagination: Pagination {
+                page: self.page,
+                per_page: self.per_page,
+                total,
+            },
+        }
+    }
+}
+
+/// Rejects empty and whitespace-only strings with the code `blank`.
+fn non_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        return Err(ValidationError::new("blank"));
+    }
+    Ok(())
+}
+
+/// Reads a field that is present as `Some`, even when it is `null`. With
+/// `#[serde(default)]` an absent field stays `None`, so `Some(None)` means
+/// "clear it".
+fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Paginated<T> {
+    pub data: Vec<T>,
+    pub pagination: Pagination,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Pagination {
+    pub page: u32,
+    pub per_page: u32,
+    /// Items across all pages.
+    pub total: i64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct FrogolDto {
+    pub id: String,
+    pub slug: String,
+    pub display_name: Option<String>,
+    pub theme: String,
+    pub avatar_url: Option<String>,
+    pub bio: Option<String>,
+    pub created_at: String,
+}
+
+impl From<Frogol> for FrogolDto {
+    fn from(frogol: Frogol) -> Self {
+        Self {
+            id: frogol.id,
+            slug: frogol.slug,
+            display_name: frogol.display_name,
+            theme: frogol.theme.unwrap_or_else(|| "default".to_string()),
+            avatar_url: frogol.avatar_url,
+            bio: frogol.bio,
+            created_at: frogol.created_at,
+        }
+    }
+}
+
+/// A frogol in a list, with its totals.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct FrogolSummaryDto {
+    pub id: String,
+    pub slug: String,
+    pub display_name: String,
+    pub total_links: i64,
+    pub total_leads: i64,
+    pub total_clicks: i64,
+    pub created_at: String,
+}
+
+impl From<FrogolSummary> for FrogolSummaryDto {
+    fn from(summary: FrogolSummary) -> Self {
+        Self {
+            id: summary.id,
+            slug: summary.slug,
+            display_name: summary.display_name,
+            total_links: summary.total_links,
+            total_leads: summary.total_leads,
+            total_clicks: summary.total_clicks,
+            created_at: summary.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Validate)]
+pub struct CreateFrogolRequest {
+    #[validate(custom = "non_blank")]
+    pub slug: String,
+    #[validate(custom = "non_blank")]
+    pub display_name: String,
+}
+
+/// Fields left out are not changed. `avatar_url` and `bio` are cleared by
+/// sending `null`.
+#[derive(Debug, Deserialize, JsonSchema, Validate)]
+pub struct UpdateFrogolRequest {
+    #[validate(custom = "non_blank")]
+    pub display_name: Option<String>,
+    pub theme: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub avatar_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub bio: Option<Option<String>>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct LinkDto {
+    pub id: String,
+    pub frogol_id: String,
+    pub url: String,
+    pub label: String,
+    pub sort_order: i64,
+    pub is_active: bool,
+}
+
+impl From<Link> for LinkDto {
+    fn from(link: Link) -> Self {
+        Self {
+            id: link.id,
+            frogol_id: link.frogol_id,
+            url: link.url,
+            label: link.label,
+            sort_order: link.sort_order,
+            is_active: link.is_active,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Validate)]
+pub struct CreateLinkRequest {
+    #[validate(custom = "non_blank")]
+    pub url: String,
+    #[validate(custom = "non_blank")]
+    pub label: String,
+}
+
+/// Fields left out are not changed.
+#[derive(Debug, Deserialize, JsonSchema, Validate)]
+pub struct UpdateLinkRequest {
+    #[validate(custom = "non_blank")]
+    pub url: Option<String>,
+    #[validate(custom = "non_blank")]
+    pub label: Option<String>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct LeadDto {
+    pub id: String,
+    pub frogol_id: String,
+    pub email: String,
+    pub source: Option<String>,
+    pub score: Option<i64>,
+    pub message: Option<String>,
+    pub created_at: String,
+}
+
+impl From<Lead> for LeadDto {
+    fn from(lead: Lead) -> Self {
+        Self {
+            id: lead.id,
+            frogol_id: lead.frogol_id,
+            email: lead.email,
+            source: lead.source,
+            score: lead.score,
+            message: lead.message,
+            created_at: lead.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Validate)]
+pub struct CreateLeadRequest {
+    #[validate(email)]
+    pub email: String,
+    pub source: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Fields left out are not changed. `source`, `score` and `message` are
+/// cleared by sending `null`.
+#[derive(Debug, Deserialize, JsonSchema, Validate)]
+pub struct UpdateLeadRequest {
+    #[validate(email)]
+    pub email: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub source: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub score: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub message: Option<Option<String>>,
+}
+
+/// A recorded click. Nothing identifying the visitor is exposed.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ClickDto {
+    pub id: String,
+    pub link_id: String,
+    /// `mobile`, `tablet`, `desktop`, `bot` or `unknown`. Missing for clicks
+    /// recorded before devices were classified.
+    pub device_class: Option<String>,
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    /// Bot clicks are listed but left out of every count.
+    pub is_bot: bool,
+    pub created_at: String,
+}
+
+impl From<Click> for ClickDto {
+    fn from(click: Click) -> Self {
+        Self {
+            id: click.id,
+            link_id: click.link_id,
+            device_class: click.device_class,
+            browser: click.browser,
+            os: click.os,
+            is_bot: click.is_bot,
+            created_at: click.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct UserAnalyticsDto {
+    pub total_frogols: i64,
+    pub total_links: i64,
+    pub total_leads: i64,
+    pub total_clicks: i64,
+    pub top_frogols: Vec<FrogolSummaryDto>,
+}
+
+impl From<UserAnalytics> for UserAnalyticsDto {
+    fn from(analytics: UserAnalytics) -> Self {
+        Self {
+            total_frogols: analytics.total_frogols,
+            total_links: analytics.total_links,
+            total_leads: analytics.total_leads,
+            total_clicks: analytics.total_clicks,
+            top_frogols: analytics.top_performing_frogols.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct FrogolAnalyticsDto {
+    pub total_clicks: i64,
+    pub unique_clicks: i64,
+    pub total_leads: i64,
+    pub clicks_by_link: Vec<LinkClicksDto>,
+    /// Clicks by device class, most first. The breakdowns leave out bots
+    /// unless `include_bots` is set.
+    pub devices: Vec<BreakdownEntryDto>,
+    pub browsers: Vec<BreakdownEntryDto>,
+    pub operating_systems: Vec<BreakdownEntryDto>,
+    /// Clicks made by bots, counted in the other figures only with
+    /// `include_bots`.
+    pub bot_clicks: i64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct BreakdownEntryDto {
+    pub name: String,
+    pub clicks: i64,
+}
+
+impl BreakdownEntryDto {
+    pub fn list(entries: Vec<(String, i64)>) -> Vec<Self> {
+        entries.into_iter().map(|(name, clicks)| Self { name, clicks }).collect()
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct LinkClicksDto {
+    pub link_id: String,
+    pub clicks: i64,
+}
+
+/// Clicks over time. Every bucket in range is listed, empty ones included.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ClickSeriesDto {
+    pub bucket: Bucket,
+    /// First day, `YYYY-MM-DD`.
+    pub from: String,
+    /// Last day, included.
+    pub to: String,
+    /// Offset from UTC in minutes that bucket boundaries follow.
+    pub tz_offset: i32,
+    /// Whether bot clicks are counted.
+    pub include_bots: bool,
+    pub data: Vec<ClickBucketDto>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ClickBucketDto {
+    /// Start of the bucket, RFC 3339 with the requested offset.
+    pub start: String,
+    pub clicks: i64,
+}
+
+impl ClickSeriesDto {
+    pub fn new(query: &ClickSeriesQuery, buckets: Vec<ClickBucket>) -> Self {
+        let offset = query.offset();
+        Self {
+            bucket: query.bucket,
+            from: query.from.to_string(),
+            to: query.to.to_string(),
+            tz_offset: query.tz_offset,
+            include_bots: query.include_bots,
+            data: buckets
+                .into_iter()
+                .map(|b| ClickBucketDto {
+                    start: b
+                        .start
+                        .and_local_timezone(offset)
+                        .single()
+                        .map(|at| at.to_rfc3339())
+                        .unwrap_or_default(),
+                    clicks: b.clicks,
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use axum::{
+    extract::{Path, State},
+    http::{Method, StatusCode},
+    Json,
+};
+
+use super::dto::{CreateFrogolRequest, FrogolDto, FrogolSummaryDto, PageParams, Paginated, UpdateFrogolRequest};
+use super::{ApiJson, ApiQuery};
+use crate::{
+    errors::AppError,
+    middleware::auth::ApiUser,
+    routes::openapi::{schema, ApiRouter, Operation, JSON},
+    services::api_token_service::ApiScope,
+    state::AppState,
+};
+
+pub(super) fn routes() -> ApiRouter {
+    ApiRouter::new()
+        .route(
+            Operation::new(Method::GET, "/api/v1/frogols", "Frogols", "List your frogols")
+                .scope(ApiScope::FrogolsWrite)
+                .paginated()
+                .responds_with("200", "A page of frogols", JSON, schema::<Paginated<FrogolSummaryDto>>),
+            list_frogols,
+        )
+        .route(
+            Operation::new(Method::POST, "/api/v1/frogols", "Frogols", "Create a frogol")
+                .scope(ApiScope::FrogolsWrite)
+                .describe("The account's email address must be verified.")
+                .body(JSON, schema::<CreateFrogolRequest>)
+                .responds_with("201", "The new frogol", JSON, schema::<FrogolDto>),
+            create_frogol,
+        )
+        .route(
+            Operation::new(Method::GET, "/api/v1/frogols/:id", "Frogols", "Get a frogol")
+                .scope(ApiScope::FrogolsWrite)
+                .responds_with("200", "The frogol", JSON, schema::<FrogolDto>),
+            get_frogol,
+        )
+        .route(
+            Operation::new(Method::PATCH, "/api/v1/frogols/:id", "Frogols", "Update a frogol")
+                .scope(ApiScope::FrogolsWrite)
+                .body(JSON, schema::<UpdateFrogolRequest>)
+                .responds_with("200", "The updated frogol", JSON, schema::<FrogolDto>),
+            update_frogol,
+        )
+        .route(
+            Operation::new(Method::DELETE, "/api/v1/frogols/:id", "Frogols", "Delete a frogol")
+                .scope(ApiScope::FrogolsWrite)
+                .describe("Also deletes its links, clicks and leads.")
+                .responds("204", "Deleted"),
+            delete_frogol,
+        )
+}
+
+async fn list_frogols(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    ApiQuery(params): ApiQuery<PageParams>,
+) -> Result<Json<Paginated<FrogolSummaryDto>>, AppError> {
+    let user = caller.require(ApiScope::FrogolsWrite)?;
+    let page = params.resolve()?;
+    let (frogols, total) = state
+        .services
+        .frogol
+        .get_user_frogols_page(&user.id, page.limit(), page.offset())
+        .await?;
+    Ok(Json(page.wrap(frogols, total)))
+}
+
+async fn create_frogol(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    ApiJson(body): ApiJson<CreateFrogolRequest>,
+) -> Result<(StatusCode, Json<FrogolDto>), AppError> {
+    let user = caller.require(ApiScope::FrogolsWrite)?;
+    state.services.auth.ensure_can_publish(user)?;
+    let frogol = state
+        .services
+        .frogol
+        .create_frogol(&user.id, &body.slug, &body.display_name)
+        .await?;
+    Ok((StatusCode::CREATED, Json(frogol.into())))
+}
+
+async fn get_frogol(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    Path(id): Path<String>,
+) -> Result<Json<FrogolDto>, AppError> {
+    let user = caller.require(ApiScope::FrogolsWrite)?;
+    let frogol = state.services.frogol.get_owned_frogol(&user.id, &id).await?;
+    Ok(Json(frogol.into()))
+}
+
+async fn update_frogol(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    Path(id): Path<String>,
+    ApiJson(body): ApiJson<UpdateFrogolRequest>,
+) -> Result<Json<FrogolDto>, AppError> {
+    let user = caller.require(ApiScope::FrogolsWrite)?;
+    let frogol = state.services.frogol.get_owned_frogol(&user.id, &id).await?;
+
+    let display_name = body
+        .display_name
+        .or(frogol.display_name)
+        .unwrap_or_default();
+    let theme = body
+        .theme
+        .or(frogol.theme)
+        .unwrap_or_else(|| "default".to_string());
+    let avatar_url = body.avatar_url.unwrap_or(frogol.avatar_url);
+    let bio = body.bio.unwrap_or(frogol.bio);
+    let frogol = state
+        .services
+        .frogol
+        .update_frogol(&id, &display_name, &theme, avatar_url.as_deref(), bio.as_deref())
+        .await?;
+    Ok(Json(frogol.into()))
+}
+
+async fn delete_frogol(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let user = caller.require(ApiScope::FrogolsWrite)?;
+    state.services.frogol.get_owned_frogol(&user.id, &id).await?;
+    state.services.frogol.delete_frogol(&id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
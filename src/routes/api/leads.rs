@@ -0,0 +1,143 @@
+use axum::{
+    extract::{Path, State},
+    http::{Method, StatusCode},
+    Json,
+};
+
+use super::dto::{CreateLeadRequest, LeadDto, PageParams, Paginated, UpdateLeadRequest};
+use super::{ApiJson, ApiQuery};
+use crate::{
+    errors::AppError,
+    middleware::auth::ApiUser,
+    repo::lead_repo::Lead,
+    repo::user_repo::User,
+    routes::openapi::{schema, ApiRouter, Operation, JSON},
+    services::api_token_service::ApiScope,
+    state::AppState,
+};
+
+pub(super) fn routes() -> ApiRouter {
+    ApiRouter::new()
+        .route(
+            Operation::new(Method::GET, "/api/v1/frogols/:id/leads", "Leads", "List a frogol's leads")
+                .scope(ApiScope::LeadsWrite)
+                .describe("Newest first.")
+                .paginated()
+                .responds_with("200", "A page of leads", JSON, schema::<Paginated<LeadDto>>),
+            list_leads,
+        )
+        .route(
+            Operation::new(Method::POST, "/api/v1/frogols/:id/leads", "Leads", "Record a lead")
+                .scope(ApiScope::LeadsWrite)
+                .body(JSON, schema::<CreateLeadRequest>)
+                .responds_with("201", "The new lead", JSON, schema::<LeadDto>),
+            create_lead,
+        )
+        .route(
+            Operation::new(Method::GET, "/api/v1/leads/:id", "Leads", "Get a lead")
+                .scope(ApiScope::LeadsWrite)
+                .responds_with("200", "The lead", JSON, schema::<LeadDto>),
+            get_lead,
+        )
+        .route(
+            Operation::new(Method::PATCH, "/api/v1/leads/:id", "Leads", "Update a lead")
+                .scope(ApiScope::LeadsWrite)
+                .body(JSON, schema::<UpdateLeadRequest>)
+                .responds_with("200", "The updated lead", JSON, schema::<LeadDto>),
+            update_lead,
+        )
+        .route(
+            Operation::new(Method::DELETE, "/api/v1/leads/:id", "Leads", "Delete a lead")
+                .scope(ApiScope::LeadsWrite)
+                .responds("204", "Deleted"),
+            delete_lead,
+        )
+}
+
+/// Loads a lead whose frogol belongs to `user`.
+async fn owned_lead(state: &AppState, user: &User, id: &str) -> Result<Lead, AppError> {
+    let lead = state
+        .services
+        .lead
+        .get_lead(id)
+        .await
+        .map_err(|e| e.not_found_if_missing("Lead not found"))?;
+    state.services.frogol.get_owned_frogol(&user.id, &lead.frogol_id).await?;
+    Ok(lead)
+}
+
+/// The frogol's leads, newest first.
+async fn list_leads(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    Path(frogol_id): Path<String>,
+    ApiQuery(params): ApiQuery<PageParams>,
+) -> Result<Json<Paginated<LeadDto>>, AppError> {
+    let user = caller.require(ApiScope::LeadsWrite)?;
+    let page = params.resolve()?;
+    state.services.frogol.get_owned_frogol(&user.id, &frogol_id).await?;
+    let (leads, total) = state
+        .services
+        .lead
+        .get_frogol_leads_page(&frogol_id, page.limit(), page.offset())
+        .await?;
+    Ok(Json(page.wrap(leads, total)))
+}
+
+async fn create_lead(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    Path(frogol_id): Path<String>,
+    ApiJson(body): ApiJson<CreateLeadRequest>,
+) -> Result<(StatusCode, Json<LeadDto>), AppError> {
+    let user = caller.require(ApiScope::LeadsWrite)?;
+    state.services.frogol.get_owned_frogol(&user.id, &frogol_id).await?;
+    let lead = state
+        .services
+        .lead
+        .capture_lead(&frogol_id, &body.email, body.source.as_deref(), body.message.as_deref(), None)
+        .await?;
+    Ok((StatusCode::CREATED, Json(lead.into())))
+}
+
+async fn get_lead(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    Path(id): Path<String>,
+) -> Result<Json<LeadDto>, AppError> {
+    let user = caller.require(ApiScope::LeadsWrite)?;
+    let lead = owned_lead(&state, user, &id).await?;
+    Ok(Json(lead.into()))
+}
+
+async fn update_lead(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    Path(id): Path<String>,
+    ApiJson(body): ApiJson<UpdateLeadRequest>,
+) -> Result<Json<LeadDto>, AppError> {
+    let user = caller.require(ApiScope::LeadsWrite)?;
+    let lead = owned_lead(&state, user, &id).await?;
+
+    let email = body.email.unwrap_or(lead.email);
+    let source = body.source.unwrap_or(lead.source);
+    let score = body.score.unwrap_or(lead.score);
+    let message = body.message.unwrap_or(lead.message);
+    let lead = state
+        .services
+        .lead
+        .update_lead(&id, &email, source.as_deref(), score, message.as_deref())
+        .await?;
+    Ok(Json(lead.into()))
+}
+
+async fn delete_lead(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let user = caller.require(ApiScope::LeadsWrite)?;
+    owned_lead(&state, user, &id).await?;
+    state.services.lead.delete_lead(&id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
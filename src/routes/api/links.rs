@@ -0,0 +1,155 @@
+use axum::{
+    extract::{Path, State},
+    http::{Method, StatusCode},
+    Json,
+};
+
+use super::dto::{BotParams, ClickDto, CreateLinkRequest, LinkDto, PageParams, Paginated, UpdateLinkRequest};
+use super::{ApiJson, ApiQuery};
+use crate::{
+    errors::AppError,
+    middleware::auth::ApiUser,
+    routes::openapi::{schema, ApiRouter, Operation, JSON},
+    services::api_token_service::ApiScope,
+    state::AppState,
+};
+
+pub(super) fn routes() -> ApiRouter {
+    ApiRouter::new()
+        .route(
+            Operation::new(Method::GET, "/api/v1/frogols/:id/links", "Links", "List a frogol's links")
+                .scope(ApiScope::LinksWrite)
+                .describe("Inactive links are included, in display order.")
+                .paginated()
+                .responds_with("200", "A page of links", JSON, schema::<Paginated<LinkDto>>),
+            list_links,
+        )
+        .route(
+            Operation::new(Method::POST, "/api/v1/frogols/:id/links", "Links", "Add a link")
+                .scope(ApiScope::LinksWrite)
+                .body(JSON, schema::<CreateLinkRequest>)
+                .responds_with("201", "The new link", JSON, schema::<LinkDto>),
+            create_link,
+        )
+        .route(
+            Operation::new(Method::GET, "/api/v1/links/:id", "Links", "Get a link")
+                .scope(ApiScope::LinksWrite)
+                .responds_with("200", "The link", JSON, schema::<LinkDto>),
+            get_link,
+        )
+        .route(
+            Operation::new(Method::PATCH, "/api/v1/links/:id", "Links", "Update a link")
+                .scope(ApiScope::LinksWrite)
+                .body(JSON, schema::<UpdateLinkRequest>)
+                .responds_with("200", "The updated link", JSON, schema::<LinkDto>),
+            update_link,
+        )
+        .route(
+            Operation::new(Method::DELETE, "/api/v1/links/:id", "Links", "Delete a link")
+                .scope(ApiScope::LinksWrite)
+                .responds("204", "Deleted"),
+            delete_link,
+        )
+        .route(
+            Operation::new(Method::GET, "/api/v1/links/:id/clicks", "Analytics", "List a link's clicks")
+                .scope(ApiScope::AnalyticsRead)
+                .describe("Newest first. Bot clicks are listed only with `include_bots`.")
+                .paginated()
+                .bots()
+                .responds_with("200", "A page of clicks", JSON, schema::<Paginated<ClickDto>>),
+            list_clicks,
+        )
+}
+
+/// All of the frogol's links, inactive ones included, in display order.
+async fn list_links(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    Path(frogol_id): Path<String>,
+    ApiQuery(params): ApiQuery<PageParams>,
+) -> Result<Json<Paginated<LinkDto>>, AppError> {
+    let user = caller.require(ApiScope::LinksWrite)?;
+    let page = params.resolve()?;
+    state.services.frogol.get_owned_frogol(&user.id, &frogol_id).await?;
+    let (links, total) = state
+        .services
+        .frogol
+        .get_links_page(&frogol_id, page.limit(), page.offset())
+        .await?;
+    Ok(Json(page.wrap(links, total)))
+}
+
+async fn create_link(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    Path(frogol_id): Path<String>,
+    ApiJson(body): ApiJson<CreateLinkRequest>,
+) -> Result<(StatusCode, Json<LinkDto>), AppError> {
+    let user = caller.require(ApiScope::LinksWrite)?;
+    state.services.frogol.get_owned_frogol(&user.id, &frogol_id).await?;
+    let link = state.services.frogol.add_link(&frogol_id, &body.url, &body.label).await?;
+    Ok((StatusCode::CREATED, Json(link.into())))
+}
+
+async fn get_link(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    Path(id): Path<String>,
+) -> Result<Json<LinkDto>, AppError> {
+    let user = caller.require(ApiScope::LinksWrite)?;
+    let link = state.services.frogol.get_owned_link(&user.id, &id).await?;
+    Ok(Json(link.into()))
+}
+
+async fn update_link(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    Path(id): Path<String>,
+    ApiJson(body): ApiJson<UpdateLinkRequest>,
+) -> Result<Json<LinkDto>, AppError> {
+    let user = caller.require(ApiScope::LinksWrite)?;
+    let link = state.services.frogol.get_owned_link(&user.id, &id).await?;
+
+    let mut link = if body.url.is_some() || body.label.is_some() {
+        let url = body.url.unwrap_or(link.url);
+        let label = body.label.unwrap_or(link.label);
+        state.services.frogol.update_link(&id, &url, &label).await?
+    } else {
+        link
+    };
+    if let Some(active) = body.is_active {
+        state.services.frogol.set_link_active(&id, active).await?;
+        link.is_active = active;
+    }
+    Ok(Json(link.into()))
+}
+
+async fn delete_link(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let user = caller.require(ApiScope::LinksWrite)?;
+    state.services.frogol.get_owned_link(&user.id, &id).await?;
+    state.services.frogol.delete_link(&id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The link's clicks, newest first. Bot clicks are listed only when asked.
+async fn list_clicks(
+    State(state): State<AppState>,
+    caller: ApiUser,
+    Path(id): Path<String>,
+    ApiQuery(params): ApiQuery<PageParams>,
+    ApiQuery(bots): ApiQuery<BotParams>,
+) -> Result<Json<Paginated<ClickDto>>, AppError> {
+    let user = caller.require(ApiScope::AnalyticsRead)?;
+    let page = params.resolve()?;
+    state.services.frogol.get_owned_link(&user.id, &id).await?;
+    let (clicks, total) = state
+        .services
+        .frogol
+        .get_link_clicks(&id, bots.include_bots, page.limit(), page.offset())
+        .await?;
+    Ok(Json(page.wrap(clicks, total)))
+}
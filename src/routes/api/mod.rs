@@ -0,0 +1,75 @@
+//! Versioned JSON API under `/api/v1`.
+//!
+//! Every route answers with JSON built from the DTOs in `dto`, never with the
+//! repo structs directly, and every failure is an `AppError` body. Callers
+//! authenticate with a personal access token or the session cookie (see
+//! `ApiUser`); cookie sessions also need the CSRF header on writes.
+
+pub mod dto;
+
+mod analytics;
+mod frogols;
+mod leads;
+mod links;
+
+use axum::{
+    async_trait,
+    extract::{FromRequest, FromRequestParts, Request},
+    http::request::Parts,
+    Json,
+};
+use axum::extract::Query;
+use serde::de::DeserializeOwned;
+use validator::Validate;
+
+use crate::{errors::AppError, routes::openapi::ApiRouter};
+
+pub fn api_v1_routes() -> ApiRouter {
+    ApiRouter::new()
+        .merge(frogols::routes())
+        .merge(links::routes())
+        .merge(leads::routes())
+        .merge(analytics::routes())
+}
+
+/// `Json` whose rejections are reported as `AppError::InvalidInput`, so a bad
+/// body gets the same error shape as every other API failure. Bodies that
+/// parse are then checked with `validator`, failing with
+/// `AppError::InvalidFields`.
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(|rejection| AppError::InvalidInput(rejection.body_text()))?;
+        value.validate()?;
+        Ok(ApiJson(value))
+    }
+}
+
+/// `Query` with the same error handling as `ApiJson`.
+pub struct ApiQuery<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Query::<T>::from_request_parts(parts, state)
+            .await
+            .map(|Query(value)| ApiQuery(value))
+            .map_err(|rejection| AppError::InvalidInput(rejection.body_text()))
+    }
+}
@@ -1,15 +1,22 @@
 use axum::{
-    extract::{State, Form},
+    extract::{Path, Query, State, Form},
+    http::{header, HeaderMap},
     response::Redirect,
     routing::{get, post},
     Router,
 };
 use askama::Template;
-use askama_axum::IntoResponse;
+use askama_axum::{IntoResponse, Response};
 use serde::Deserialize;
 use validator::Validate;
 use crate::state::AppState;
 use crate::errors::AppError;
+use crate::middleware::auth::AUTH_COOKIE;
+use crate::middleware::client_ip::ClientIp;
+use crate::middleware::csrf::CsrfToken;
+use crate::middleware::security_headers::CspNonce;
+use crate::repo::user_repo::Session;
+use crate::services::auth_service::{ClientInfo, LoginOutcome, INVALID_SECOND_FACTOR};
 use tower_cookies::{Cookies, Cookie};
 use time::Duration;
 
@@ -17,12 +24,65 @@ use time::Duration;
 #[template(path = "auth/login.html")]
 struct LoginTemplate {
     error_msg: String,
+    notice: String,
+    csrf_token: String,
+    csp_nonce: String,
+}
+
+#[derive(Template)]
+#[template(path = "auth/two_factor.html")]
+struct TwoFactorTemplate {
+    error_msg: String,
+    csrf_token: String,
+    csp_nonce: String,
 }
 
 #[derive(Template)]
 #[template(path = "auth/register.html")]
 struct RegisterTemplate {
     error_msg: String,
+    csrf_token: String,
+    csp_nonce: String,
+}
+
+#[derive(Template)]
+#[template(path = "auth/forgot_password.html")]
+struct ForgotPasswordTemplate {
+    error_msg: String,
+    sent: bool,
+    csrf_token: String,
+    csp_nonce: String,
+}
+
+#[derive(Template)]
+#[template(path = "auth/reset_password.html")]
+struct ResetPasswordTemplate {
+    token: String,
+    error_msg: String,
+    csrf_token: String,
+    csp_nonce: String,
+}
+
+#[derive(Template)]
+#[template(path = "auth/resend_verification.html")]
+struct ResendVerificationTemplate {
+    error_msg: String,
+    sent: bool,
+    csrf_token: String,
+    csp_nonce: String,
+}
+
+#[derive(Template)]
+#[template(path = "auth/verify_email.html")]
+struct VerifyEmailTemplate {
+    email: String,
+    error_msg: String,
+    csp_nonce: String,
+}
+
+#[derive(Deserialize)]
+pub struct LoginQuery {
+    registered: Option<String>,
 }
 
 #[derive(Deserialize, Validate)]
@@ -30,6 +90,13 @@ pub struct LoginForm {
     #[validate(email)]
     email: String,
     password: String,
+    /// Checkbox: present ("on") when ticked.
+    remember_me: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TwoFactorForm {
+    code: String,
 }
 
 #[derive(Deserialize, Validate)]
@@ -40,48 +107,133 @@ pub struct RegisterForm {
     password: String,
 }
 
+#[derive(Deserialize, Validate)]
+pub struct ForgotPasswordForm {
+    #[validate(email)]
+    email: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ResendVerificationForm {
+    #[validate(email)]
+    email: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ResetPasswordForm {
+    #[validate(length(min = 6))]
+    password: String,
+    password_confirm: String,
+}
+
+#[allow(dead_code)]
 #[derive(serde::Serialize)]
 pub struct AuthResponse {
     success: bool,
     message: String,
 }
 
+/// Short-lived cookie carrying the pending second-factor challenge.
+const LOGIN_CHALLENGE_COOKIE: &str = "login_challenge";
+
 pub fn auth_routes() -> Router<AppState> {
     Router::new()
         .route("/login", get(show_login).post(login))
+        .route("/login/2fa", get(show_two_factor).post(two_factor))
         .route("/register", get(show_register).post(register))
         .route("/logout", post(logout))
+        .route("/forgot-password", get(show_forgot_password).post(forgot_password))
+        .route("/reset-password/:token", get(show_reset_password).post(reset_password))
+        .route("/verify-email/resend", get(show_resend_verification).post(resend_verification))
+        .route("/verify-email/:token", get(verify_email))
 }
 
-async fn show_login() -> impl IntoResponse {
+async fn show_login(
+    Query(query): Query<LoginQuery>,
+    CsrfToken(csrf_token): CsrfToken,
+    CspNonce(csp_nonce): CspNonce,
+) -> impl IntoResponse {
+    let notice = if query.registered.is_some() {
+        "Account created. We've emailed you a link to confirm your address."
+    } else {
+        ""
+    };
     LoginTemplate {
         error_msg: "".to_string(),
+        notice: notice.to_string(),
+        csrf_token,
+        csp_nonce,
     }
 }
 
-async fn show_register() -> impl IntoResponse {
+async fn show_register(CsrfToken(csrf_token): CsrfToken, CspNonce(csp_nonce): CspNonce) -> impl IntoResponse {
     RegisterTemplate {
         error_msg: "".to_string(),
+        csrf_token,
+        csp_nonce,
+    }
+}
+
+/// Address and user agent of the browser signing in, recorded on its session.
+fn client_info(ClientIp(ip): ClientIp, headers: &HeaderMap) -> ClientInfo {
+    ClientInfo {
+        ip_address: ip.map(|ip| ip.to_string()),
+        user_agent: headers
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|ua| ua.chars().take(512).collect()),
     }
 }
 
 async fn login(
     State(state): State<AppState>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
     cookies: Cookies,
+    CsrfToken(csrf_token): CsrfToken,
+    CspNonce(csp_nonce): CspNonce,
     Form(form): Form<LoginForm>,
-) -> Result<impl IntoResponse, AppError> {
-    // Validate form
-    if let Err(errors) = form.validate() {
-        return Err(AppError::InvalidInput(format!("Validation errors: {:?}", errors)));
-    }
+) -> Result<Response, AppError> {
+    form.validate()?;
 
     // Attempt login
-    let session = state.services.auth.login(&form.email, &form.password).await?;
+    let client = client_info(client_ip, &headers);
+    let remember_me = form.remember_me.is_some();
+    let outcome = match state.services.auth.login(&form.email, &form.password, remember_me, &client).await {
+        Ok(outcome) => outcome,
+        // Wrong credentials, a disabled account, or unverified under the login
+        // policy: explain on the form rather than fail opaquely
+        Err(AppError::Unauthorized(error_msg)) | Err(AppError::Forbidden(error_msg)) => {
+            return Ok(LoginTemplate { error_msg, notice: "".to_string(), csrf_token, csp_nonce }.into_response());
+        }
+        Err(e) => return Err(e),
+    };
+
+    match outcome {
+        LoginOutcome::Authenticated(session) => {
+            set_auth_cookie(&state, &cookies, session);
+            Ok(Redirect::to("/dashboard").into_response())
+        }
+        LoginOutcome::TwoFactorRequired { challenge } => {
+            let mut cookie = Cookie::new(LOGIN_CHALLENGE_COOKIE, challenge);
+            cookie.set_http_only(true);
+            cookie.set_path("/login");
+            cookie.set_max_age(Duration::minutes(5));
+            cookies.add(cookie);
+            Ok(Redirect::to("/login/2fa").into_response())
+        }
+    }
+}
 
-    // Set auth token cookie
-    let mut cookie = Cookie::new("auth_token", session.token);
+/// Remembered sessions get a cookie that outlives the browser; others end with it.
+fn set_auth_cookie(state: &AppState, cookies: &Cookies, session: Session) {
+    let mut cookie = Cookie::new(AUTH_COOKIE, session.token);
     cookie.set_http_only(true);
     cookie.set_path("/");
+    if session.remember_me {
+        let max_age = state.services.auth.session_max_age();
+        cookie.set_max_age(Duration::seconds(max_age.num_seconds()));
+    }
     // In production, consider setting Secure and SameSite to strengthen CSRF defenses
     #[cfg(not(debug_assertions))]
     {
@@ -93,41 +245,202 @@ async fn login(
         cookie.set_same_site(SameSite::Strict);
     }
     cookies.add(cookie);
+}
 
-    // In a real app, you'd set a cookie here
-    // For now, we'll just redirect to dashboard
-    Ok(Redirect::to("/dashboard"))
+async fn show_two_factor(
+    cookies: Cookies,
+    CsrfToken(csrf_token): CsrfToken,
+    CspNonce(csp_nonce): CspNonce,
+) -> Response {
+    if cookies.get(LOGIN_CHALLENGE_COOKIE).is_none() {
+        return Redirect::to("/login").into_response();
+    }
+    TwoFactorTemplate { error_msg: "".to_string(), csrf_token, csp_nonce }.into_response()
+}
+
+async fn two_factor(
+    State(state): State<AppState>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
+    cookies: Cookies,
+    CsrfToken(csrf_token): CsrfToken,
+    CspNonce(csp_nonce): CspNonce,
+    Form(form): Form<TwoFactorForm>,
+) -> Result<Response, AppError> {
+    let Some(challenge) = cookies.get(LOGIN_CHALLENGE_COOKIE).map(|c| c.value().to_string()) else {
+        return Ok(Redirect::to("/login").into_response());
+    };
+
+    let client = client_info(client_ip, &headers);
+    match state.services.auth.complete_two_factor_login(&challenge, &form.code, &client).await {
+        Ok(session) => {
+            let mut expired = Cookie::from(LOGIN_CHALLENGE_COOKIE);
+            expired.set_max_age(Duration::seconds(0));
+            expired.set_path("/login");
+            cookies.add(expired);
+            set_auth_cookie(&state, &cookies, session);
+            Ok(Redirect::to("/dashboard").into_response())
+        }
+        Err(AppError::Unauthorized(error_msg)) if error_msg == INVALID_SECOND_FACTOR => {
+            Ok(TwoFactorTemplate { error_msg, csrf_token, csp_nonce }.into_response())
+        }
+        Err(AppError::Unauthorized(error_msg)) => {
+            Ok(LoginTemplate { error_msg, notice: "".to_string(), csrf_token, csp_nonce }.into_response())
+        }
+        Err(e) => Err(e),
+    }
 }
 
 async fn register(
     State(state): State<AppState>,
     Form(form): Form<RegisterForm>,
 ) -> Result<impl IntoResponse, AppError> {
-    // Validate form
-    if let Err(errors) = form.validate() {
-        return Err(AppError::InvalidInput(format!("Validation errors: {:?}", errors)));
-    }
+    form.validate()?;
 
     // Attempt registration
     let _user = state.services.auth.register(&form.email, &form.password).await?;
 
     // Redirect to login
-    Ok(Redirect::to("/login"))
+    Ok(Redirect::to("/login?registered=1"))
 }
 
 async fn logout(
     State(state): State<AppState>,
     cookies: Cookies,
 ) -> Result<impl IntoResponse, AppError> {
-    if let Some(c) = cookies.get("auth_token") {
+    if let Some(c) = cookies.get(AUTH_COOKIE) {
         let token = c.value().to_string();
         // best-effort delete on server
         let _ = state.services.auth.logout(&token).await;
         // remove client cookie
-        let mut expired = Cookie::from("auth_token");
+        let mut expired = Cookie::from(AUTH_COOKIE);
         expired.set_max_age(Duration::seconds(0));
         expired.set_path("/");
         cookies.add(expired);
     }
     Ok(Redirect::to("/login"))
-} 
\ No newline at end of file
+}
+
+async fn show_forgot_password(CsrfToken(csrf_token): CsrfToken, CspNonce(csp_nonce): CspNonce) -> impl IntoResponse {
+    ForgotPasswordTemplate {
+        error_msg: "".to_string(),
+        sent: false,
+        csrf_token,
+        csp_nonce,
+    }
+}
+
+async fn forgot_password(
+    State(state): State<AppState>,
+    CsrfToken(csrf_token): CsrfToken,
+    CspNonce(csp_nonce): CspNonce,
+    Form(form): Form<ForgotPasswordForm>,
+) -> Result<impl IntoResponse, AppError> {
+    if form.validate().is_err() {
+        return Ok(ForgotPasswordTemplate {
+            error_msg: "Please enter a valid email address".to_string(),
+            sent: false,
+            csrf_token,
+            csp_nonce,
+        });
+    }
+
+    // Same response, as quickly, whether or not the account exists
+    state.services.auth.spawn_password_reset(form.email);
+    Ok(ForgotPasswordTemplate {
+        error_msg: "".to_string(),
+        sent: true,
+        csrf_token,
+        csp_nonce,
+    })
+}
+
+async fn show_reset_password(
+    Path(token): Path<String>,
+    CsrfToken(csrf_token): CsrfToken,
+    CspNonce(csp_nonce): CspNonce,
+) -> impl IntoResponse {
+    ResetPasswordTemplate {
+        token,
+        error_msg: "".to_string(),
+        csrf_token,
+        csp_nonce,
+    }
+}
+
+async fn reset_password(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    CsrfToken(csrf_token): CsrfToken,
+    CspNonce(csp_nonce): CspNonce,
+    Form(form): Form<ResetPasswordForm>,
+) -> Result<Response, AppError> {
+    let error_msg = if form.validate().is_err() {
+        Some("Password must be at least 6 characters".to_string())
+    } else if form.password != form.password_confirm {
+        Some("Passwords do not match".to_string())
+    } else {
+        match state.services.auth.reset_password(&token, &form.password).await {
+            Ok(()) => None,
+            Err(AppError::InvalidInput(msg)) => Some(msg),
+            Err(e) => return Err(e),
+        }
+    };
+
+    match error_msg {
+        Some(error_msg) => Ok(ResetPasswordTemplate { token, error_msg, csrf_token, csp_nonce }.into_response()),
+        None => Ok(Redirect::to("/login").into_response()),
+    }
+}
+
+async fn show_resend_verification(
+    CsrfToken(csrf_token): CsrfToken,
+    CspNonce(csp_nonce): CspNonce,
+) -> impl IntoResponse {
+    ResendVerificationTemplate {
+        error_msg: "".to_string(),
+        sent: false,
+        csrf_token,
+        csp_nonce,
+    }
+}
+
+async fn resend_verification(
+    State(state): State<AppState>,
+    CsrfToken(csrf_token): CsrfToken,
+    CspNonce(csp_nonce): CspNonce,
+    Form(form): Form<ResendVerificationForm>,
+) -> Result<impl IntoResponse, AppError> {
+    if form.validate().is_err() {
+        return Ok(ResendVerificationTemplate {
+            error_msg: "Please enter a valid email address".to_string(),
+            sent: false,
+            csrf_token,
+            csp_nonce,
+        });
+    }
+
+    state.services.auth.resend_email_verification(&form.email).await?;
+
+    // Same response whether or not the account exists or is already verified
+    Ok(ResendVerificationTemplate {
+        error_msg: "".to_string(),
+        sent: true,
+        csrf_token,
+        csp_nonce,
+    })
+}
+
+async fn verify_email(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    CspNonce(csp_nonce): CspNonce,
+) -> Result<impl IntoResponse, AppError> {
+    match state.services.auth.verify_email(&token).await {
+        Ok(user) => Ok(VerifyEmailTemplate { email: user.email, error_msg: "".to_string(), csp_nonce }),
+        Err(AppError::InvalidInput(error_msg)) => {
+            Ok(VerifyEmailTemplate { email: "".to_string(), error_msg, csp_nonce })
+        }
+        Err(e) => Err(e),
+    }
+}
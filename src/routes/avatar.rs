@@ -1,27 +1,65 @@
 use axum::{
-    extract::{Path, State, multipart::Multipart},
+    extract::{DefaultBodyLimit, Path, State, multipart::Multipart},
+    handler::Handler,
+    http::Method,
     response::Response,
-    routing::post,
-    Router,
 };
 use axum::response::IntoResponse;
 
-use uuid::Uuid;
+use schemars::JsonSchema;
+use serde::Serialize;
 use crate::{
+    config::AvatarConfig,
     errors::AppError,
+    middleware::auth::AuthUser,
+    routes::openapi::{inline, schema, ApiRouter, Operation, JSON},
     state::AppState,
 };
 
-pub fn avatar_routes() -> Router<AppState> {
-    Router::new()
-        .route("/api/frogol/:id/avatar", post(upload_avatar))
+#[derive(Serialize, JsonSchema)]
+pub struct AvatarUploadResponse {
+    pub success: bool,
+    /// Public URL of the stored image.
+    pub avatar_url: String,
+}
+
+/// Room for the multipart framing around the image itself.
+const MULTIPART_OVERHEAD_BYTES: usize = 64 * 1024;
+
+pub fn avatar_routes(config: &AvatarConfig) -> ApiRouter {
+    ApiRouter::new().route(
+        Operation::new(Method::POST, "/api/frogol/:id/avatar", "Dashboard", "Upload an avatar")
+            .session_only()
+            .describe("A JPEG, PNG, GIF or WebP image of at most 5 MB, in the first multipart field.")
+            .body("multipart/form-data", |_| {
+                inline(serde_json::json!({
+                    "type": "object",
+                    "properties": {"avatar": {"type": "string", "format": "binary"}}
+                }))
+            })
+            .responds_with("200", "The stored avatar", JSON, schema::<AvatarUploadResponse>),
+        upload_avatar.layer(DefaultBodyLimit::max(config.max_upload_bytes + MULTIPART_OVERHEAD_BYTES)),
+    )
+}
+
+/// `5 MB`, or `512 KB` for limits that are not whole megabytes.
+fn describe_size(bytes: usize) -> String {
+    const MB: usize = 1024 * 1024;
+    if bytes.is_multiple_of(MB) {
+        format!("{} MB", bytes / MB)
+    } else {
+        format!("{} KB", bytes / 1024)
+    }
 }
 
 async fn upload_avatar(
     State(state): State<AppState>,
+    AuthUser(user): AuthUser,
     Path(frogol_id): Path<String>,
     mut multipart: Multipart,
 ) -> Result<Response, AppError> {
+    state.services.frogol.get_owned_frogol(&user.id, &frogol_id).await?;
+
     // Find the avatar field in the multipart data
     let avatar_field = multipart.next_field().await.map_err(|e| {
         tracing::error!("Multipart error: {}", e);
@@ -40,32 +78,29 @@ async fn upload_avatar(
         AppError::Internal("Failed to read uploaded file".to_string())
     })?;
     
-    // Validate file size (5MB limit)
-    if file_data.len() > 5 * 1024 * 1024 {
-        return Err(AppError::ValidationError("File size must be less than 5MB".to_string()));
+    // Validate file size
+    let max_upload_bytes = state.services.avatar.max_upload_bytes();
+    if file_data.len() > max_upload_bytes {
+        return Err(AppError::PayloadTooLarge(format!(
+            "File size must be at most {}",
+            describe_size(max_upload_bytes)
+        )));
     }
     
     // Validate content type
     let allowed_types = ["image/jpeg", "image/png", "image/gif", "image/webp"];
     if let Some(ct) = &content_type {
         if !allowed_types.contains(&ct.as_str()) {
-            return Err(AppError::ValidationError("Only JPEG, PNG, GIF, and WebP images are allowed".to_string()));
+            return Err(AppError::UnsupportedMediaType("Only JPEG, PNG, GIF, and WebP images are allowed".to_string()));
         }
     }
     
-    // Generate unique filename
+    // Save under a unique filename in the avatars directory
     let extension = std::path::Path::new(&original_filename)
         .extension()
         .and_then(std::ffi::OsStr::to_str)
         .unwrap_or("bin");
-    let unique_filename = format!("{}.{}", Uuid::new_v4(), extension);
-    
-    // Save file to avatars directory
-    let avatar_path = std::path::Path::new("static/avatars").join(&unique_filename);
-    tokio::fs::write(&avatar_path, &file_data).await.map_err(|e| {
-        tracing::error!("Failed to save avatar file: {}", e);
-        AppError::Internal("Failed to save uploaded file".to_string())
-    })?;
+    let unique_filename = state.services.avatar.save_upload(extension, &file_data).await?;
     
     // Get the URL for the uploaded image
     let avatar_url = format!("/static/avatars/{}", unique_filename);
@@ -74,10 +109,10 @@ async fn upload_avatar(
     state.services.frogol.update_frogol_avatar_url(&frogol_id, &avatar_url).await?;
     
     // Return the new avatar URL as JSON
-    let response = serde_json::json!({
-        "success": true,
-        "avatar_url": avatar_url
-    });
+    let response = AvatarUploadResponse {
+        success: true,
+        avatar_url,
+    };
     
     Ok(axum::response::Json(response).into_response())
 }
@@ -1,20 +1,29 @@
 use axum::{
     extract::{Path, State},
+    http::Method,
     response::Response,
     routing::{get, delete},
     Form, Router,
 };
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use askama::Template;
 use askama_axum::IntoResponse;
 use chrono::DateTime;
 use crate::{
     errors::AppError,
+    middleware::{auth::AuthUser, csrf::CsrfToken, security_headers::CspNonce},
+    routes::api::ApiQuery,
+    routes::openapi::{schema, ApiRouter, Operation, FORM},
+    services::{
+        auth_service::timestamp,
+        click_series::{Bucket, ClickBucket, ClickSeriesParams, ClickSeriesQuery},
+    },
     state::AppState,
     repo::{
         frogol_repo::FrogolSummary,
         lead_repo::LeadSummary,
-        click_repo::ClickStats,
+        click_repo::{ClickBreakdown, ClickScope, ClickStats},
     },
 };
 
@@ -26,11 +35,15 @@ struct DashboardTemplate {
     frogols_count: usize,
     total_leads: i64,
     total_clicks: i64,
+    csp_nonce: String,
 }
 
 #[derive(Template)]
 #[template(path = "dashboard/create_frogol.html")]
-struct CreateFrogolTemplate;
+struct CreateFrogolTemplate {
+    csrf_token: String,
+    csp_nonce: String,
+}
 
 #[derive(Template)]
 #[template(path = "dashboard/frogol.html")]
@@ -41,6 +54,9 @@ struct FrogolDetailTemplate {
     leads: Vec<LeadSummary>,
     leads_count: usize,
     click_stats: ClickStats,
+    chart: ClickChart,
+    breakdown: ClickBreakdown,
+    csp_nonce: String,
 }
 
 #[derive(Serialize)]
@@ -69,6 +85,7 @@ struct LinkDetail {
 #[template(path = "dashboard/edit_frogol.html")]
 struct EditFrogolTemplate {
     frogol: FrogolDetail,
+    csp_nonce: String,
 }
 
 #[derive(Template)]
@@ -79,21 +96,71 @@ struct AnalyticsTemplate {
     total_leads: i64,
     total_clicks: i64,
     top_frogols: Vec<FrogolSummary>,
+    chart: ClickChart,
+    csp_nonce: String,
 }
 
-#[derive(Template)]
-#[template(path = "dashboard/settings.html")]
-struct SettingsTemplate {
-    user_email: String,
+/// A click series drawn as bars by `dashboard/partials/click-chart.html`.
+struct ClickChart {
+    bars: Vec<ChartBar>,
+    total: i64,
+    bucket: &'static str,
+    from: String,
+    to: String,
+    tz_offset: i32,
+    include_bots: bool,
+    /// `UTC` or an offset such as `+02:00`.
+    zone: String,
+    first_label: String,
+    last_label: String,
+}
+
+struct ChartBar {
+    label: String,
+    clicks: i64,
+    /// Percent of the chart's height.
+    height: i64,
+}
+
+impl ClickChart {
+    fn new(query: &ClickSeriesQuery, buckets: Vec<ClickBucket>) -> Self {
+        let label_format = match query.bucket {
+            Bucket::Hour => "%b %d, %H:00",
+            Bucket::Day => "%b %d",
+            Bucket::Week => "Week of %b %d",
+        };
+        let peak = buckets.iter().map(|b| b.clicks).max().unwrap_or(0);
+        let bars: Vec<ChartBar> = buckets
+            .iter()
+            .map(|b| ChartBar {
+                label: b.start.format(label_format).to_string(),
+                clicks: b.clicks,
+                // Keep any click visible
+                height: if b.clicks == 0 { 0 } else { (b.clicks * 100 / peak).max(2) },
+            })
+            .collect();
+        Self {
+            total: buckets.iter().map(|b| b.clicks).sum(),
+            bucket: query.bucket.as_str(),
+            from: query.from.to_string(),
+            to: query.to.to_string(),
+            tz_offset: query.tz_offset,
+            include_bots: query.include_bots,
+            zone: if query.tz_offset == 0 { "UTC".to_string() } else { query.offset().to_string() },
+            first_label: bars.first().map(|b| b.label.clone()).unwrap_or_default(),
+            last_label: bars.last().map(|b| b.label.clone()).unwrap_or_default(),
+            bars,
+        }
+    }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct CreateFrogolForm {
     display_name: String,
     slug: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct UpdateFrogolForm {
     display_name: String,
     theme: String,
@@ -110,32 +177,44 @@ fn format_date(date_str: &str) -> String {
     }
 }
 
-pub fn dashboard_routes() -> Router<AppState> {
-    Router::new()
+/// The dashboard pages. The forms they submit are documented; the pages are not.
+pub fn dashboard_routes() -> ApiRouter {
+    let pages = Router::new()
         .route("/dashboard", get(show_dashboard))
-        .route("/dashboard/frogol/new", get(show_create_frogol).post(create_frogol))
+        .route("/dashboard/frogol/new", get(show_create_frogol))
         .route("/dashboard/frogol/:id", get(show_frogol_detail))
-        .route("/dashboard/frogol/:id/edit", get(show_edit_frogol).put(update_frogol))
+        .route("/dashboard/frogol/:id/edit", get(show_edit_frogol))
         .route("/dashboard/frogol/:id/delete", delete(delete_frogol))
-        .route("/dashboard/analytics", get(show_analytics))
-        .route("/dashboard/settings", get(show_settings))
+        .route("/dashboard/analytics", get(show_analytics));
+
+    ApiRouter::new()
+        .undocumented(pages)
+        .route(
+            Operation::new(Method::POST, "/dashboard/frogol/new", "Dashboard", "Create a frogol from a form")
+                .session_only()
+                .body(FORM, schema::<CreateFrogolForm>)
+                .responds("303", "Redirect to the new frogol's dashboard page"),
+            create_frogol,
+        )
+        .route(
+            Operation::new(Method::PUT, "/dashboard/frogol/:id/edit", "Dashboard", "Update a frogol from a form")
+                .session_only()
+                .body(FORM, schema::<UpdateFrogolForm>)
+                .responds("303", "Redirect to the frogol's dashboard page"),
+            update_frogol,
+        )
 }
 
-use tower_cookies::Cookies;
 use axum::response::Redirect;
 
 async fn show_dashboard(
     State(state): State<AppState>,
-    cookies: Cookies,
+    user: Option<AuthUser>,
+    CspNonce(csp_nonce): CspNonce,
 ) -> Result<Response, AppError> {
-    // Derive user identity from auth cookie
-    let token = cookies.get("auth_token").map(|c| c.value().to_string());
-    let user = if let Some(token) = token {
-        Some(state.services.auth.validate_token(&token).await?)
-    } else {
+    let Some(AuthUser(user)) = user else {
         return Ok(Redirect::to("/login").into_response());
     };
-    let user = user.expect("User should be authenticated at this point");
     let user_email = user.email.clone();
     let user_id = user.id.clone();
     
@@ -144,7 +223,7 @@ async fn show_dashboard(
     
     // Get total leads and clicks
     let total_leads = state.services.lead.get_user_total_leads(&user_id).await?;
-    let total_clicks = state.services.frogol.get_user_total_clicks(&user_id).await?;
+    let total_clicks = state.services.frogol.get_user_total_clicks(&user_id, false).await?;
     
     let template = DashboardTemplate {
         user_email,
@@ -152,31 +231,40 @@ async fn show_dashboard(
         frogols,
         total_leads,
         total_clicks,
+        csp_nonce,
     };
     
     Ok(template.into_response())
 }
 
-async fn show_create_frogol() -> Result<Response, AppError> {
-    Ok(CreateFrogolTemplate.into_response())
+async fn show_create_frogol(
+    user: Option<AuthUser>,
+    CsrfToken(csrf_token): CsrfToken,
+    CspNonce(csp_nonce): CspNonce,
+) -> Result<Response, AppError> {
+    if user.is_none() {
+        return Ok(Redirect::to("/login").into_response());
+    }
+    Ok(CreateFrogolTemplate { csrf_token, csp_nonce }.into_response())
 }
 
 async fn create_frogol(
     State(state): State<AppState>,
-    cookies: Cookies,
+    user: Option<AuthUser>,
     Form(form): Form<CreateFrogolForm>,
 ) -> Result<Response, AppError> {
-    // Require auth for creation; fall back to demo user email if not authenticated
-    let token = cookies.get("auth_token").map(|c| c.value().to_string());
-    let user = if let Some(token) = token { state.services.auth.validate_token(&token).await? } else { return Ok(Redirect::to("/login").into_response()); };
+    let Some(AuthUser(user)) = user else {
+        return Ok(Redirect::to("/login").into_response());
+    };
+    state.services.auth.ensure_can_publish(&user)?;
     
     let frogol = match state.services.frogol.create_frogol(&user.id, &form.slug, &form.display_name).await {
         Ok(f) => f,
-        Err(AppError::InvalidInput(msg)) if msg == "Slug already exists" => {
+        Err(AppError::Conflict(_)) => {
             // Re-render the create form with a friendly message
-            return Ok(axum::response::Html(format!(
+            return Ok(axum::response::Html(
                 "<div class=\"max-w-xl mx-auto p-4\"><p class=\"text-red-600\">Slug already exists. Choose another.</p><a href=\"/dashboard/frogol/new\" class=\"text-indigo-600\">Back</a></div>"
-            )).into_response());
+            ).into_response());
         }
         Err(e) => return Err(e),
     };
@@ -186,13 +274,22 @@ async fn create_frogol(
 
 async fn show_frogol_detail(
     State(state): State<AppState>,
+    AuthUser(user): AuthUser,
     Path(id): Path<String>,
+    ApiQuery(params): ApiQuery<ClickSeriesParams>,
+    CspNonce(csp_nonce): CspNonce,
 ) -> Result<Response, AppError> {
-    let frogol = state.services.frogol.get_by_id(&id).await?;
+    let query = state.services.frogol.click_series_query(&params)?;
+    let frogol = state.services.frogol.get_owned_frogol(&user.id, &id).await?;
     let links = state.services.frogol.get_links_all(&id).await?;
-    let clicks_by_link = state.services.frogol.get_clicks_by_link(&id).await?;
+    let clicks_by_link = state.services.frogol.get_clicks_by_link(&id, query.include_bots).await?;
     let leads = state.services.lead.get_frogol_leads(&id).await?;
-    let click_stats = state.services.frogol.get_click_stats(&id).await?;
+    let click_stats = state.services.frogol.get_click_stats(&id, query.include_bots).await?;
+    let series = state.services.frogol.get_click_series(ClickScope::Frogol(&id), &query).await?;
+    // Over the chart's range
+    let (start, end) = query.utc_range();
+    let (start, end) = (timestamp(start), timestamp(end));
+    let breakdown = state.services.frogol.get_click_breakdown(ClickScope::Frogol(&id), Some((&start, &end)), query.include_bots).await?;
     
     let frogol_detail = FrogolDetail {
         id: frogol.id,
@@ -225,6 +322,9 @@ async fn show_frogol_detail(
         leads_count: leads.len(),
         leads,
         click_stats,
+        chart: ClickChart::new(&query, series),
+        breakdown,
+        csp_nonce,
     };
     
     Ok(template.into_response())
@@ -232,9 +332,11 @@ async fn show_frogol_detail(
 
 async fn show_edit_frogol(
     State(state): State<AppState>,
+    AuthUser(user): AuthUser,
     Path(id): Path<String>,
+    CspNonce(csp_nonce): CspNonce,
 ) -> Result<Response, AppError> {
-    let frogol = state.services.frogol.get_by_id(&id).await?;
+    let frogol = state.services.frogol.get_owned_frogol(&user.id, &id).await?;
 
     let frogol_detail = FrogolDetail {
         id: frogol.id,
@@ -247,20 +349,25 @@ async fn show_edit_frogol(
         formatted_date: format_date(&frogol.created_at),
     };
 
-    Ok(EditFrogolTemplate { frogol: frogol_detail }.into_response())
+    Ok(EditFrogolTemplate { frogol: frogol_detail, csp_nonce }.into_response())
 }
 
 async fn update_frogol(
     State(state): State<AppState>,
+    AuthUser(user): AuthUser,
     Path(id): Path<String>,
     Form(form): Form<UpdateFrogolForm>,
 ) -> Result<Response, AppError> {
+    let frogol = state.services.frogol.get_owned_frogol(&user.id, &id).await?;
+    // Fields the form leaves out keep their value
+    let avatar_url = form.avatar_url.or(frogol.avatar_url);
+    let bio = form.bio.or(frogol.bio);
     let frogol = state.services.frogol.update_frogol(
         &id,
         &form.display_name,
         &form.theme,
-        form.avatar_url.as_deref(),
-        form.bio.as_deref(),
+        avatar_url.as_deref(),
+        bio.as_deref(),
     ).await?;
     
     Ok(axum::response::Redirect::to(&format!("/dashboard/frogol/{}", frogol.id)).into_response())
@@ -268,8 +375,10 @@ async fn update_frogol(
 
 async fn delete_frogol(
     State(state): State<AppState>,
+    AuthUser(user): AuthUser,
     Path(id): Path<String>,
 ) -> Result<Response, AppError> {
+    state.services.frogol.get_owned_frogol(&user.id, &id).await?;
     state.services.frogol.delete_frogol(&id).await?;
     
     Ok(axum::response::Redirect::to("/dashboard").into_response())
@@ -277,21 +386,20 @@ async fn delete_frogol(
 
 async fn show_analytics(
     State(state): State<AppState>,
-    cookies: tower_cookies::Cookies,
+    user: Option<AuthUser>,
+    ApiQuery(params): ApiQuery<ClickSeriesParams>,
+    CspNonce(csp_nonce): CspNonce,
 ) -> Result<Response, AppError> {
-    // Derive user identity from auth cookie
-    let token = cookies.get("auth_token").map(|c| c.value().to_string());
-    let user = if let Some(token) = token {
-        Some(state.services.auth.validate_token(&token).await?)
-    } else {
-        return Ok(axum::response::Redirect::to("/login").into_response());
+    let Some(AuthUser(user)) = user else {
+        return Ok(Redirect::to("/login").into_response());
     };
-    let user = user.expect("User should be authenticated at this point");
+    let query = state.services.frogol.click_series_query(&params)?;
+    let series = state.services.frogol.get_click_series(ClickScope::User(&user.id), &query).await?;
 
     let analytics = state
         .services
         .frogol
-        .get_user_analytics(&user.id)
+        .get_user_analytics(&user.id, query.include_bots)
         .await?;
 
     let template = AnalyticsTemplate {
@@ -300,14 +408,9 @@ async fn show_analytics(
         total_leads: analytics.total_leads,
         total_clicks: analytics.total_clicks,
         top_frogols: analytics.top_performing_frogols,
+        chart: ClickChart::new(&query, series),
+        csp_nonce,
     };
 
     Ok(template.into_response())
 }
-
-async fn show_settings(
-    State(_state): State<AppState>,
-) -> Result<Response, AppError> {
-    let user_email = "user@example.com".to_string();
-    Ok(SettingsTemplate { user_email }.into_response())
-} 
\ No newline at end of file
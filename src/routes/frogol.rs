@@ -1,6 +1,14 @@
 use crate::{
     errors::AppError,
+    middleware::{
+        auth::{ApiUser, AuthUser},
+        client_ip::ClientIp,
+        do_not_track::DoNotTrack,
+        security_headers::CspNonce,
+    },
     repo::link_repo::Link,
+    routes::openapi::{html_fragment, inline, schema, ApiRouter, Operation, FORM, HTML, JSON},
+    services::{api_token_service::ApiScope, user_agent},
     state::AppState,
 };
 use askama::Template;
@@ -8,28 +16,73 @@ use askama::Template;
 use axum::{
     extract::{Path, State},
     response::Response,
-    routing::{get, post, put},
+    routing::get,
     Form, Router,
 };
-use axum::http::HeaderMap;
+use axum::http::{HeaderMap, Method};
 use axum::body::Bytes;
 // Accept both JSON and form bodies using two handlers
+use schemars::JsonSchema;
 use serde::Deserialize;
 use axum::response::Redirect;
 
-pub fn frogol_routes() -> Router<AppState> {
-    use axum::middleware::from_fn;
-    let api = Router::new()
-        .route("/api/frogol/:slug/links", post(add_link))
-        .route("/api/links/order", put(update_link_order_any))
-        .route("/api/links/:id", get(show_link_fragment).put(update_link).delete(delete_link))
-        .route("/api/links/:id/edit", get(edit_link_form))
-        .route("/api/links/:id/click", get(track_link_click).post(track_link_click))
-        .route_layer(from_fn(crate::middleware::csrf::csrf_verify));
+/// The public frogol page, and the endpoints behind it and the dashboard's
+/// link list. These answer with HTML fragments for HTMX; `X-View: dashboard`
+/// picks the dashboard variant.
+pub fn frogol_routes() -> ApiRouter {
+    let follow = |method| {
+        Operation::new(method, "/api/links/:id/click", "Public", "Follow a link")
+            .public()
+            .describe("Records a click and redirects to the link's URL.")
+            .responds("303", "Redirect to the link's URL")
+    };
 
-    Router::new()
-        .route("/:slug", get(show_frogol))
-        .merge(api)
+    ApiRouter::new()
+        .undocumented(Router::new().route("/:slug", get(show_frogol)))
+        .route(
+            Operation::new(Method::POST, "/api/frogol/:slug/links", "Dashboard", "Add a link from a form")
+                .scope(ApiScope::LinksWrite)
+                .describe("Redirects back to the dashboard unless sent with `HX-Request`.")
+                .body(FORM, schema::<AddLinkForm>)
+                .responds_with("200", "Link fragment", HTML, html_fragment)
+                .responds("303", "Redirect to the frogol's dashboard page"),
+            add_link,
+        )
+        .route(
+            Operation::new(Method::PUT, "/api/links/order", "Dashboard", "Reorder links")
+                .scope(ApiScope::LinksWrite)
+                .describe("Takes `id=…&id=…` as a form, or JSON `{\"ids\": [...]}`, `{\"id\": [...]}` or a bare array. Links not listed keep their relative order after the listed ones.")
+                .body(JSON, |_| inline(serde_json::json!({"type": "array", "items": {"type": "string"}})))
+                .responds("200", "Reordered; empty body"),
+            update_link_order_any,
+        )
+        .route(
+            Operation::new(Method::GET, "/api/links/:id", "Dashboard", "Link fragment")
+                .session_only()
+                .responds_with("200", "Link fragment", HTML, html_fragment),
+            show_link_fragment,
+        )
+        .route(
+            Operation::new(Method::PUT, "/api/links/:id", "Dashboard", "Update a link from a form")
+                .scope(ApiScope::LinksWrite)
+                .body(FORM, schema::<UpdateLinkForm>)
+                .responds_with("200", "Link fragment, or empty once hidden", HTML, html_fragment),
+            update_link,
+        )
+        .route(
+            Operation::new(Method::DELETE, "/api/links/:id", "Dashboard", "Delete a link")
+                .scope(ApiScope::LinksWrite)
+                .responds("200", "Deleted; empty body"),
+            delete_link,
+        )
+        .route(
+            Operation::new(Method::GET, "/api/links/:id/edit", "Dashboard", "Link edit form fragment")
+                .session_only()
+                .responds_with("200", "Edit form fragment", HTML, html_fragment),
+            edit_link_form,
+        )
+        .route(follow(Method::GET), track_link_click)
+        .route(follow(Method::POST), track_link_click)
 }
 
 #[derive(Template)]
@@ -42,6 +95,7 @@ struct FrogolPageTemplate<'a> {
     theme: &'a str,
     avatar_url: Option<&'a str>,
     bio: Option<&'a str>,
+    csp_nonce: &'a str,
 }
 
 #[derive(Template)]
@@ -75,14 +129,16 @@ struct DashboardEditLinkFragmentTemplate<'a> {
     link: &'a Link,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct AddLinkForm {
     pub url: String,
     pub label: String,
 }
 
-#[derive(Deserialize)]
-struct UpdateLinkForm {
+/// Fields left out are not changed. `is_active=false` hides the link and
+/// answers with an empty body.
+#[derive(Deserialize, JsonSchema)]
+pub struct UpdateLinkForm {
     url: Option<String>,
     label: Option<String>,
     #[serde(default)]
@@ -95,6 +151,7 @@ async fn show_frogol(
     Path(slug): Path<String>,
     State(state): State<AppState>,
     headers: HeaderMap,
+    CspNonce(csp_nonce): CspNonce,
 ) -> Result<Response, AppError> {
     let frogol = state.services.frogol.get_by_slug(&slug).await?;
     let links = state.services.frogol.get_links(&frogol.id).await?;
@@ -111,6 +168,7 @@ async fn show_frogol(
             theme: frogol.theme.as_deref().unwrap_or("default"),
             avatar_url: frogol.avatar_url.as_deref(),
             bio: frogol.bio.as_deref(),
+            csp_nonce: &csp_nonce,
         };
         Ok(<FrogolPageTemplate as askama_axum::IntoResponse>::into_response(template))
     }
@@ -118,11 +176,13 @@ async fn show_frogol(
 
 async fn add_link(
     State(state): State<AppState>,
+    caller: ApiUser,
     Path(slug): Path<String>,
     headers: HeaderMap,
     Form(form): Form<AddLinkForm>,
 ) -> Result<Response, AppError> {
-    let frogol = state.services.frogol.get_by_slug(&slug).await?;
+    let user = caller.require(ApiScope::LinksWrite)?;
+    let frogol = state.services.frogol.get_owned_frogol_by_slug(&user.id, &slug).await?;
     let link = state
         .services
         .frogol
@@ -152,9 +212,11 @@ async fn add_link(
 
 async fn update_link_order_any(
     State(state): State<AppState>,
+    caller: ApiUser,
     headers: HeaderMap,
     body: Bytes,
 ) -> Result<Response, AppError> {
+    let user = caller.require(ApiScope::LinksWrite)?;
     let content_type = headers
         .get(axum::http::header::CONTENT_TYPE)
         .and_then(|v| v.to_str().ok())
@@ -167,11 +229,11 @@ async fn update_link_order_any(
             match obj {
                 serde_json::Value::Object(map) => {
                     if let Some(serde_json::Value::Array(arr)) = map.get("id") {
-                        arr.into_iter()
+                        arr.iter()
                             .filter_map(|v| v.as_str().map(|s| s.to_string()))
                             .collect()
                     } else if let Some(serde_json::Value::Array(arr)) = map.get("ids") {
-                        arr.into_iter()
+                        arr.iter()
                             .filter_map(|v| v.as_str().map(|s| s.to_string()))
                             .collect()
                     } else {
@@ -206,6 +268,18 @@ async fn update_link_order_any(
     };
 
     if !link_ids.is_empty() {
+        // Every id must belong to the same frogol, and that frogol to the caller
+        let mut frogol_id: Option<String> = None;
+        for link_id in &link_ids {
+            let link = state.services.frogol.get_owned_link(&user.id, link_id).await?;
+            match &frogol_id {
+                Some(id) if *id != link.frogol_id => {
+                    return Err(AppError::InvalidInput("Links must belong to the same frogol".into()));
+                }
+                Some(_) => {}
+                None => frogol_id = Some(link.frogol_id),
+            }
+        }
         tracing::info!(count = link_ids.len(), "update_link_order_any: received link ids");
         state.services.frogol.update_link_order(&link_ids).await?;
     } else {
@@ -218,10 +292,11 @@ async fn update_link_order_any(
 
 async fn edit_link_form(
     State(state): State<AppState>,
+    AuthUser(user): AuthUser,
     Path(id): Path<String>,
     headers: HeaderMap,
 ) -> Result<Response, AppError> {
-    let link = state.services.frogol.get_link(&id).await?;
+    let link = state.services.frogol.get_owned_link(&user.id, &id).await?;
     // If dashboard view requested, render dashboard edit fragment
     if headers
         .get("X-View")
@@ -238,10 +313,11 @@ async fn edit_link_form(
 
 async fn show_link_fragment(
     State(state): State<AppState>,
+    AuthUser(user): AuthUser,
     Path(id): Path<String>,
     headers: HeaderMap,
 ) -> Result<Response, AppError> {
-    let link = state.services.frogol.get_link(&id).await?;
+    let link = state.services.frogol.get_owned_link(&user.id, &id).await?;
     if headers
         .get("X-View")
         .and_then(|v| v.to_str().ok())
@@ -252,7 +328,7 @@ async fn show_link_fragment(
         // Use a small helper: get click stats for this link via repo.
         // For simplicity, call get_clicks_by_link on the frogol id after fetching link
         let frogol_id = link.frogol_id.clone();
-        let clicks_map = state.services.frogol.get_clicks_by_link(&frogol_id).await?;
+        let clicks_map = state.services.frogol.get_clicks_by_link(&frogol_id, false).await?;
         let clicks = *clicks_map.get(&link.id).unwrap_or(&0);
         let tmpl = DashboardLinkFragmentTemplate { link: &link, clicks };
         return Ok(<DashboardLinkFragmentTemplate as askama_axum::IntoResponse>::into_response(tmpl));
@@ -263,10 +339,14 @@ async fn show_link_fragment(
 
 async fn update_link(
     State(state): State<AppState>,
+    caller: ApiUser,
     Path(id): Path<String>,
     headers: HeaderMap,
     Form(form): Form<UpdateLinkForm>,
 ) -> Result<Response, AppError> {
+    let user = caller.require(ApiScope::LinksWrite)?;
+    state.services.frogol.get_owned_link(&user.id, &id).await?;
+
     // Toggle visibility if requested first
     if let Some(active) = form.is_active {
         state.services.frogol.set_link_active(&id, active).await?;
@@ -291,7 +371,7 @@ async fn update_link(
         .map(|v| v.eq_ignore_ascii_case("dashboard"))
         .unwrap_or(false)
     {
-        let clicks_map = state.services.frogol.get_clicks_by_link(&link.frogol_id).await?;
+        let clicks_map = state.services.frogol.get_clicks_by_link(&link.frogol_id, false).await?;
         let clicks = *clicks_map.get(&link.id).unwrap_or(&0);
         let tmpl = DashboardLinkFragmentTemplate { link: &link, clicks };
         return Ok(<DashboardLinkFragmentTemplate as askama_axum::IntoResponse>::into_response(tmpl));
@@ -302,8 +382,11 @@ async fn update_link(
 
 async fn delete_link(
     State(state): State<AppState>,
+    caller: ApiUser,
     Path(id): Path<String>,
 ) -> Result<Response, AppError> {
+    let user = caller.require(ApiScope::LinksWrite)?;
+    state.services.frogol.get_owned_link(&user.id, &id).await?;
     state.services.frogol.delete_link(&id).await?;
     Ok(Response::new("".to_string().into()))
 }
@@ -311,19 +394,20 @@ async fn delete_link(
 async fn track_link_click(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    ClientIp(ip): ClientIp,
+    DoNotTrack(opted_out): DoNotTrack,
+    method: Method,
     headers: HeaderMap,
 ) -> Result<Response, AppError> {
     let user_agent = headers
         .get("user-agent")
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.to_string());
+        .and_then(|v| v.to_str().ok());
 
-    // Track the click (no IP from headers; can be extended with X-Forwarded-For)
-    state
-        .services
-        .frogol
-        .track_click(&id, None, user_agent)
-        .await?;
+    // Track the click, flagged if a bot made it
+    let visitor = state.services.visitors.identify(ip, user_agent, opted_out).await?;
+    let agent = user_agent::classify(user_agent);
+    let bot = state.services.bots.detect(&method, &headers, &agent, &visitor).await?;
+    state.services.frogol.track_click(&id, visitor, agent, bot).await?;
 
     // Get the link to redirect
     let link = state.services.frogol.get_link(&id).await?;
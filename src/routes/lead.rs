@@ -1,12 +1,18 @@
 use axum::{
     extract::{Path, State, Form},
+    http::Method,
     response::{IntoResponse, Response},
-    routing::{get, post},
-    Router,
 };
+use schemars::JsonSchema;
 use serde::Deserialize;
 use crate::state::AppState;
 use crate::errors::AppError;
+use crate::middleware::auth::{ApiUser, AuthUser};
+use crate::middleware::client_ip::ClientIp;
+use crate::middleware::do_not_track::DoNotTrack;
+use crate::repo::{lead_repo::Lead, user_repo::User};
+use crate::routes::openapi::{html_fragment, schema, ApiRouter, Operation, FORM, HTML};
+use crate::services::{api_token_service::ApiScope, visitor_service::truncate_ip};
 use askama::Template;
 // askama_axum::IntoResponse is used via the trait; no direct import needed
 
@@ -21,7 +27,7 @@ struct LeadCaptureErrorTemplate {
 }
 
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct LeadCaptureForm {
     email: String,
     #[allow(dead_code)]
@@ -29,18 +35,48 @@ pub struct LeadCaptureForm {
     message: Option<String>,
 }
 
-pub fn lead_routes() -> Router<AppState> {
-    use axum::middleware::from_fn;
-    let api_csrf = from_fn(crate::middleware::csrf::csrf_verify);
-    Router::new()
-        .route("/api/lead/:frogol_id", post(capture_lead)).route_layer(api_csrf.clone())
-        .route("/api/leads/:id", get(show_lead_fragment).put(update_lead).delete(delete_lead)).route_layer(api_csrf.clone())
-        .route("/api/leads/:id/edit", get(edit_lead_form))
+pub fn lead_routes() -> ApiRouter {
+    ApiRouter::new()
+        .route(
+            Operation::new(Method::POST, "/api/lead/:frogol_id", "Public", "Capture a lead")
+                .public()
+                .describe("Submitted by the form on a frogol's public page.")
+                .body(FORM, schema::<LeadCaptureForm>)
+                .responds_with("200", "Success or error fragment", HTML, html_fragment),
+            capture_lead,
+        )
+        .route(
+            Operation::new(Method::GET, "/api/leads/:id", "Dashboard", "Lead fragment")
+                .scope(ApiScope::LeadsWrite)
+                .responds_with("200", "Lead fragment", HTML, html_fragment),
+            show_lead_fragment,
+        )
+        .route(
+            Operation::new(Method::PUT, "/api/leads/:id", "Dashboard", "Update a lead from a form")
+                .scope(ApiScope::LeadsWrite)
+                .body(FORM, schema::<UpdateLeadForm>)
+                .responds_with("200", "Lead fragment", HTML, html_fragment),
+            update_lead,
+        )
+        .route(
+            Operation::new(Method::DELETE, "/api/leads/:id", "Dashboard", "Delete a lead")
+                .scope(ApiScope::LeadsWrite)
+                .responds("200", "Deleted; empty body"),
+            delete_lead,
+        )
+        .route(
+            Operation::new(Method::GET, "/api/leads/:id/edit", "Dashboard", "Lead edit form fragment")
+                .session_only()
+                .responds_with("200", "Edit form fragment", HTML, html_fragment),
+            edit_lead_form,
+        )
 }
 
 async fn capture_lead(
     Path(frogol_id): Path<String>,
     State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    DoNotTrack(opted_out): DoNotTrack,
     Form(payload): Form<LeadCaptureForm>,
 ) -> Result<impl IntoResponse, AppError> {
     // Validate email
@@ -59,6 +95,7 @@ async fn capture_lead(
             &payload.email,
             payload.source.as_deref(),
             payload.message.as_deref(),
+            ip.filter(|_| !opted_out).map(|ip| truncate_ip(ip).to_string()),
         )
         .await;
 
@@ -79,46 +116,65 @@ async fn capture_lead(
 #[derive(Template)]
 #[template(path = "dashboard/partials/lead.html")]
 struct DashboardLeadFragmentTemplate<'a> {
-    lead: &'a crate::repo::lead_repo::Lead,
+    lead: &'a Lead,
 }
 
 #[derive(Template)]
 #[template(path = "dashboard/partials/edit-lead-form.html")]
 struct DashboardEditLeadFragmentTemplate<'a> {
-    lead: &'a crate::repo::lead_repo::Lead,
+    lead: &'a Lead,
 }
 
-#[derive(Deserialize)]
-struct UpdateLeadForm {
+#[derive(Deserialize, JsonSchema)]
+pub struct UpdateLeadForm {
     email: String,
     source: Option<String>,
     score: Option<String>,
     message: Option<String>,
 }
 
+/// Loads a lead and checks that the frogol it was captured on belongs to `user`.
+async fn get_owned_lead(state: &AppState, user: &User, id: &str) -> Result<Lead, AppError> {
+    let lead = state
+        .services
+        .lead
+        .get_lead(id)
+        .await
+        .map_err(|e| e.not_found_if_missing("Lead not found"))?;
+    state.services.frogol.get_owned_frogol(&user.id, &lead.frogol_id).await?;
+    Ok(lead)
+}
+
 async fn edit_lead_form(
     State(state): State<AppState>,
+    AuthUser(user): AuthUser,
     Path(id): Path<String>,
 ) -> Result<Response, AppError> {
-    let lead = state.services.lead.get_lead(&id).await?;
+    let lead = get_owned_lead(&state, &user, &id).await?;
     let tmpl = DashboardEditLeadFragmentTemplate { lead: &lead };
     Ok(tmpl.into_response())
 }
 
 async fn show_lead_fragment(
     State(state): State<AppState>,
+    caller: ApiUser,
     Path(id): Path<String>,
 ) -> Result<Response, AppError> {
-    let lead = state.services.lead.get_lead(&id).await?;
+    let user = caller.require(ApiScope::LeadsWrite)?;
+    let lead = get_owned_lead(&state, user, &id).await?;
     let tmpl = DashboardLeadFragmentTemplate { lead: &lead };
     Ok(tmpl.into_response())
 }
 
 async fn update_lead(
     State(state): State<AppState>,
+    caller: ApiUser,
     Path(id): Path<String>,
     Form(form): Form<UpdateLeadForm>,
 ) -> Result<Response, AppError> {
+    let user = caller.require(ApiScope::LeadsWrite)?;
+    get_owned_lead(&state, user, &id).await?;
+
     let parsed_score: Option<i64> = match form.score.as_deref() {
         Some("") | None => None,
         Some(s) => s.parse::<i64>().ok(),
@@ -135,8 +191,11 @@ async fn update_lead(
 
 async fn delete_lead(
     State(state): State<AppState>,
+    caller: ApiUser,
     Path(id): Path<String>,
 ) -> Result<Response, AppError> {
+    let user = caller.require(ApiScope::LeadsWrite)?;
+    get_owned_lead(&state, user, &id).await?;
     state.services.lead.delete_lead(&id).await?;
     Ok(Response::new("".into()))
 }
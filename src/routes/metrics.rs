@@ -0,0 +1,32 @@
+//! `/metrics` for Prometheus. When `metrics.bearer_token` is configured the
+//! scraper must send it as `Authorization: Bearer <token>`.
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{errors::AppError, middleware::auth::bearer_token, state::AppState};
+
+pub fn metrics_routes() -> Router<AppState> {
+    Router::new().route("/metrics", get(serve_metrics))
+}
+
+async fn serve_metrics(State(state): State<AppState>, headers: HeaderMap) -> Result<impl IntoResponse, AppError> {
+    if let Some(expected) = &state.config.metrics.bearer_token {
+        let given = bearer_token(&headers).unwrap_or_default();
+        // Comparing digests keeps the comparison time independent of the token
+        if Sha256::digest(given.as_bytes()) != Sha256::digest(expected.as_bytes()) {
+            return Err(AppError::Unauthorized("A valid metrics token is required".to_string()));
+        }
+    }
+
+    Ok((
+        [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+        state.metrics.render(),
+    ))
+}
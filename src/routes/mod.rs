@@ -3,3 +3,8 @@ pub mod lead;
 pub mod auth;
 pub mod dashboard;
 pub mod avatar;
+pub mod settings;
+pub mod api;
+pub mod openapi;
+pub mod health;
+pub mod metrics;
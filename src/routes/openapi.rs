@@ -0,0 +1,361 @@
+//! OpenAPI 3 description of the HTTP API, served at `/api/openapi.json`.
+//!
+//! Request and response schemas are generated from the same types the
+//! handlers use, so a field added to a DTO or form shows up here without
+//! further work.
+//!
+//! The operations are not generated, but they are registered together with
+//! their handlers: route modules add documented routes to an `ApiRouter`,
+//! which takes each handler along with the `Operation` describing it and
+//! builds both the `Router` and the document from that one list. A route
+//! cannot be documented without being served, or moved without its
+//! description moving too. Pages that are not part of the API are added
+//! with `ApiRouter::undocumented`. `tests::openapi` checks the served
+//! document against the app's real router, and fails when a route under
+//! `/api` is served without being documented. Scopes and statuses are
+//! still written out by hand and not checked against the handlers.
+
+use axum::{
+    handler::Handler,
+    http::Method,
+    routing::{get, on, MethodFilter},
+    Json, Router,
+};
+use schemars::{
+    gen::{SchemaGenerator, SchemaSettings},
+    schema::Schema,
+    JsonSchema,
+};
+use serde_json::{json, Map, Value};
+
+use super::api::dto::{DEFAULT_PER_PAGE, MAX_PER_PAGE};
+use crate::{
+    errors::ErrorBody,
+    services::{api_token_service::ApiScope, click_series::DEFAULT_DAYS},
+    state::AppState,
+};
+
+pub const JSON: &str = "application/json";
+pub const FORM: &str = "application/x-www-form-urlencoded";
+pub const HTML: &str = "text/html";
+
+const DOCUMENT_PATH: &str = "/api/openapi.json";
+
+/// Routes together with the operations documenting them.
+#[derive(Default)]
+pub struct ApiRouter {
+    router: Router<AppState>,
+    operations: Vec<Operation>,
+}
+
+impl ApiRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves `handler` at the operation's method and path, and documents it.
+    pub fn route<H, T>(mut self, operation: Operation, handler: H) -> Self
+    where
+        H: Handler<T, AppState>,
+        T: 'static,
+    {
+        let filter = MethodFilter::try_from(operation.method.clone()).expect("operations use a standard method");
+        self.router = self.router.route(operation.path, on(filter, handler));
+        self.operations.push(operation);
+        self
+    }
+
+    /// Serves routes that are left out of the document, such as HTML pages.
+    pub fn undocumented(mut self, router: Router<AppState>) -> Self {
+        self.router = self.router.merge(router);
+        self
+    }
+
+    pub fn merge(mut self, other: ApiRouter) -> Self {
+        self.router = self.router.merge(other.router);
+        self.operations.extend(other.operations);
+        self
+    }
+
+    /// The routes, plus `GET /api/openapi.json` serving their document.
+    pub fn into_router(mut self) -> Router<AppState> {
+        self.operations.push(
+            Operation::new(Method::GET, DOCUMENT_PATH, "Meta", "This document")
+                .public()
+                .responds_with("200", "OpenAPI 3 document", JSON, |_| inline(json!({"type": "object"}))),
+        );
+        let document = Json(document(&self.operations));
+        self.router.route(DOCUMENT_PATH, get(move || async move { document }))
+    }
+}
+
+/// How an operation authenticates.
+enum Auth {
+    /// No credentials needed.
+    Public,
+    /// Only the `auth_token` session cookie.
+    Session,
+    /// A bearer token or the session cookie; tokens also need the scope, if any.
+    Api(Option<ApiScope>),
+}
+
+/// Builds a schema, adding any definitions it refers to to the generator.
+pub type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+/// A media type and the schema of its body.
+type Content = (&'static str, SchemaFn);
+
+/// One documented route. Paths use axum's `:param` syntax.
+pub struct Operation {
+    method: Method,
+    path: &'static str,
+    tag: &'static str,
+    summary: &'static str,
+    description: Option<&'static str>,
+    auth: Auth,
+    paginated: bool,
+    /// Takes the `ClickSeriesParams` query string.
+    series: bool,
+    /// Takes `?include_bots=`.
+    bots: bool,
+    request: Option<Content>,
+    /// Status, description and body.
+    responses: Vec<(&'static str, &'static str, Option<Content>)>,
+}
+
+impl Operation {
+    pub fn new(method: Method, path: &'static str, tag: &'static str, summary: &'static str) -> Self {
+        Self {
+            method,
+            path,
+            tag,
+            summary,
+            description: None,
+            auth: Auth::Api(None),
+            paginated: false,
+            series: false,
+            bots: false,
+            request: None,
+            responses: Vec::new(),
+        }
+    }
+
+    pub fn describe(mut self, description: &'static str) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    pub fn public(mut self) -> Self {
+        self.auth = Auth::Public;
+        self
+    }
+
+    pub fn session_only(mut self) -> Self {
+        self.auth = Auth::Session;
+        self
+    }
+
+    pub fn scope(mut self, scope: ApiScope) -> Self {
+        self.auth = Auth::Api(Some(scope));
+        self
+    }
+
+    pub fn paginated(mut self) -> Self {
+        self.paginated = true;
+        self
+    }
+
+    pub fn series(mut self) -> Self {
+        self.series = true;
+        self
+    }
+
+    pub fn bots(mut self) -> Self {
+        self.bots = true;
+        self
+    }
+
+    pub fn body(mut self, content_type: &'static str, schema: SchemaFn) -> Self {
+        self.request = Some((content_type, schema));
+        self
+    }
+
+    pub fn responds(mut self, status: &'static str, description: &'static str) -> Self {
+        self.responses.push((status, description, None));
+        self
+    }
+
+    pub fn responds_with(
+        mut self,
+        status: &'static str,
+        description: &'static str,
+        content_type: &'static str,
+        schema: SchemaFn,
+    ) -> Self {
+        self.responses.push((status, description, Some((content_type, schema))));
+        self
+    }
+
+    fn to_json(&self, gen: &mut SchemaGenerator, error: &Schema) -> Value {
+        let mut parameters: Vec<Value> = path_params(self.path)
+            .map(|name| json!({"name": name, "in": "path", "required": true, "schema": {"type": "string"}}))
+            .collect();
+        if self.paginated {
+            parameters.push(json!({
+                "name": "page", "in": "query",
+                "schema": {"type": "integer", "minimum": 1, "default": 1}
+            }));
+            parameters.push(json!({
+                "name": "per_page", "in": "query",
+                "schema": {"type": "integer", "minimum": 1, "maximum": MAX_PER_PAGE, "default": DEFAULT_PER_PAGE}
+            }));
+        }
+
+        if self.series {
+            parameters.push(json!({
+                "name": "bucket", "in": "query",
+                "schema": {"type": "string", "enum": ["hour", "day", "week"], "default": "day"}
+            }));
+            parameters.push(json!({
+                "name": "from", "in": "query",
+                "description": format!("First day. Defaults to {} days before `to`.", DEFAULT_DAYS - 1),
+                "schema": {"type": "string", "format": "date"}
+            }));
+            parameters.push(json!({
+                "name": "to", "in": "query",
+                "description": "Last day, included. Defaults to today.",
+                "schema": {"type": "string", "format": "date"}
+            }));
+            parameters.push(json!({
+                "name": "tz_offset", "in": "query",
+                "description": "Minutes east of UTC that days and hours are counted in. Offsets that are not whole hours allow at most 92 days.",
+                "schema": {"type": "integer", "minimum": -840, "maximum": 840, "default": 0}
+            }));
+        }
+        if self.bots {
+            parameters.push(json!({
+                "name": "include_bots", "in": "query",
+                "description": "Include clicks flagged as bots.",
+                "schema": {"type": "boolean", "default": false}
+            }));
+        }
+
+        let mut responses = Map::new();
+        for (status, description, content) in &self.responses {
+            let mut response = json!({"description": description});
+            if let Some((content_type, schema)) = content {
+                response["content"] = json!({*content_type: {"schema": schema(gen)}});
+            }
+            responses.insert(status.to_string(), response);
+        }
+        responses.insert(
+            "default".to_string(),
+            json!({"description": "Error", "content": {JSON: {"schema": error}}}),
+        );
+
+        let mut operation = json!({
+            "tags": [self.tag],
+            "summary": self.summary,
+            "operationId": operation_id(&self.method, self.path),
+            "parameters": parameters,
+            "responses": responses,
+        });
+        let mut description = self.description.map(str::to_string);
+        match &self.auth {
+            Auth::Public => operation["security"] = json!([]),
+            Auth::Session => operation["security"] = json!([{"cookieAuth": []}]),
+            Auth::Api(Some(scope)) => {
+                let note = format!("API tokens need the `{}` scope.", scope.as_str());
+                description = Some(match description {
+                    Some(text) => format!("{} {}", text, note),
+                    None => note,
+                });
+            }
+            Auth::Api(None) => {}
+        }
+        if let Some(description) = description {
+            operation["description"] = json!(description);
+        }
+        if let Some((content_type, schema)) = &self.request {
+            operation["requestBody"] = json!({"required": true, "content": {*content_type: {"schema": schema(gen)}}});
+        }
+        operation
+    }
+}
+
+/// Names of the `:param` segments in an axum path.
+fn path_params(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter_map(|segment| segment.strip_prefix(':'))
+}
+
+/// `/api/v1/frogols/:id` becomes `/api/v1/frogols/{id}`.
+fn openapi_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(param) => format!("{{{}}}", param),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// `GET /api/v1/frogols/:id` becomes `get_api_v1_frogols_id`.
+fn operation_id(method: &Method, path: &str) -> String {
+    let mut id = method.as_str().to_ascii_lowercase();
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        id.push('_');
+        id.extend(
+            segment
+                .chars()
+                .filter(|c| *c != ':')
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }),
+        );
+    }
+    id
+}
+
+/// The schema of `T`, for `Operation::body` and `Operation::responds_with`.
+pub fn schema<T: JsonSchema>(gen: &mut SchemaGenerator) -> Schema {
+    gen.subschema_for::<T>()
+}
+
+/// An HTML fragment answered to HTMX.
+pub fn html_fragment(_: &mut SchemaGenerator) -> Schema {
+    inline(json!({"type": "string"}))
+}
+
+/// A schema written out by hand, for bodies no Rust type describes.
+pub fn inline(schema: Value) -> Schema {
+    serde_json::from_value(schema).expect("valid JSON schema")
+}
+
+/// Builds the OpenAPI document for `operations`.
+fn document(operations: &[Operation]) -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    let error = gen.subschema_for::<ErrorBody>();
+
+    let mut paths = Map::new();
+    for operation in operations {
+        let item = paths.entry(openapi_path(operation.path)).or_insert_with(|| json!({}));
+        item[operation.method.as_str().to_ascii_lowercase()] = operation.to_json(&mut gen, &error);
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Frogolio API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Authenticate with a personal API token (`Authorization: Bearer frg_…`) or the session cookie. \
+                Cookie-authenticated writes must also send the `csrf_token` cookie's value in an `X-CSRF-Token` header.",
+        },
+        "paths": paths,
+        "components": {
+            "schemas": gen.take_definitions(),
+            "securitySchemes": {
+                "bearerAuth": {"type": "http", "scheme": "bearer"},
+                "cookieAuth": {"type": "apiKey", "in": "cookie", "name": crate::middleware::auth::AUTH_COOKIE},
+            },
+        },
+        "security": [{"bearerAuth": []}, {"cookieAuth": []}],
+    })
+}
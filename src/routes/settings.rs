@@ -0,0 +1,490 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Redirect,
+    routing::{get, post},
+    Form, Router,
+};
+use askama::Template;
+use askama_axum::{IntoResponse, Response};
+use chrono::DateTime;
+use serde::Deserialize;
+use time::Duration;
+use tower_cookies::{Cookie, Cookies};
+use validator::Validate;
+
+use crate::{
+    errors::AppError,
+    middleware::{
+        auth::{AuthUser, AUTH_COOKIE},
+        csrf::CsrfToken,
+        security_headers::CspNonce,
+    },
+    repo::user_repo::User,
+    services::{
+        api_token_service::{parse_scopes, ApiScope},
+        auth_service::TotpEnrollment,
+    },
+    state::AppState,
+};
+
+#[derive(Template)]
+#[template(path = "dashboard/settings.html")]
+struct SettingsTemplate {
+    user_email: String,
+    email_verified: bool,
+    sessions: Vec<SessionView>,
+    failed_logins: Vec<FailedLoginView>,
+    two_factor_enabled: bool,
+    totp_setup: Option<TotpEnrollment>,
+    recovery_codes_left: i64,
+    /// Freshly issued recovery codes, shown once.
+    recovery_codes: Vec<String>,
+    api_tokens: Vec<ApiTokenView>,
+    api_scopes: Vec<ScopeOption>,
+    /// Freshly created API token, shown once.
+    new_api_token: Option<String>,
+    notice: String,
+    error_msg: String,
+    csrf_token: String,
+    csp_nonce: String,
+}
+
+struct SessionView {
+    id: String,
+    created_at: String,
+    expires_at: String,
+    last_seen_at: String,
+    user_agent: String,
+    ip_address: String,
+    remember_me: bool,
+    is_current: bool,
+}
+
+struct ApiTokenView {
+    id: String,
+    name: String,
+    prefix: String,
+    scopes: String,
+    created_at: String,
+    last_used_at: Option<String>,
+}
+
+struct ScopeOption {
+    value: &'static str,
+    label: &'static str,
+}
+
+struct FailedLoginView {
+    attempted_at: String,
+    ip_address: String,
+}
+
+#[derive(Deserialize)]
+pub struct SettingsQuery {
+    updated: Option<String>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ChangeEmailForm {
+    #[validate(email)]
+    new_email: String,
+    current_password: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ChangePasswordForm {
+    current_password: String,
+    #[validate(length(min = 6))]
+    new_password: String,
+    new_password_confirm: String,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountForm {
+    current_password: String,
+}
+
+#[derive(Deserialize)]
+pub struct TotpCodeForm {
+    code: String,
+}
+
+#[derive(Deserialize)]
+pub struct DisableTotpForm {
+    current_password: String,
+    code: String,
+}
+
+#[derive(Deserialize)]
+pub struct RegenerateRecoveryCodesForm {
+    current_password: String,
+}
+
+pub fn settings_routes() -> Router<AppState> {
+    Router::new()
+        .route("/dashboard/settings", get(show_settings))
+        .route("/dashboard/settings/email", post(change_email))
+        .route("/dashboard/settings/password", post(change_password))
+        .route("/dashboard/settings/sessions/:id/revoke", post(revoke_session))
+        .route("/dashboard/settings/delete", post(delete_account))
+        .route("/dashboard/settings/2fa/setup", post(begin_two_factor))
+        .route("/dashboard/settings/2fa/confirm", post(confirm_two_factor))
+        .route("/dashboard/settings/2fa/disable", post(disable_two_factor))
+        .route("/dashboard/settings/2fa/recovery-codes", post(regenerate_recovery_codes))
+        .route("/dashboard/settings/api-tokens", post(create_api_token))
+        .route("/dashboard/settings/api-tokens/:id/revoke", post(revoke_api_token))
+}
+
+fn format_date(date_str: &str) -> String {
+    DateTime::parse_from_rfc3339(date_str)
+        .map(|dt| dt.format("%b %d, %Y at %I:%M %p").to_string())
+        .unwrap_or_else(|_| date_str.to_string())
+}
+
+fn current_token(cookies: &Cookies) -> String {
+    cookies.get(AUTH_COOKIE).map(|c| c.value().to_string()).unwrap_or_default()
+}
+
+fn clear_auth_cookie(cookies: &Cookies) {
+    let mut expired = Cookie::from(AUTH_COOKIE);
+    expired.set_max_age(Duration::seconds(0));
+    expired.set_path("/");
+    cookies.add(expired);
+}
+
+/// Loads everything the settings page shows, leaving messages empty.
+async fn settings_page(
+    state: &AppState,
+    user: User,
+    cookies: &Cookies,
+    csp_nonce: &str,
+) -> Result<SettingsTemplate, AppError> {
+    let token = current_token(cookies);
+    let sessions = state
+        .services
+        .auth
+        .list_sessions(&user.id)
+        .await?
+        .into_iter()
+        .map(|s| SessionView {
+            is_current: s.token == token,
+            id: s.id,
+            created_at: format_date(&s.created_at),
+            expires_at: format_date(&s.expires_at),
+            last_seen_at: format_date(s.last_seen_at.as_deref().unwrap_or(&s.created_at)),
+            user_agent: s.user_agent.unwrap_or_else(|| "Unknown browser".to_string()),
+            ip_address: s.ip_address.unwrap_or_else(|| "Unknown".to_string()),
+            remember_me: s.remember_me,
+        })
+        .collect();
+    let failed_logins = state
+        .services
+        .auth
+        .recent_failed_logins(&user.id)
+        .await?
+        .into_iter()
+        .map(|a| FailedLoginView {
+            attempted_at: format_date(&a.attempted_at),
+            ip_address: a.ip_address.unwrap_or_else(|| "Unknown".to_string()),
+        })
+        .collect();
+    let two_factor = state.services.auth.two_factor_status(&user).await?;
+    let api_tokens = state
+        .services
+        .api_tokens
+        .list(&user.id)
+        .await?
+        .into_iter()
+        .map(|t| ApiTokenView {
+            scopes: parse_scopes(&t.scopes)
+                .iter()
+                .map(|scope| scope.description())
+                .collect::<Vec<_>>()
+                .join(", "),
+            id: t.id,
+            name: t.name,
+            prefix: t.prefix,
+            created_at: format_date(&t.created_at),
+            last_used_at: t.last_used_at.as_deref().map(format_date),
+        })
+        .collect();
+
+    Ok(SettingsTemplate {
+        email_verified: user.is_email_verified(),
+        user_email: user.email,
+        sessions,
+        failed_logins,
+        two_factor_enabled: two_factor.enabled,
+        totp_setup: two_factor.pending,
+        recovery_codes_left: two_factor.recovery_codes_left,
+        recovery_codes: Vec::new(),
+        api_tokens,
+        api_scopes: ApiScope::ALL
+            .iter()
+            .map(|scope| ScopeOption { value: scope.as_str(), label: scope.description() })
+            .collect(),
+        new_api_token: None,
+        notice: String::new(),
+        error_msg: String::new(),
+        csrf_token: CsrfToken::from_cookies(cookies).0,
+        csp_nonce: csp_nonce.to_string(),
+    })
+}
+
+async fn render_settings(
+    state: &AppState,
+    user: User,
+    cookies: &Cookies,
+    csp_nonce: &str,
+    notice: &str,
+    error_msg: &str,
+) -> Result<Response, AppError> {
+    let mut page = settings_page(state, user, cookies, csp_nonce).await?;
+    page.notice = notice.to_string();
+    page.error_msg = error_msg.to_string();
+    Ok(page.into_response())
+}
+
+/// Shows newly issued recovery codes, or the error if issuing them failed.
+async fn render_recovery_codes(
+    state: &AppState,
+    user: User,
+    cookies: &Cookies,
+    csp_nonce: &str,
+    result: Result<Vec<String>, AppError>,
+    notice: &str,
+) -> Result<Response, AppError> {
+    match result {
+        Ok(codes) => {
+            let mut page = settings_page(state, user, cookies, csp_nonce).await?;
+            page.recovery_codes = codes;
+            page.notice = notice.to_string();
+            Ok(page.into_response())
+        }
+        Err(AppError::InvalidInput(msg)) => render_settings(state, user, cookies, csp_nonce, "", &msg).await,
+        Err(e) => Err(e),
+    }
+}
+
+/// Renders the settings page with the service's message for user-facing
+/// failures, and propagates anything else.
+async fn render_result(
+    state: &AppState,
+    user: User,
+    cookies: &Cookies,
+    csp_nonce: &str,
+    result: Result<(), AppError>,
+    updated: &str,
+) -> Result<Response, AppError> {
+    match result {
+        Ok(()) => Ok(Redirect::to(&format!("/dashboard/settings?updated={}", updated)).into_response()),
+        Err(AppError::InvalidInput(msg)) | Err(AppError::ValidationError(msg)) | Err(AppError::Conflict(msg)) => {
+            render_settings(state, user, cookies, csp_nonce, "", &msg).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+async fn show_settings(
+    State(state): State<AppState>,
+    user: Option<AuthUser>,
+    cookies: Cookies,
+    CspNonce(csp_nonce): CspNonce,
+    Query(query): Query<SettingsQuery>,
+) -> Result<Response, AppError> {
+    let Some(AuthUser(user)) = user else {
+        return Ok(Redirect::to("/login").into_response());
+    };
+
+    let notice = match query.updated.as_deref() {
+        Some("email") => "Check your new inbox: we've sent a link to confirm the address change.",
+        Some("password") => "Password changed. Your other sessions have been signed out.",
+        Some("session") => "Session revoked.",
+        Some("2fa-setup") => "Scan the code below with your authenticator app, then enter the 6-digit code it shows.",
+        Some("2fa-disabled") => "Two-factor authentication is off.",
+        Some("api-token") => "API token revoked.",
+        _ => "",
+    };
+    render_settings(&state, user, &cookies, &csp_nonce, notice, "").await
+}
+
+async fn change_email(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    cookies: Cookies,
+    CspNonce(csp_nonce): CspNonce,
+    Form(form): Form<ChangeEmailForm>,
+) -> Result<Response, AppError> {
+    let result = if form.validate().is_err() {
+        Err(AppError::InvalidInput("Please enter a valid email address".to_string()))
+    } else {
+        state
+            .services
+            .auth
+            .request_email_change(&user, &form.current_password, &form.new_email)
+            .await
+    };
+    render_result(&state, user, &cookies, &csp_nonce, result, "email").await
+}
+
+async fn change_password(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    cookies: Cookies,
+    CspNonce(csp_nonce): CspNonce,
+    Form(form): Form<ChangePasswordForm>,
+) -> Result<Response, AppError> {
+    let result = if form.validate().is_err() {
+        Err(AppError::InvalidInput("New password must be at least 6 characters".to_string()))
+    } else if form.new_password != form.new_password_confirm {
+        Err(AppError::InvalidInput("New passwords do not match".to_string()))
+    } else {
+        state
+            .services
+            .auth
+            .change_password(&user, &current_token(&cookies), &form.current_password, &form.new_password)
+            .await
+    };
+    render_result(&state, user, &cookies, &csp_nonce, result, "password").await
+}
+
+async fn revoke_session(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    cookies: Cookies,
+    Path(session_id): Path<String>,
+) -> Result<Response, AppError> {
+    let is_current = state
+        .services
+        .auth
+        .list_sessions(&user.id)
+        .await?
+        .iter()
+        .any(|s| s.id == session_id && s.token == current_token(&cookies));
+
+    state.services.auth.revoke_session(&user.id, &session_id).await?;
+
+    if is_current {
+        clear_auth_cookie(&cookies);
+        return Ok(Redirect::to("/login").into_response());
+    }
+    Ok(Redirect::to("/dashboard/settings?updated=session").into_response())
+}
+
+async fn delete_account(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    cookies: Cookies,
+    CspNonce(csp_nonce): CspNonce,
+    Form(form): Form<DeleteAccountForm>,
+) -> Result<Response, AppError> {
+    match state.services.auth.delete_account(&user, &form.current_password).await {
+        Ok(avatar_files) => {
+            state.services.avatar.delete_avatar_files(avatar_files).await;
+            clear_auth_cookie(&cookies);
+            Ok(Redirect::to("/register").into_response())
+        }
+        Err(AppError::InvalidInput(msg)) => render_settings(&state, user, &cookies, &csp_nonce, "", &msg).await,
+        Err(e) => Err(e),
+    }
+}
+
+async fn begin_two_factor(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    cookies: Cookies,
+    CspNonce(csp_nonce): CspNonce,
+) -> Result<Response, AppError> {
+    let result = state.services.auth.begin_totp_enrollment(&user).await.map(|_| ());
+    render_result(&state, user, &cookies, &csp_nonce, result, "2fa-setup").await
+}
+
+async fn confirm_two_factor(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    cookies: Cookies,
+    CspNonce(csp_nonce): CspNonce,
+    Form(form): Form<TotpCodeForm>,
+) -> Result<Response, AppError> {
+    let result = state.services.auth.confirm_totp_enrollment(&user, &form.code).await;
+    render_recovery_codes(
+        &state,
+        user,
+        &cookies,
+        &csp_nonce,
+        result,
+        "Two-factor authentication is on. Save these recovery codes somewhere safe: each works once if you lose your device.",
+    )
+    .await
+}
+
+async fn disable_two_factor(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    cookies: Cookies,
+    CspNonce(csp_nonce): CspNonce,
+    Form(form): Form<DisableTotpForm>,
+) -> Result<Response, AppError> {
+    let result = state.services.auth.disable_totp(&user, &form.current_password, &form.code).await;
+    render_result(&state, user, &cookies, &csp_nonce, result, "2fa-disabled").await
+}
+
+async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    cookies: Cookies,
+    CspNonce(csp_nonce): CspNonce,
+    Form(form): Form<RegenerateRecoveryCodesForm>,
+) -> Result<Response, AppError> {
+    let result = state.services.auth.regenerate_recovery_codes(&user, &form.current_password).await;
+    render_recovery_codes(
+        &state,
+        user,
+        &cookies,
+        &csp_nonce,
+        result,
+        "New recovery codes issued. Your old ones no longer work.",
+    )
+    .await
+}
+
+/// Fields arrive as pairs because the scope checkboxes share a name.
+async fn create_api_token(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    cookies: Cookies,
+    CspNonce(csp_nonce): CspNonce,
+    Form(fields): Form<Vec<(String, String)>>,
+) -> Result<Response, AppError> {
+    let name = fields
+        .iter()
+        .find(|(key, _)| key == "name")
+        .map(|(_, value)| value.as_str())
+        .unwrap_or_default();
+    let scopes = fields
+        .iter()
+        .filter(|(key, _)| key == "scope")
+        .map(|(_, value)| value.parse::<ApiScope>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(AppError::InvalidInput)?;
+
+    match state.services.api_tokens.create(&user, name, &scopes).await {
+        Ok(created) => {
+            let mut page = settings_page(&state, user, &cookies, &csp_nonce).await?;
+            page.new_api_token = Some(created.secret);
+            page.notice = format!("Created API token \"{}\".", created.token.name);
+            Ok(page.into_response())
+        }
+        Err(AppError::InvalidInput(msg)) => render_settings(&state, user, &cookies, &csp_nonce, "", &msg).await,
+        Err(e) => Err(e),
+    }
+}
+
+async fn revoke_api_token(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    Path(token_id): Path<String>,
+) -> Result<Response, AppError> {
+    state.services.api_tokens.revoke(&user.id, &token_id).await?;
+    Ok(Redirect::to("/dashboard/settings?updated=api-token").into_response())
+}
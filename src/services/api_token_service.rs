@@ -0,0 +1,151 @@
+use crate::errors::AppError;
+use crate::repo::api_token_repo::{ApiToken, ApiTokenRepo, NewApiToken};
+use crate::repo::user_repo::{User, UserRepo};
+use crate::services::auth_service::timestamp;
+use crate::services::clock::Clock;
+use crate::services::token;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Marks personal access tokens so they are recognisable in scripts and secret scanners.
+pub const TOKEN_PREFIX: &str = "frg_";
+/// Characters of the token kept in the clear so users can tell tokens apart.
+const DISPLAY_PREFIX_LEN: usize = TOKEN_PREFIX.len() + 8;
+const MAX_NAME_LEN: usize = 100;
+
+/// What a personal access token may do. Cookie sessions may do everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiScope {
+    /// Read click and lead statistics.
+    AnalyticsRead,
+    /// List, view, create, edit and delete frogols.
+    FrogolsWrite,
+    /// List, view, create, edit, reorder and delete links.
+    LinksWrite,
+    /// View, edit and delete captured leads.
+    LeadsWrite,
+}
+
+impl ApiScope {
+    pub const ALL: [ApiScope; 4] = [
+        ApiScope::AnalyticsRead,
+        ApiScope::FrogolsWrite,
+        ApiScope::LinksWrite,
+        ApiScope::LeadsWrite,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::AnalyticsRead => "analytics:read",
+            ApiScope::FrogolsWrite => "frogols:write",
+            ApiScope::LinksWrite => "links:write",
+            ApiScope::LeadsWrite => "leads:write",
+        }
+    }
+
+    /// Label for the settings page.
+    pub fn description(&self) -> &'static str {
+        match self {
+            ApiScope::AnalyticsRead => "Read analytics",
+            ApiScope::FrogolsWrite => "Manage frogols",
+            ApiScope::LinksWrite => "Manage links",
+            ApiScope::LeadsWrite => "Manage leads",
+        }
+    }
+}
+
+impl std::str::FromStr for ApiScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ApiScope::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == s)
+            .ok_or_else(|| format!("unknown API scope {:?}", s))
+    }
+}
+
+/// Scopes stored on a token. Names this version does not know are dropped.
+pub fn parse_scopes(scopes: &str) -> Vec<ApiScope> {
+    scopes.split_whitespace().filter_map(|s| s.parse().ok()).collect()
+}
+
+/// A token as just created; `secret` is shown to the user once and never stored.
+pub struct CreatedApiToken {
+    pub token: ApiToken,
+    pub secret: String,
+}
+
+pub struct ApiTokenService {
+    repo: ApiTokenRepo,
+    user_repo: UserRepo,
+    clock: Arc<dyn Clock>,
+}
+
+impl ApiTokenService {
+    pub fn new(repo: ApiTokenRepo, user_repo: UserRepo, clock: Arc<dyn Clock>) -> Self {
+        Self { repo, user_repo, clock }
+    }
+
+    pub async fn create(&self, user: &User, name: &str, scopes: &[ApiScope]) -> Result<CreatedApiToken, AppError> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+            return Err(AppError::InvalidInput(format!(
+                "Token name must be between 1 and {} characters",
+                MAX_NAME_LEN
+            )));
+        }
+        if scopes.is_empty() {
+            return Err(AppError::InvalidInput("Choose at least one scope".to_string()));
+        }
+
+        let secret = format!("{}{}", TOKEN_PREFIX, token::generate());
+        let scopes: Vec<&str> = ApiScope::ALL
+            .iter()
+            .filter(|scope| scopes.contains(scope))
+            .map(|scope| scope.as_str())
+            .collect();
+
+        let token = self
+            .repo
+            .create(NewApiToken {
+                id: Uuid::new_v4().to_string(),
+                user_id: user.id.clone(),
+                name: name.to_string(),
+                prefix: secret[..DISPLAY_PREFIX_LEN].to_string(),
+                token_hash: token::hash(&secret),
+                scopes: scopes.join(" "),
+            })
+            .await?;
+
+        Ok(CreatedApiToken { token, secret })
+    }
+
+    pub async fn list(&self, user_id: &str) -> Result<Vec<ApiToken>, AppError> {
+        self.repo.list_for_user(user_id).await
+    }
+
+    pub async fn revoke(&self, user_id: &str, token_id: &str) -> Result<(), AppError> {
+        if !self.repo.delete(user_id, token_id).await? {
+            return Err(AppError::NotFound("API token not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Resolves a bearer token to its owner and scopes, recording the use.
+    pub async fn authenticate(&self, secret: &str) -> Result<(User, Vec<ApiScope>), AppError> {
+        let invalid = || AppError::Unauthorized("Invalid API token".to_string());
+        if !secret.starts_with(TOKEN_PREFIX) {
+            return Err(invalid());
+        }
+
+        let token = self.repo.get_by_hash(&token::hash(secret)).await?.ok_or_else(invalid)?;
+        let user = self.user_repo.get_by_id(&token.user_id).await?.ok_or_else(invalid)?;
+        if !user.is_active {
+            return Err(AppError::Unauthorized("Account is disabled".to_string()));
+        }
+
+        self.repo.touch(&token.id, &timestamp(self.clock.now())).await?;
+        Ok((user, parse_scopes(&token.scopes)))
+    }
+}
@@ -1,41 +1,167 @@
-use crate::repo::user_repo::{UserRepo, User, NewUser, Session, NewSession};
+use crate::repo::user_repo::{
+    UserRepo, User, NewUser, Session, NewSession, NewPasswordResetToken, NewEmailVerificationToken,
+    NewLoginChallenge, UserTotp,
+};
+use crate::repo::login_attempt_repo::{FailureStats, LoginAttempt, LoginAttemptRepo, NewLoginAttempt};
 use crate::errors::AppError;
+use crate::services::clock::Clock;
+use crate::services::jwt_keys::JwtKeyring;
+use crate::services::mailer::{Email, Mailer};
+use crate::services::{token, totp};
 use bcrypt::{hash, verify, DEFAULT_COST};
-use jsonwebtoken::{encode, decode, Header, Validation, EncodingKey, DecodingKey};
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+/// How long an emailed password reset link stays valid.
+const PASSWORD_RESET_TTL_MINUTES: i64 = 60;
+/// How long an emailed address confirmation link stays valid.
+const EMAIL_VERIFICATION_TTL_HOURS: i64 = 24;
+
+/// Failed sign-ins allowed per account before backoff starts.
+const ACCOUNT_FREE_FAILURES: i64 = 5;
+/// Failed sign-ins allowed per client IP, across accounts, before backoff starts.
+const IP_FREE_FAILURES: i64 = 20;
+/// Lockout after the free attempts are used up; doubles with each further failure.
+const LOGIN_BACKOFF_BASE_SECS: i64 = 30;
+/// Longest a single lockout lasts.
+const LOGIN_BACKOFF_MAX_SECS: i64 = 15 * 60;
+/// Failures older than this no longer count towards a lockout.
+const LOGIN_FAILURE_WINDOW_HOURS: i64 = 24;
+/// How far back the settings page lists failed sign-ins, and so how long attempts are kept.
+pub(crate) const RECENT_FAILURES_DAYS: i64 = 30;
+
+/// Checked against when there is no real hash, so that signing in as nobody
+/// takes as long as a wrong password. Same cost as `DEFAULT_COST`.
+pub(crate) const DUMMY_PASSWORD_HASH: &str = "$2b$12$eOB0L8LyEhwdkCCwITXu5udHImljMdAFhVGAbqgcoF8hMPBiXSkKu";
+
+/// Last-seen times are only rewritten when older than this, to avoid a write per request.
+const LAST_SEEN_RESOLUTION_MINUTES: i64 = 5;
+
+/// How long the second sign-in step stays open after a correct password.
+const LOGIN_CHALLENGE_TTL_MINUTES: i64 = 5;
+/// Wrong codes allowed before the user has to enter their password again.
+const LOGIN_CHALLENGE_MAX_FAILURES: i64 = 5;
+/// Returned as `AppError::Unauthorized` for a wrong second-step code, so the
+/// sign-in form can tell it apart from an expired challenge.
+pub(crate) const INVALID_SECOND_FACTOR: &str = "Invalid authentication code";
+/// Recovery codes issued per enrollment or regeneration.
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Issuer label shown in authenticator apps.
+const TOTP_ISSUER: &str = "Frogolio";
+
+/// Which actions an account with an unverified email address is held back from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailVerificationPolicy {
+    /// Unverified users cannot sign in at all.
+    RequiredForLogin,
+    /// Unverified users can sign in but cannot publish frogols.
+    RequiredForPublish,
+}
+
+impl std::str::FromStr for EmailVerificationPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "login" => Ok(Self::RequiredForLogin),
+            "publish" => Ok(Self::RequiredForPublish),
+            other => Err(format!("unknown email verification policy {:?} (expected \"login\" or \"publish\")", other)),
+        }
+    }
+}
+
+/// What a correct password leads to.
+#[derive(Debug)]
+pub enum LoginOutcome {
+    Authenticated(Session),
+    /// Two-factor authentication is on; pass `challenge` and a code to
+    /// `complete_two_factor_login` to get a session.
+    TwoFactorRequired { challenge: String },
+}
+
+/// Details an authenticator app needs to enroll.
+#[derive(Debug)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug)]
+pub struct TwoFactorStatus {
+    pub enabled: bool,
+    /// Set while an enrollment has been started but not confirmed.
+    pub pending: Option<TotpEnrollment>,
+    pub recovery_codes_left: i64,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 struct Claims {
     sub: String, // user_id
     exp: u64,    // expiration time
     iat: u64,    // issued at
+    jti: String, // unique per token, so two sign-ins in the same second differ
+}
+
+/// Deployment settings for `AuthService`.
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    /// Signs new session tokens and verifies existing ones.
+    pub jwt_keys: JwtKeyring,
+    /// Public URL used in links sent by email.
+    pub base_url: String,
+    pub verification_policy: EmailVerificationPolicy,
+    /// Idle lifetime of a normal session; renewed while in use.
+    pub session_ttl: Duration,
+    /// Idle lifetime of a "remember me" session; renewed while in use.
+    pub remember_me_ttl: Duration,
+    /// Hard limit on any session's age, however often it is renewed.
+    pub session_max_age: Duration,
+}
+
+/// Where a sign-in comes from, recorded on the session it creates.
+#[derive(Debug, Default, Clone)]
+pub struct ClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
 }
 
 pub struct AuthService {
     user_repo: UserRepo,
-    jwt_secret: String,
+    login_attempts: LoginAttemptRepo,
+    config: AuthConfig,
+    mailer: Arc<dyn Mailer>,
+    clock: Arc<dyn Clock>,
 }
 
 impl AuthService {
-    pub fn new(user_repo: UserRepo, jwt_secret: String) -> Self {
+    pub fn new(
+        user_repo: UserRepo,
+        login_attempts: LoginAttemptRepo,
+        config: AuthConfig,
+        mailer: Arc<dyn Mailer>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
         Self {
             user_repo,
-            jwt_secret,
+            login_attempts,
+            config,
+            mailer,
+            clock,
         }
     }
 
     pub async fn register(&self, email: &str, password: &str) -> Result<User, AppError> {
         // Check if user already exists
-        if let Some(_) = self.user_repo.get_by_email(email).await? {
-            return Err(AppError::InvalidInput("User already exists".to_string()));
+        if self.user_repo.get_by_email(email).await?.is_some() {
+            return Err(AppError::Conflict("User already exists".to_string()));
         }
 
         // Hash password
         let password_hash = hash(password, DEFAULT_COST)
-            .map_err(|_| AppError::InternalError("Failed to hash password".to_string()))?;
+            .map_err(|_| AppError::Internal("Failed to hash password".to_string()))?;
 
         // Create user
         let new_user = NewUser {
@@ -44,50 +170,140 @@ impl AuthService {
             password_hash,
         };
 
-        self.user_repo.create_user(new_user).await
+        let user = self.user_repo.create_user(new_user).await?;
+
+        // The account exists either way; the user can ask for another link
+        if let Err(e) = self
+            .send_email_verification(
+                &user.id,
+                &user.email,
+                "Confirm your Frogolio email address",
+                "Thanks for signing up to Frogolio! Please confirm this is your email address.",
+            )
+            .await
+        {
+            tracing::warn!(user_id = %user.id, "Failed to send verification email: {}", e);
+        }
+
+        Ok(user)
     }
 
-    pub async fn login(&self, email: &str, password: &str) -> Result<Session, AppError> {
+    /// Signs in with a password. Attempts are recorded per account and per
+    /// client IP, and rejected with `AppError::RateLimited` while either is
+    /// locked out after repeated failures. Accounts with two-factor enabled get
+    /// a challenge instead of a session. `remember_me` selects the longer
+    /// session lifetime.
+    pub async fn login(
+        &self,
+        email: &str,
+        password: &str,
+        remember_me: bool,
+        client: &ClientInfo,
+    ) -> Result<LoginOutcome, AppError> {
+        let email_key = email.trim().to_lowercase();
+        let now = self.clock.now();
+        let ip_address = client.ip_address.as_deref();
+        self.check_login_throttle(&email_key, ip_address, now).await?;
+
         // Get user by email
-        let user = self.user_repo.get_by_email(email).await?
-            .ok_or_else(|| AppError::InvalidInput("Invalid credentials".to_string()))?;
+        let user = self.user_repo.get_by_email(email).await?;
 
         // Verify password
-        if let Some(password_hash) = &user.password_hash {
-            let is_valid = verify(password, password_hash)
-                .map_err(|_| AppError::InternalError("Failed to verify password".to_string()))?;
-            
-            if !is_valid {
-                return Err(AppError::InvalidInput("Invalid credentials".to_string()));
+        let is_valid = match user.as_ref().and_then(|u| u.password_hash.as_deref()) {
+            Some(password_hash) => verify(password, password_hash)
+                .map_err(|_| AppError::Internal("Failed to verify password".to_string()))?,
+            // Unknown email or no password: spend the same time, then fail
+            None => {
+                let _ = verify(password, DUMMY_PASSWORD_HASH);
+                false
             }
-        } else {
-            return Err(AppError::InvalidInput("Invalid credentials".to_string()));
+        };
+
+        // With two-factor on, a correct password is only half a sign-in and
+        // must not clear the account's failures; the second step records it
+        let awaiting_second_factor = match &user {
+            Some(user) if is_valid => self.two_factor_enabled(&user.id).await?.is_some(),
+            _ => false,
+        };
+        if !awaiting_second_factor {
+            let user_id = user.as_ref().map(|u| u.id.as_str());
+            self.record_login_attempt(&email_key, user_id, ip_address, is_valid, now).await?;
         }
 
+        let user = match user {
+            Some(user) if is_valid => user,
+            _ => return Err(AppError::Unauthorized("Invalid credentials".to_string())),
+        };
+
         // Check if user is active
         if !user.is_active {
-            return Err(AppError::InvalidInput("Account is disabled".to_string()));
+            return Err(AppError::Forbidden("Account is disabled".to_string()));
         }
 
-        // Generate JWT token
-        let token = self.generate_jwt(&user.id)?;
+        if self.config.verification_policy == EmailVerificationPolicy::RequiredForLogin && !user.is_email_verified() {
+            return Err(AppError::Forbidden("Please verify your email address before signing in".to_string()));
+        }
 
-        // Create session
-        let expires_at = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("System time should be after Unix epoch")
-            .as_secs() + (24 * 60 * 60); // 24 hours
+        if awaiting_second_factor {
+            let challenge = token::generate();
+            let expires_at = now + Duration::minutes(LOGIN_CHALLENGE_TTL_MINUTES);
+            self.user_repo
+                .create_login_challenge(NewLoginChallenge {
+                    id: Uuid::new_v4().to_string(),
+                    user_id: user.id,
+                    token_hash: token::hash(&challenge),
+                    expires_at: expires_at.to_rfc3339(),
+                    remember_me,
+                })
+                .await?;
+            return Ok(LoginOutcome::TwoFactorRequired { challenge });
+        }
 
-        let new_session = NewSession {
-            id: Uuid::new_v4().to_string(),
-            user_id: user.id,
-            token: token.clone(),
-            expires_at: DateTime::from_timestamp(expires_at as i64, 0)
-                .expect("Valid timestamp should be convertible to DateTime")
-                .to_rfc3339(),
-        };
+        Ok(LoginOutcome::Authenticated(self.start_session(&user.id, remember_me, client).await?))
+    }
 
-        self.user_repo.create_session(new_session).await
+    /// Second sign-in step: exchanges a login challenge plus an authenticator or
+    /// recovery code for a session. Wrong codes count against the account like
+    /// wrong passwords, so asking for fresh challenges buys no extra guesses.
+    pub async fn complete_two_factor_login(
+        &self,
+        challenge: &str,
+        code: &str,
+        client: &ClientInfo,
+    ) -> Result<Session, AppError> {
+        let expired = || AppError::Unauthorized("Your sign-in attempt has expired. Please sign in again.".to_string());
+
+        let record = self.user_repo.get_login_challenge(&token::hash(challenge)).await?
+            .ok_or_else(expired)?;
+        let expires_at = DateTime::parse_from_rfc3339(&record.expires_at).map_err(|_| expired())?;
+        if record.used_at.is_some()
+            || expires_at < self.clock.now()
+            || record.failed_attempts >= LOGIN_CHALLENGE_MAX_FAILURES
+        {
+            return Err(expired());
+        }
+
+        let totp = self.two_factor_enabled(&record.user_id).await?.ok_or_else(expired)?;
+        let user = self.user_repo.get_by_id(&record.user_id).await?
+            .filter(|u| u.is_active)
+            .ok_or_else(expired)?;
+
+        let email_key = user.email.trim().to_lowercase();
+        let now = self.clock.now();
+        let ip_address = client.ip_address.as_deref();
+        self.check_login_throttle(&email_key, ip_address, now).await?;
+
+        let verified = self.verify_second_factor(&user.id, &totp, code).await?;
+        self.record_login_attempt(&email_key, Some(&user.id), ip_address, verified, now).await?;
+        if !verified {
+            self.user_repo.record_login_challenge_failure(&record.id).await?;
+            return Err(AppError::Unauthorized(INVALID_SECOND_FACTOR.to_string()));
+        }
+        if !self.user_repo.consume_login_challenge(&record.id, &timestamp(now)).await? {
+            return Err(expired());
+        }
+
+        self.start_session(&user.id, record.remember_me, client).await
     }
 
     pub async fn validate_token(&self, token: &str) -> Result<User, AppError> {
@@ -96,24 +312,30 @@ impl AuthService {
 
         // Check if session exists and is valid
         let session = self.user_repo.get_session_by_token(token).await?
-            .ok_or_else(|| AppError::InvalidInput("Invalid session".to_string()))?;
+            .ok_or_else(|| AppError::Unauthorized("Invalid session".to_string()))?;
 
         // Check if session is expired
         let expires_at = DateTime::parse_from_rfc3339(&session.expires_at)
-            .map_err(|_| AppError::InvalidInput("Invalid session format".to_string()))?;
+            .map_err(|_| AppError::Unauthorized("Invalid session format".to_string()))?;
         
-        if expires_at < Utc::now() {
-            return Err(AppError::InvalidInput("Session expired".to_string()));
+        let expires_at = expires_at.with_timezone(&Utc);
+        let hard_expiry = DateTime::from_timestamp(claims.exp as i64, 0)
+            .ok_or_else(|| AppError::Unauthorized("Invalid session format".to_string()))?;
+
+        if expires_at < self.clock.now() || hard_expiry < self.clock.now() {
+            return Err(AppError::Unauthorized("Session expired".to_string()));
         }
 
         // Get user
         let user = self.user_repo.get_by_id(&claims.sub).await?
-            .ok_or_else(|| AppError::InvalidInput("User not found".to_string()))?;
+            .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
 
         if !user.is_active {
-            return Err(AppError::InvalidInput("Account is disabled".to_string()));
+            return Err(AppError::Unauthorized("Account is disabled".to_string()));
         }
 
+        self.renew_session(&session, expires_at, hard_expiry).await?;
+
         Ok(user)
     }
 
@@ -121,33 +343,521 @@ impl AuthService {
         self.user_repo.delete_session(token).await
     }
 
-    fn generate_jwt(&self, user_id: &str) -> Result<String, AppError> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("System time should be after Unix epoch")
-            .as_secs();
+    /// Failed sign-ins against the user's account over the last few weeks, newest first.
+    pub async fn recent_failed_logins(&self, user_id: &str) -> Result<Vec<LoginAttempt>, AppError> {
+        let since = self.clock.now() - Duration::days(RECENT_FAILURES_DAYS);
+        self.login_attempts.recent_failures_for_user(user_id, &timestamp(since), 10).await
+    }
+
+    pub async fn two_factor_status(&self, user: &User) -> Result<TwoFactorStatus, AppError> {
+        let totp = self.user_repo.get_totp(&user.id).await?;
+        let enabled = totp.as_ref().is_some_and(|t| t.enabled_at.is_some());
+        let pending = totp
+            .filter(|t| t.enabled_at.is_none())
+            .map(|t| self.enrollment_details(user, t.secret));
+        let recovery_codes_left = if enabled {
+            self.user_repo.count_unused_recovery_codes(&user.id).await?
+        } else {
+            0
+        };
+
+        Ok(TwoFactorStatus { enabled, pending, recovery_codes_left })
+    }
+
+    /// Starts TOTP enrollment with a fresh secret. Two-factor stays off until
+    /// `confirm_totp_enrollment` sees a code generated from it.
+    pub async fn begin_totp_enrollment(&self, user: &User) -> Result<TotpEnrollment, AppError> {
+        let secret = totp::encode_secret(&totp::generate_secret());
+        if !self.user_repo.set_pending_totp(&user.id, &secret).await? {
+            return Err(AppError::InvalidInput("Two-factor authentication is already enabled".to_string()));
+        }
+        Ok(self.enrollment_details(user, secret))
+    }
+
+    /// Turns two-factor on once the user proves their app produces valid codes.
+    /// Returns the one-time recovery codes, which are only shown now.
+    pub async fn confirm_totp_enrollment(&self, user: &User, code: &str) -> Result<Vec<String>, AppError> {
+        let pending = self.user_repo.get_totp(&user.id).await?
+            .filter(|t| t.enabled_at.is_none())
+            .ok_or_else(|| AppError::InvalidInput("Start two-factor setup first".to_string()))?;
+
+        let secret = decode_totp_secret(&pending)?;
+        let step = totp::verify(&secret, code, self.clock.now().timestamp())
+            .ok_or_else(|| AppError::InvalidInput("That code didn't match. Check your authenticator app and try again.".to_string()))?;
+
+        let (codes, hashed) = generate_recovery_codes();
+        self.user_repo
+            .enable_totp(&user.id, &timestamp(self.clock.now()), step, &hashed)
+            .await?;
+        tracing::info!(user_id = %user.id, "Two-factor authentication enabled");
+        Ok(codes)
+    }
+
+    /// Turns two-factor off; needs the password and a current or recovery code.
+    pub async fn disable_totp(&self, user: &User, current_password: &str, code: &str) -> Result<(), AppError> {
+        self.verify_current_password(user, current_password)?;
+        let totp = self.two_factor_enabled(&user.id).await?
+            .ok_or_else(|| AppError::InvalidInput("Two-factor authentication is not enabled".to_string()))?;
+        if !self.verify_second_factor(&user.id, &totp, code).await? {
+            return Err(AppError::InvalidInput("Invalid authentication code".to_string()));
+        }
+
+        self.user_repo.delete_totp(&user.id).await?;
+        tracing::info!(user_id = %user.id, "Two-factor authentication disabled");
+        Ok(())
+    }
+
+    /// Replaces every recovery code, used or not, with a fresh set.
+    pub async fn regenerate_recovery_codes(&self, user: &User, current_password: &str) -> Result<Vec<String>, AppError> {
+        self.verify_current_password(user, current_password)?;
+        if self.two_factor_enabled(&user.id).await?.is_none() {
+            return Err(AppError::InvalidInput("Two-factor authentication is not enabled".to_string()));
+        }
+
+        let (codes, hashed) = generate_recovery_codes();
+        self.user_repo.replace_recovery_codes(&user.id, &hashed).await?;
+        Ok(codes)
+    }
+
+    /// Publishing a frogol needs a verified address under every policy.
+    pub fn ensure_can_publish(&self, user: &User) -> Result<(), AppError> {
+        if !user.is_email_verified() {
+            return Err(AppError::Forbidden("Please verify your email address before publishing a frogol".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Sends a fresh verification link if `email` belongs to an active, unverified
+    /// account. Succeeds silently otherwise, like `request_password_reset`.
+    pub async fn resend_email_verification(&self, email: &str) -> Result<(), AppError> {
+        let user = match self.user_repo.get_by_email(email).await? {
+            Some(user) if user.is_active && !user.is_email_verified() => user,
+            _ => return Ok(()),
+        };
+
+        self.send_email_verification(
+            &user.id,
+            &user.email,
+            "Confirm your Frogolio email address",
+            "Here is a new link to confirm the email address for your Frogolio account.",
+        )
+        .await
+    }
+
+    /// Runs `request_password_reset` on its own task and returns at once.
+    ///
+    /// Known addresses cost a token write and an email, unknown ones nothing,
+    /// so a caller that waited would answer at a telling speed. Failures are
+    /// logged, since nobody is waiting for them.
+    pub fn spawn_password_reset(self: &Arc<Self>, email: String) -> JoinHandle<()> {
+        let auth = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = auth.request_password_reset(&email).await {
+                tracing::error!("Failed to send password reset email: {}", e);
+            }
+        })
+    }
+
+    /// Emails a single-use reset link if `email` belongs to an active account.
+    ///
+    /// Succeeds silently for unknown addresses so the form cannot be used to
+    /// discover which emails are registered.
+    pub async fn request_password_reset(&self, email: &str) -> Result<(), AppError> {
+        let user = match self.user_repo.get_by_email(email).await? {
+            Some(user) if user.is_active => user,
+            _ => return Ok(()),
+        };
+
+        let reset_token = token::generate();
+        let expires_at = self.clock.now() + Duration::minutes(PASSWORD_RESET_TTL_MINUTES);
+        self.user_repo
+            .create_password_reset_token(NewPasswordResetToken {
+                id: Uuid::new_v4().to_string(),
+                user_id: user.id,
+                token_hash: token::hash(&reset_token),
+                expires_at: expires_at.to_rfc3339(),
+            })
+            .await?;
+
+        let link = format!("{}/reset-password/{}", self.config.base_url.trim_end_matches('/'), reset_token);
+        self.mailer
+            .send(Email {
+                to: user.email,
+                subject: "Reset your Frogolio password".to_string(),
+                body: format!(
+                    "Someone asked to reset the password for your Frogolio account.\n\n\
+                     Open this link within {} minutes to choose a new one:\n{}\n\n\
+                     If this wasn't you, you can ignore this email.",
+                    PASSWORD_RESET_TTL_MINUTES, link
+                ),
+            })
+            .await
+    }
+
+    /// Sets a new password using an emailed reset token and signs the user out
+    /// everywhere. The token is consumed whether or not it is reused later.
+    pub async fn reset_password(&self, reset_token: &str, new_password: &str) -> Result<(), AppError> {
+        let invalid = || AppError::InvalidInput("Invalid or expired reset link".to_string());
+
+        let record = self.user_repo.get_password_reset_token(&token::hash(reset_token)).await?
+            .ok_or_else(invalid)?;
+        if record.used_at.is_some() {
+            return Err(invalid());
+        }
+        let expires_at = DateTime::parse_from_rfc3339(&record.expires_at).map_err(|_| invalid())?;
+        if expires_at < self.clock.now() {
+            return Err(invalid());
+        }
+
+        let now = self.clock.now().to_rfc3339();
+        if !self.user_repo.consume_password_reset_token(&record.id, &now).await? {
+            return Err(invalid());
+        }
+
+        let password_hash = hash(new_password, DEFAULT_COST)
+            .map_err(|_| AppError::Internal("Failed to hash password".to_string()))?;
+        self.user_repo.update_password(&record.user_id, &password_hash).await?;
+        self.user_repo.invalidate_password_reset_tokens(&record.user_id, &now).await?;
+        // Following the emailed link proves control of the address
+        self.user_repo.mark_email_verified(&record.user_id, &now).await?;
+        let revoked = self.user_repo.delete_user_sessions(&record.user_id).await?;
+        tracing::info!(user_id = %record.user_id, revoked_sessions = revoked, "Password reset");
+
+        Ok(())
+    }
+
+    /// Changes the password after checking the current one, then signs out
+    /// every other session so only `current_token` stays valid.
+    pub async fn change_password(
+        &self,
+        user: &User,
+        current_token: &str,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<(), AppError> {
+        self.verify_current_password(user, current_password)?;
+
+        let password_hash = hash(new_password, DEFAULT_COST)
+            .map_err(|_| AppError::Internal("Failed to hash password".to_string()))?;
+        self.user_repo.update_password(&user.id, &password_hash).await?;
+        self.user_repo.delete_user_sessions_except(&user.id, current_token).await?;
+        Ok(())
+    }
+
+    /// Emails a confirmation link to `new_email`; the address only changes once
+    /// that link is opened via `verify_email`.
+    pub async fn request_email_change(
+        &self,
+        user: &User,
+        current_password: &str,
+        new_email: &str,
+    ) -> Result<(), AppError> {
+        self.verify_current_password(user, current_password)?;
+
+        if new_email.eq_ignore_ascii_case(&user.email) {
+            return Err(AppError::InvalidInput("That is already your email address".to_string()));
+        }
+        if self.user_repo.get_by_email(new_email).await?.is_some() {
+            return Err(AppError::Conflict("Email is already in use".to_string()));
+        }
+
+        self.send_email_verification(
+            &user.id,
+            new_email,
+            "Confirm your new Frogolio email address",
+            "Please confirm you want to use this email address for your Frogolio account.",
+        )
+        .await
+    }
+
+    /// Confirms an emailed verification token and applies the address it was issued for.
+    pub async fn verify_email(&self, verification_token: &str) -> Result<User, AppError> {
+        let invalid = || AppError::InvalidInput("Invalid or expired verification link".to_string());
+
+        let record = self.user_repo.get_email_verification_token(&token::hash(verification_token)).await?
+            .ok_or_else(invalid)?;
+        if record.used_at.is_some() {
+            return Err(invalid());
+        }
+        let expires_at = DateTime::parse_from_rfc3339(&record.expires_at).map_err(|_| invalid())?;
+        if expires_at < self.clock.now() {
+            return Err(invalid());
+        }
+
+        // The address may have been claimed since the link was sent
+        if let Some(other) = self.user_repo.get_by_email(&record.email).await? {
+            if other.id != record.user_id {
+                return Err(AppError::Conflict("Email is already in use".to_string()));
+            }
+        }
+
+        let now = self.clock.now().to_rfc3339();
+        if !self.user_repo.consume_email_verification_token(&record.id, &now).await? {
+            return Err(invalid());
+        }
+        self.user_repo.set_verified_email(&record.user_id, &record.email, &now).await?;
+        // Older links would otherwise switch the address back
+        self.user_repo.invalidate_email_verification_tokens(&record.user_id, &now).await?;
+
+        self.user_repo.get_by_id(&record.user_id).await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))
+    }
 
+    /// Sessions for `user_id` that have not yet expired, newest first.
+    pub async fn list_sessions(&self, user_id: &str) -> Result<Vec<Session>, AppError> {
+        let now = self.clock.now();
+        let sessions = self.user_repo.list_user_sessions(user_id).await?;
+        Ok(sessions
+            .into_iter()
+            .filter(|s| {
+                DateTime::parse_from_rfc3339(&s.expires_at)
+                    .map(|exp| exp > now)
+                    .unwrap_or(false)
+            })
+            .collect())
+    }
+
+    pub async fn revoke_session(&self, user_id: &str, session_id: &str) -> Result<(), AppError> {
+        if !self.user_repo.delete_user_session(user_id, session_id).await? {
+            return Err(AppError::NotFound("Session not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Permanently deletes the account and everything it owns after checking
+    /// the password. Returns avatar filenames the caller should remove from disk.
+    pub async fn delete_account(&self, user: &User, current_password: &str) -> Result<Vec<String>, AppError> {
+        self.verify_current_password(user, current_password)?;
+        let avatar_files = self.user_repo.delete_user(&user.id).await?;
+        tracing::info!(user_id = %user.id, "Account deleted");
+        Ok(avatar_files)
+    }
+
+    /// Stores a new verification token for `email` and mails its link there.
+    async fn send_email_verification(&self, user_id: &str, email: &str, subject: &str, intro: &str) -> Result<(), AppError> {
+        let verification_token = token::generate();
+        let expires_at = self.clock.now() + Duration::hours(EMAIL_VERIFICATION_TTL_HOURS);
+        self.user_repo
+            .create_email_verification_token(NewEmailVerificationToken {
+                id: Uuid::new_v4().to_string(),
+                user_id: user_id.to_string(),
+                email: email.to_string(),
+                token_hash: token::hash(&verification_token),
+                expires_at: expires_at.to_rfc3339(),
+            })
+            .await?;
+
+        let link = format!("{}/verify-email/{}", self.config.base_url.trim_end_matches('/'), verification_token);
+        self.mailer
+            .send(Email {
+                to: email.to_string(),
+                subject: subject.to_string(),
+                body: format!(
+                    "{}\n\nOpen this link within {} hours to confirm:\n{}\n\n\
+                     If you didn't ask for this, you can ignore this email.",
+                    intro, EMAIL_VERIFICATION_TTL_HOURS, link
+                ),
+            })
+            .await
+    }
+
+    async fn record_login_attempt(
+        &self,
+        email_key: &str,
+        user_id: Option<&str>,
+        ip_address: Option<&str>,
+        succeeded: bool,
+        now: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        self.login_attempts
+            .record(NewLoginAttempt {
+                id: Uuid::new_v4().to_string(),
+                email: email_key.to_string(),
+                user_id: user_id.map(str::to_string),
+                ip_address: ip_address.map(str::to_string),
+                succeeded,
+                attempted_at: timestamp(now),
+            })
+            .await
+    }
+
+    async fn check_login_throttle(
+        &self,
+        email_key: &str,
+        ip_address: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        let since = timestamp(now - Duration::hours(LOGIN_FAILURE_WINDOW_HOURS));
+
+        let account = self.login_attempts.account_failures(email_key, &since).await?;
+        let mut retry_after = lockout_remaining(&account, ACCOUNT_FREE_FAILURES, now);
+        if let Some(ip) = ip_address {
+            let by_ip = self.login_attempts.ip_failures(ip, &since).await?;
+            retry_after = retry_after.max(lockout_remaining(&by_ip, IP_FREE_FAILURES, now));
+        }
+
+        match retry_after {
+            Some(secs) => Err(AppError::RateLimited {
+                message: format!("Too many failed sign-in attempts. Try again in {} seconds.", secs),
+                retry_after_secs: secs,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    async fn start_session(&self, user_id: &str, remember_me: bool, client: &ClientInfo) -> Result<Session, AppError> {
+        let now = self.clock.now();
+        let token = self.generate_jwt(user_id, now + self.config.session_max_age)?;
+        let expires_at = now + self.session_ttl(remember_me);
+
+        let new_session = NewSession {
+            id: Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            token,
+            expires_at: expires_at.to_rfc3339(),
+            last_seen_at: timestamp(now),
+            user_agent: client.user_agent.clone(),
+            ip_address: client.ip_address.clone(),
+            remember_me,
+        };
+
+        self.user_repo.create_session(new_session).await
+    }
+
+    /// Longest any session can last, used for the lifetime of remembered cookies.
+    pub fn session_max_age(&self) -> Duration {
+        self.config.session_max_age
+    }
+
+    /// Idle lifetime for a session of the given kind.
+    pub fn session_ttl(&self, remember_me: bool) -> Duration {
+        if remember_me {
+            self.config.remember_me_ttl
+        } else {
+            self.config.session_ttl
+        }
+    }
+
+    /// Pushes the expiry of a session that is in use and has less than half of
+    /// its lifetime left, never past `hard_expiry`, and refreshes `last_seen_at`.
+    async fn renew_session(&self, session: &Session, expires_at: DateTime<Utc>, hard_expiry: DateTime<Utc>) -> Result<(), AppError> {
+        let now = self.clock.now();
+        let ttl = self.session_ttl(session.remember_me);
+
+        let mut new_expiry = expires_at;
+        if expires_at - now < ttl / 2 {
+            new_expiry = (now + ttl).min(hard_expiry).max(expires_at);
+        }
+
+        let seen_recently = session
+            .last_seen_at
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .is_some_and(|t| now - t.with_timezone(&Utc) < Duration::minutes(LAST_SEEN_RESOLUTION_MINUTES));
+
+        if new_expiry != expires_at || !seen_recently {
+            self.user_repo
+                .touch_session(&session.id, &timestamp(now), &new_expiry.to_rfc3339())
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn two_factor_enabled(&self, user_id: &str) -> Result<Option<UserTotp>, AppError> {
+        Ok(self.user_repo.get_totp(user_id).await?.filter(|t| t.enabled_at.is_some()))
+    }
+
+    fn enrollment_details(&self, user: &User, secret: String) -> TotpEnrollment {
+        TotpEnrollment {
+            otpauth_uri: totp::otpauth_uri(TOTP_ISSUER, &user.email, &secret),
+            secret,
+        }
+    }
+
+    /// Accepts either an unused authenticator code or an unused recovery code,
+    /// marking whichever matched as spent.
+    async fn verify_second_factor(&self, user_id: &str, totp: &UserTotp, code: &str) -> Result<bool, AppError> {
+        let secret = decode_totp_secret(totp)?;
+        if let Some(step) = totp::verify(&secret, code, self.clock.now().timestamp()) {
+            return self.user_repo.use_totp_step(user_id, step).await;
+        }
+
+        let recovery_code = normalize_recovery_code(code);
+        if recovery_code.is_empty() {
+            return Ok(false);
+        }
+        self.user_repo
+            .consume_recovery_code(user_id, &token::hash(&recovery_code), &timestamp(self.clock.now()))
+            .await
+    }
+
+    fn verify_current_password(&self, user: &User, password: &str) -> Result<(), AppError> {
+        let is_valid = match &user.password_hash {
+            Some(password_hash) => verify(password, password_hash)
+                .map_err(|_| AppError::Internal("Failed to verify password".to_string()))?,
+            None => false,
+        };
+        if !is_valid {
+            return Err(AppError::InvalidInput("Current password is incorrect".to_string()));
+        }
+        Ok(())
+    }
+
+    fn generate_jwt(&self, user_id: &str, expires_at: DateTime<Utc>) -> Result<String, AppError> {
         let claims = Claims {
             sub: user_id.to_string(),
-            exp: now + (24 * 60 * 60), // 24 hours
-            iat: now,
+            exp: expires_at.timestamp() as u64,
+            iat: self.clock.now().timestamp() as u64,
+            jti: Uuid::new_v4().to_string(),
         };
 
-        encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(self.jwt_secret.as_ref()),
-        )
-        .map_err(|_| AppError::InternalError("Failed to generate JWT".to_string()))
+        self.config.jwt_keys.encode(&claims)
     }
 
     fn verify_jwt(&self, token: &str) -> Result<Claims, AppError> {
-        decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(self.jwt_secret.as_ref()),
-            &Validation::default(),
-        )
-        .map(|data| data.claims)
-        .map_err(|_| AppError::InvalidInput("Invalid token".to_string()))
+        self.config.jwt_keys.decode(token)
     }
-} 
\ No newline at end of file
+}
+
+fn decode_totp_secret(totp: &UserTotp) -> Result<Vec<u8>, AppError> {
+    totp::decode_secret(&totp.secret)
+        .ok_or_else(|| AppError::Internal("Stored two-factor secret is invalid".to_string()))
+}
+
+/// Plain codes to show the user once, and `(id, hash)` pairs to store.
+fn generate_recovery_codes() -> (Vec<String>, Vec<(String, String)>) {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let raw = &token::generate()[..10];
+            let code = format!("{}-{}", &raw[..5], &raw[5..]);
+            let hashed = (Uuid::new_v4().to_string(), token::hash(&normalize_recovery_code(&code)));
+            (code, hashed)
+        })
+        .unzip()
+}
+
+/// Recovery codes are matched ignoring case, dashes and spaces.
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Fixed-width UTC timestamp so stored attempts compare correctly as strings.
+pub(crate) fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Seconds left on a lockout, if `stats` has used up its `free_failures`.
+/// Each failure past the allowance doubles the wait, up to the maximum.
+fn lockout_remaining(stats: &FailureStats, free_failures: i64, now: DateTime<Utc>) -> Option<u64> {
+    if stats.failures < free_failures {
+        return None;
+    }
+    let last_failure = DateTime::parse_from_rfc3339(stats.last_failure_at.as_deref()?).ok()?;
+    let doublings = (stats.failures - free_failures).min(16) as u32;
+    let delay = (LOGIN_BACKOFF_BASE_SECS << doublings).min(LOGIN_BACKOFF_MAX_SECS);
+    let remaining_ms = (last_failure.with_timezone(&Utc) + Duration::seconds(delay) - now).num_milliseconds();
+    (remaining_ms > 0).then_some((remaining_ms as u64).div_ceil(1000))
+}
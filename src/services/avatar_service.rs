@@ -1,30 +1,64 @@
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
 use sqlx::SqlitePool;
+use uuid::Uuid;
 use axum_typed_multipart::FieldData;
 use tempfile::NamedTempFile;
 
+use crate::config::AvatarConfig;
 use crate::errors::AppError;
+use crate::metrics::Metrics;
 use crate::handler::image_handler::{
     process_and_save_image,
     save_avatar_image_metadata,
     get_frogol_avatar_image,
     delete_all_avatar_images_for_frogol,
+    delete_images_batch,
+    avatar_file_in_use,
 };
 
 pub struct AvatarService {
     pool: SqlitePool,
     image_save_dir: PathBuf,
+    max_upload_bytes: usize,
+    metrics: Arc<Metrics>,
 }
 
 impl AvatarService {
-    pub fn new(pool: SqlitePool, image_save_dir: PathBuf) -> Self {
+    pub fn new(pool: SqlitePool, config: AvatarConfig, metrics: Arc<Metrics>) -> Self {
         Self {
             pool,
-            image_save_dir,
+            image_save_dir: config.dir,
+            max_upload_bytes: config.max_upload_bytes,
+            metrics,
         }
     }
 
+    /// Largest image accepted, in bytes.
+    pub fn max_upload_bytes(&self) -> usize {
+        self.max_upload_bytes
+    }
+
+    /// Where an uploaded file called `filename` is stored.
+    pub fn file_path(&self, filename: &str) -> PathBuf {
+        self.image_save_dir.join(filename)
+    }
+
+    /// Stores an uploaded image under a fresh name with `extension`, returning
+    /// that name.
+    pub async fn save_upload(&self, extension: &str, bytes: &[u8]) -> Result<String, AppError> {
+        let filename = format!("{}.{}", Uuid::new_v4(), extension);
+        tokio::fs::write(self.file_path(&filename), bytes).await.map_err(|e| {
+            tracing::error!("Failed to save avatar file: {}", e);
+            AppError::Internal("Failed to save uploaded file".to_string())
+        })?;
+        self.metrics.avatar_uploads.inc();
+        Ok(filename)
+    }
+
     /// Uploads a new avatar image for a frogol
+    #[allow(dead_code)]
     pub async fn upload_avatar(
         &self,
         frogol_id: &str,
@@ -42,22 +76,83 @@ impl AvatarService {
 
         // Save metadata to database
         save_avatar_image_metadata(&self.pool, frogol_id, &processed_data).await?;
+        self.metrics.avatar_uploads.inc();
 
         Ok(processed_data.unique_filename)
     }
 
     /// Gets the current avatar image filename for a frogol
+    #[allow(dead_code)]
     pub async fn get_avatar_filename(&self, frogol_id: &str) -> Result<Option<String>, AppError> {
         let avatar_image = get_frogol_avatar_image(&self.pool, frogol_id).await?;
         Ok(avatar_image.map(|img| img.image_filename))
     }
 
     /// Deletes the avatar image for a frogol
+    #[allow(dead_code)]
     pub async fn delete_avatar(&self, frogol_id: &str) -> Result<(), AppError> {
         delete_all_avatar_images_for_frogol(&self.pool, frogol_id, &self.image_save_dir).await
     }
 
+    /// Removes avatar files left behind by deleted rows. Failures are logged
+    /// rather than returned since the database side is already gone.
+    pub async fn delete_avatar_files(&self, filenames: Vec<String>) {
+        for err in delete_images_batch(filenames, &self.image_save_dir).await {
+            tracing::warn!("Avatar cleanup failed: {}", err);
+        }
+    }
+
+    /// Deletes uploaded files that no frogol references any more, returning how
+    /// many were removed. Only files last modified before `older_than` are
+    /// considered, so an upload whose database row is still being written is
+    /// left alone.
+    pub async fn delete_orphaned_files(&self, older_than: SystemTime) -> Result<u64, AppError> {
+        let mut entries = match tokio::fs::read_dir(&self.image_save_dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => {
+                tracing::error!("Failed to list avatar directory {:?}: {}", self.image_save_dir, e);
+                return Err(AppError::Internal("Failed to list avatar files".to_string()));
+            }
+        };
+
+        let mut orphaned = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(|e| {
+            tracing::error!("Failed to read avatar directory {:?}: {}", self.image_save_dir, e);
+            AppError::Internal("Failed to list avatar files".to_string())
+        })? {
+            let path = entry.path();
+            // Only files shaped like our uploads; anything else was put there by hand
+            let is_upload = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| Uuid::parse_str(stem).is_ok());
+            let Some(filename) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if !is_upload {
+                continue;
+            }
+
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+            let old_enough = metadata.modified().is_ok_and(|modified| modified < older_than);
+            if metadata.is_file() && old_enough && !avatar_file_in_use(&self.pool, filename).await? {
+                orphaned.push(filename.to_string());
+            }
+        }
+
+        let found = orphaned.len() as u64;
+        let errors = delete_images_batch(orphaned, &self.image_save_dir).await;
+        for err in &errors {
+            tracing::warn!("Avatar cleanup failed: {}", err);
+        }
+        Ok(found - errors.len() as u64)
+    }
+
     /// Gets the full URL for an avatar image
+    #[allow(dead_code)]
     pub fn get_avatar_url(&self, filename: &str) -> String {
         format!("/static/avatars/{}", filename)
     }
@@ -0,0 +1,97 @@
+//! Tells bot clicks from human ones, so counts can leave them out.
+//!
+//! A click is flagged as a bot when any of these hold:
+//! - the user agent names a crawler, previewer or HTTP library (`user_agent`);
+//! - the request only looks ahead: a `HEAD`, or a prefetch or prerender as
+//!   announced by `Purpose`, `Sec-Purpose`, `X-Purpose` or `X-Moz`;
+//! - the visitor made `BURST_LIMIT` clicks or more in the last
+//!   `BURST_WINDOW_SECONDS`, which people following links do not.
+//!
+//! Bot clicks are still recorded, flagged with `is_bot`.
+
+use axum::http::{HeaderMap, Method};
+use chrono::Duration;
+use std::sync::Arc;
+
+use crate::errors::AppError;
+use crate::repo::click_repo::ClickRepo;
+use crate::services::{auth_service::timestamp, clock::Clock, user_agent::UserAgentInfo, visitor_service::Visitor};
+
+/// Clicks one visitor may make within `BURST_WINDOW_SECONDS` before the next
+/// is flagged as a bot.
+pub const BURST_LIMIT: i64 = 10;
+pub const BURST_WINDOW_SECONDS: i64 = 60;
+
+/// Headers browsers and previewers send on speculative requests.
+const PURPOSE_HEADERS: &[&str] = &["purpose", "sec-purpose", "x-purpose", "x-moz"];
+
+/// Why a click was flagged as a bot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BotSignal {
+    UserAgent,
+    Prefetch,
+    Burst,
+}
+
+impl BotSignal {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BotSignal::UserAgent => "user_agent",
+            BotSignal::Prefetch => "prefetch",
+            BotSignal::Burst => "burst",
+        }
+    }
+}
+
+/// Whether the request fetches the link ahead of, or instead of, a visit.
+pub fn is_prefetch(method: &Method, headers: &HeaderMap) -> bool {
+    if method == Method::HEAD {
+        return true;
+    }
+    PURPOSE_HEADERS.iter().any(|name| {
+        headers
+            .get_all(*name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .any(|value| {
+                let value = value.to_ascii_lowercase();
+                value.contains("prefetch") || value.contains("prerender") || value.contains("preview")
+            })
+    })
+}
+
+pub struct BotDetector {
+    clicks: ClickRepo,
+    clock: Arc<dyn Clock>,
+}
+
+impl BotDetector {
+    pub fn new(clicks: ClickRepo, clock: Arc<dyn Clock>) -> Self {
+        Self { clicks, clock }
+    }
+
+    /// The first signal that the click about to be recorded comes from a bot, if any.
+    /// Only visitors with an id can be caught bursting.
+    pub async fn detect(
+        &self,
+        method: &Method,
+        headers: &HeaderMap,
+        agent: &UserAgentInfo,
+        visitor: &Visitor,
+    ) -> Result<Option<BotSignal>, AppError> {
+        if agent.is_bot() {
+            return Ok(Some(BotSignal::UserAgent));
+        }
+        if is_prefetch(method, headers) {
+            return Ok(Some(BotSignal::Prefetch));
+        }
+        if let Some(visitor_id) = &visitor.visitor_id {
+            let since = timestamp(self.clock.now() - Duration::seconds(BURST_WINDOW_SECONDS));
+            let recent = self.clicks.count_recent_visitor_clicks(visitor_id, &since).await?;
+            if recent >= BURST_LIMIT {
+                return Ok(Some(BotSignal::Burst));
+            }
+        }
+        Ok(None)
+    }
+}
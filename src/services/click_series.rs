@@ -0,0 +1,227 @@
+//! Clicks over time, in hour, day or week buckets of the viewer's local time.
+//!
+//! Day and week buckets in UTC are summed from `click_daily_rollups`, and
+//! every other series in a time zone a whole number of hours from UTC from
+//! `click_hourly_rollups`, shifted into local time. `ClickRepo::record_click`
+//! keeps both up to date. Hours cannot be split, so time zones with a
+//! minutes part (such as +05:30) are counted from the clicks in range
+//! themselves, and their range is capped at `MAX_UNROLLED_DAYS`.
+
+use chrono::{DateTime, Datelike, Days, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::errors::AppError;
+use crate::repo::click_repo::{ClickRepo, ClickScope};
+use crate::services::auth_service::timestamp;
+
+/// Days shown when no range is given, today included.
+pub const DEFAULT_DAYS: u64 = 30;
+/// Buckets one series may have: six weeks of hours, or years of days.
+pub const MAX_BUCKETS: u64 = 1008;
+/// Furthest from UTC any time zone is, in minutes.
+const MAX_TZ_OFFSET: i32 = 14 * 60;
+/// Days one series may span in a time zone the rollups cannot serve.
+pub const MAX_UNROLLED_DAYS: u64 = 92;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Bucket {
+    Hour,
+    #[default]
+    Day,
+    /// Weeks start on Monday.
+    Week,
+}
+
+impl Bucket {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Bucket::Hour => "hour",
+            Bucket::Day => "day",
+            Bucket::Week => "week",
+        }
+    }
+}
+
+/// A requested series, as sent in the query string. Every field is optional.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct ClickSeriesParams {
+    pub bucket: Option<Bucket>,
+    /// First day, `YYYY-MM-DD` in the viewer's time zone.
+    pub from: Option<String>,
+    /// Last day, included.
+    pub to: Option<String>,
+    /// The viewer's offset from UTC in minutes, east positive.
+    pub tz_offset: Option<i32>,
+    /// Count bot clicks too.
+    pub include_bots: Option<bool>,
+}
+
+/// A validated series request.
+#[derive(Debug, Clone)]
+pub struct ClickSeriesQuery {
+    pub bucket: Bucket,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub tz_offset: i32,
+    pub include_bots: bool,
+}
+
+/// Clicks in the bucket starting at `start`, local time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClickBucket {
+    pub start: NaiveDateTime,
+    pub clicks: i64,
+}
+
+fn parse_day(name: &str, value: Option<&str>) -> Result<Option<NaiveDate>, AppError> {
+    match value.map(str::trim).filter(|v| !v.is_empty()) {
+        None => Ok(None),
+        Some(v) => NaiveDate::parse_from_str(v, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|_| AppError::InvalidInput(format!("{} must be a date like 2026-01-31", name))),
+    }
+}
+
+impl ClickSeriesParams {
+    /// Fills in the defaults: day buckets over the last `DEFAULT_DAYS` days
+    /// up to today as of `now`, in UTC.
+    pub fn resolve(&self, now: DateTime<Utc>) -> Result<ClickSeriesQuery, AppError> {
+        let tz_offset = self.tz_offset.unwrap_or(0);
+        if !(-MAX_TZ_OFFSET..=MAX_TZ_OFFSET).contains(&tz_offset) {
+            return Err(AppError::InvalidInput(format!(
+                "tz_offset must be between -{} and {} minutes",
+                MAX_TZ_OFFSET, MAX_TZ_OFFSET
+            )));
+        }
+        let offset = FixedOffset::east_opt(tz_offset * 60).expect("offset is within a day");
+        let to = parse_day("to", self.to.as_deref())?.unwrap_or_else(|| now.with_timezone(&offset).date_naive());
+        let from = parse_day("from", self.from.as_deref())?
+            .unwrap_or_else(|| to - Days::new(DEFAULT_DAYS - 1));
+        if from > to {
+            return Err(AppError::InvalidInput("from must not be after to".to_string()));
+        }
+
+        let query = ClickSeriesQuery {
+            bucket: self.bucket.unwrap_or_default(),
+            from,
+            to,
+            tz_offset,
+            include_bots: self.include_bots.unwrap_or(false),
+        };
+        if query.bucket_starts_len() > MAX_BUCKETS {
+            return Err(AppError::InvalidInput(format!(
+                "That range has more than {} {} buckets",
+                MAX_BUCKETS,
+                query.bucket.as_str()
+            )));
+        }
+        if !query.hour_aligned() && query.days() > MAX_UNROLLED_DAYS {
+            return Err(AppError::InvalidInput(format!(
+                "Time zones that are not a whole number of hours from UTC can chart at most {} days",
+                MAX_UNROLLED_DAYS
+            )));
+        }
+        Ok(query)
+    }
+}
+
+impl ClickSeriesQuery {
+    pub fn offset(&self) -> FixedOffset {
+        FixedOffset::east_opt(self.tz_offset * 60).expect("offset is validated")
+    }
+
+    /// The bucket a local time falls in.
+    fn bucket_start(&self, at: NaiveDateTime) -> NaiveDateTime {
+        match self.bucket {
+            Bucket::Hour => at.date().and_hms_opt(at.hour(), 0, 0).expect("an hour of the day is valid"),
+            Bucket::Day => at.date().and_time(NaiveTime::MIN),
+            Bucket::Week => week_start(at.date()).and_time(NaiveTime::MIN),
+        }
+    }
+
+    fn days(&self) -> u64 {
+        (self.to - self.from).num_days() as u64 + 1
+    }
+
+    /// Whether the series can be summed from the rollups, that is whether
+    /// local hours line up with UTC hours.
+    fn hour_aligned(&self) -> bool {
+        self.tz_offset % 60 == 0
+    }
+
+    fn bucket_starts_len(&self) -> u64 {
+        let days = self.days();
+        match self.bucket {
+            Bucket::Hour => days * 24,
+            Bucket::Day => days,
+            Bucket::Week => (self.to - week_start(self.from)).num_days() as u64 / 7 + 1,
+        }
+    }
+
+    /// Every bucket in range, in order. A week bucket may start before `from`.
+    fn bucket_starts(&self) -> Vec<NaiveDateTime> {
+        let (first, step) = match self.bucket {
+            Bucket::Hour => (self.from.and_time(NaiveTime::MIN), chrono::Duration::hours(1)),
+            Bucket::Day => (self.from.and_time(NaiveTime::MIN), chrono::Duration::days(1)),
+            Bucket::Week => (week_start(self.from).and_time(NaiveTime::MIN), chrono::Duration::weeks(1)),
+        };
+        let end = (self.to + Days::new(1)).and_time(NaiveTime::MIN);
+        std::iter::successors(Some(first), |start| Some(*start + step))
+            .take_while(|start| *start < end)
+            .collect()
+    }
+
+    /// The range in UTC: midnight before `from` up to midnight after `to`.
+    pub fn utc_range(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        let shift = chrono::Duration::minutes(i64::from(self.tz_offset));
+        let start = self.from.and_time(NaiveTime::MIN) - shift;
+        let end = (self.to + Days::new(1)).and_time(NaiveTime::MIN) - shift;
+        (start.and_utc(), end.and_utc())
+    }
+}
+
+/// The Monday on or before `day`.
+fn week_start(day: NaiveDate) -> NaiveDate {
+    day - Days::new(u64::from(day.weekday().num_days_from_monday()))
+}
+
+/// Clicks in `scope` per bucket of `query`, every bucket in range included.
+pub async fn click_series(repo: &ClickRepo, scope: ClickScope<'_>, query: &ClickSeriesQuery) -> Result<Vec<ClickBucket>, AppError> {
+    let counts: Vec<(NaiveDateTime, i64)> = if query.tz_offset == 0 && query.bucket != Bucket::Hour {
+        repo.daily_click_counts(scope, query.include_bots, &query.from.to_string(), &query.to.to_string())
+            .await?
+            .into_iter()
+            .filter_map(|(day, clicks)| Some((NaiveDate::parse_from_str(&day, "%Y-%m-%d").ok()?.and_time(NaiveTime::MIN), clicks)))
+            .collect()
+    } else {
+        let format = match query.bucket {
+            Bucket::Hour => "%Y-%m-%dT%H:00:00",
+            Bucket::Day | Bucket::Week => "%Y-%m-%dT00:00:00",
+        };
+        let modifier = format!("{:+} minutes", query.tz_offset);
+        let (start, end) = query.utc_range();
+        let (start, end) = (timestamp(start), timestamp(end));
+        let local_counts = if query.hour_aligned() {
+            repo.hourly_click_counts(scope, query.include_bots, format, &modifier, &start[..13], &end[..13]).await?
+        } else {
+            repo.local_click_counts(scope, query.include_bots, format, &modifier, &start, &end).await?
+        };
+        local_counts
+            .into_iter()
+            .filter_map(|(at, clicks)| Some((NaiveDateTime::parse_from_str(&at, "%Y-%m-%dT%H:%M:%S").ok()?, clicks)))
+            .collect()
+    };
+
+    let mut by_bucket: HashMap<NaiveDateTime, i64> = HashMap::new();
+    for (at, clicks) in counts {
+        *by_bucket.entry(query.bucket_start(at)).or_default() += clicks;
+    }
+    Ok(query
+        .bucket_starts()
+        .into_iter()
+        .map(|start| ClickBucket { start, clicks: by_bucket.get(&start).copied().unwrap_or(0) })
+        .collect())
+}
@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+
+/// Source of the current time, injectable so time-dependent rules such as
+/// login backoff can be exercised without sleeping.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Wall-clock time.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
@@ -1,28 +1,42 @@
 use crate::{
     errors::AppError,
+    metrics::Metrics,
+    middleware::request_id::record_frogol_id,
     repo::{
         frogol_repo::{Frogol, FrogolRepo, NewFrogol, FrogolSummary, UserAnalytics},
         link_repo::{Link, LinkRepo, NewLink},
-        click_repo::ClickRepo,
+        click_repo::{Click, ClickBreakdown, ClickRepo, ClickScope, NewClick},
+    },
+    services::{
+        auth_service::timestamp,
+        bot_detection::BotSignal,
+        click_series::{self, ClickBucket, ClickSeriesParams, ClickSeriesQuery},
+        clock::Clock,
+        user_agent::UserAgentInfo,
+        visitor_service::Visitor,
     },
 };
+use std::collections::HashMap;
 use std::sync::Arc;
 use uuid::Uuid;
 
-#[derive(Debug)]
 pub struct FrogolService {
     frogol_repo: Arc<FrogolRepo>,
     link_repo: Arc<LinkRepo>,
     click_repo: Arc<ClickRepo>,
+    metrics: Arc<Metrics>,
+    clock: Arc<dyn Clock>,
 }
 
 impl FrogolService {
-    pub fn new(frogol_repo: Arc<FrogolRepo>, link_repo: Arc<LinkRepo>) -> Self {
+    pub fn new(frogol_repo: Arc<FrogolRepo>, link_repo: Arc<LinkRepo>, metrics: Arc<Metrics>, clock: Arc<dyn Clock>) -> Self {
         let pool = frogol_repo.get_pool().clone();
         Self {
             frogol_repo,
             link_repo,
             click_repo: Arc::new(ClickRepo::new(pool)),
+            metrics,
+            clock,
         }
     }
 
@@ -35,8 +49,8 @@ impl FrogolService {
         // Sanitize and validate slug
         let sanitized = Self::sanitize_slug(slug)?;
         // Ensure unique slug for better UX (DB also enforces UNIQUE)
-        if let Ok(_) = self.frogol_repo.get_by_slug(&sanitized).await {
-            return Err(AppError::InvalidInput("Slug already exists".to_string()));
+        if self.frogol_repo.get_by_slug(&sanitized).await.is_ok() {
+            return Err(AppError::Conflict("Slug already exists".to_string()));
         }
         let new_frogol = NewFrogol {
             id: Uuid::new_v4().to_string(),
@@ -44,21 +58,76 @@ impl FrogolService {
             slug: sanitized,
             display_name: Some(display_name.to_string()),
         };
-        self.frogol_repo.create_frogol(new_frogol).await
+        let frogol = self.frogol_repo.create_frogol(new_frogol).await?;
+        record_frogol_id(&frogol.id);
+        self.metrics.frogols_created.inc();
+        Ok(frogol)
     }
 
+    /// Loads a public page, failing with `Gone` if its frogol was deleted and
+    /// `NotFound` if the slug was never used.
     pub async fn get_by_slug(&self, slug: &str) -> Result<Frogol, AppError> {
-        self.frogol_repo.get_by_slug(slug).await
+        match self.frogol_repo.get_by_slug(slug).await {
+            Err(AppError::Database(sqlx::Error::RowNotFound)) => {
+                if self.frogol_repo.is_slug_deleted(slug).await? {
+                    Err(AppError::Gone("This page has been deleted".to_string()))
+                } else {
+                    Err(AppError::NotFound("Page not found".to_string()))
+                }
+            }
+            result => {
+                if let Ok(frogol) = &result {
+                    record_frogol_id(&frogol.id);
+                }
+                result
+            }
+        }
+    }
+
+    /// Loads a frogol for `user_id`, failing with `NotFound` if it does not exist
+    /// and `Forbidden` if it belongs to someone else.
+    pub async fn get_owned_frogol(&self, user_id: &str, id: &str) -> Result<Frogol, AppError> {
+        let frogol = self
+            .frogol_repo
+            .get_by_id(id)
+            .await
+            .map_err(|e| e.not_found_if_missing("Frogol not found"))?;
+        Self::ensure_owner(user_id, frogol)
+    }
+
+    /// Same as `get_owned_frogol`, looked up by slug.
+    pub async fn get_owned_frogol_by_slug(&self, user_id: &str, slug: &str) -> Result<Frogol, AppError> {
+        let frogol = self
+            .frogol_repo
+            .get_by_slug(slug)
+            .await
+            .map_err(|e| e.not_found_if_missing("Frogol not found"))?;
+        Self::ensure_owner(user_id, frogol)
     }
 
-    pub async fn get_by_id(&self, id: &str) -> Result<Frogol, AppError> {
-        self.frogol_repo.get_by_id(id).await
+    /// Loads a link and checks that its frogol belongs to `user_id`.
+    pub async fn get_owned_link(&self, user_id: &str, link_id: &str) -> Result<Link, AppError> {
+        let link = self
+            .link_repo
+            .get_link(link_id)
+            .await
+            .map_err(|e| e.not_found_if_missing("Link not found"))?;
+        self.get_owned_frogol(user_id, &link.frogol_id).await?;
+        Ok(link)
     }
 
     pub async fn get_user_frogols(&self, user_id: &str) -> Result<Vec<FrogolSummary>, AppError> {
         self.frogol_repo.get_user_frogols(user_id).await
     }
 
+    /// One page of the user's frogols, newest first, with the total count.
+    pub async fn get_user_frogols_page(&self, user_id: &str, limit: i64, offset: i64) -> Result<(Vec<FrogolSummary>, i64), AppError> {
+        let frogols = self.frogol_repo.list_user_frogols(user_id, limit, offset).await?;
+        let total = self.frogol_repo.count_user_frogols(user_id).await?;
+        Ok((frogols, total))
+    }
+
+    /// Writes every field as given, so `None` clears `avatar_url` or `bio`.
     pub async fn update_frogol(&self, id: &str, display_name: &str, theme: &str, avatar_url: Option<&str>, bio: Option<&str>) -> Result<Frogol, AppError> {
         self.frogol_repo.update_frogol(id, display_name, theme, avatar_url, bio).await
     }
@@ -100,12 +169,21 @@ impl FrogolService {
         self.link_repo.get_links_all(frogol_id).await
     }
 
+    /// One page of the frogol's links in display order, with the total count.
+    pub async fn get_links_page(&self, frogol_id: &str, limit: i64, offset: i64) -> Result<(Vec<Link>, i64), AppError> {
+        let links = self.link_repo.list_links(frogol_id, limit, offset).await?;
+        let total = self.link_repo.count_links(frogol_id).await?;
+        Ok((links, total))
+    }
+
     pub async fn update_link_order(&self, link_ids: &[String]) -> Result<(), AppError> {
         self.link_repo.update_link_order(link_ids).await
     }
 
     pub async fn get_link(&self, link_id: &str) -> Result<Link, AppError> {
-        self.link_repo.get_link(link_id).await
+        let link = self.link_repo.get_link(link_id).await?;
+        record_frogol_id(&link.frogol_id);
+        Ok(link)
     }
 
     pub async fn update_link(&self, link_id: &str, url: &str, label: &str) -> Result<Link, AppError> {
@@ -121,25 +199,117 @@ impl FrogolService {
         self.link_repo.set_link_active(link_id, active).await
     }
 
-    pub async fn track_click(&self, link_id: &str, ip_address: Option<String>, user_agent: Option<String>) -> Result<(), AppError> {
-        self.click_repo.track_click(link_id, ip_address, user_agent).await
+    /// Records a click, flagged as a bot if `bot` says why.
+    pub async fn track_click(
+        &self,
+        link_id: &str,
+        visitor: Visitor,
+        agent: UserAgentInfo,
+        bot: Option<BotSignal>,
+    ) -> Result<(), AppError> {
+        self.click_repo
+            .record_click(NewClick {
+                id: Uuid::new_v4().to_string(),
+                link_id: link_id.to_string(),
+                ip_address: visitor.ip_address,
+                visitor_id: visitor.visitor_id,
+                device_class: Some(agent.device.as_str().to_string()),
+                browser: Some(agent.browser.to_string()),
+                os: Some(agent.os.to_string()),
+                is_bot: bot.is_some(),
+                created_at: timestamp(self.clock.now()),
+            })
+            .await?;
+        self.metrics.link_clicks.inc();
+        if let Some(signal) = bot {
+            self.metrics.bot_clicks.with_label_values(&[signal.as_str()]).inc();
+        }
+        Ok(())
+    }
+
+    pub async fn get_click_stats(&self, frogol_id: &str, include_bots: bool) -> Result<crate::repo::click_repo::ClickStats, AppError> {
+        self.click_repo.get_frogol_click_stats(frogol_id, include_bots).await
     }
 
-    pub async fn get_click_stats(&self, frogol_id: &str) -> Result<crate::repo::click_repo::ClickStats, AppError> {
-        self.click_repo.get_frogol_click_stats(frogol_id).await
+    pub async fn get_user_total_clicks(&self, user_id: &str, include_bots: bool) -> Result<i64, AppError> {
+        self.click_repo.get_user_total_clicks(user_id, include_bots).await
     }
 
-    pub async fn get_user_total_clicks(&self, user_id: &str) -> Result<i64, AppError> {
-        self.click_repo.get_user_total_clicks(user_id).await
+    /// Resolves `params` against the service clock, so default ranges end on its today.
+    pub fn click_series_query(&self, params: &ClickSeriesParams) -> Result<ClickSeriesQuery, AppError> {
+        params.resolve(self.clock.now())
     }
 
-    pub async fn get_clicks_by_link(&self, frogol_id: &str) -> Result<std::collections::HashMap<String, i64>, AppError> {
-        let pairs = self.click_repo.get_clicks_by_link(frogol_id).await?;
+    /// Clicks in `scope` over time, one entry per bucket of `query`.
+    pub async fn get_click_series(&self, scope: ClickScope<'_>, query: &ClickSeriesQuery) -> Result<Vec<ClickBucket>, AppError> {
+        click_series::click_series(&self.click_repo, scope, query).await
+    }
+
+    /// Clicks in `scope` by device, browser and OS, within `range` (RFC 3339
+    /// start and excluded end) if given. Bot clicks are always counted in
+    /// `bot_clicks`, and in the breakdowns only with `include_bots`.
+    pub async fn get_click_breakdown(
+        &self,
+        scope: ClickScope<'_>,
+        range: Option<(&str, &str)>,
+        include_bots: bool,
+    ) -> Result<ClickBreakdown, AppError> {
+        let (start, end) = (range.map(|r| r.0), range.map(|r| r.1));
+        let rows = self.click_repo.click_breakdown(scope, start, end, include_bots).await?;
+
+        let mut breakdown = ClickBreakdown {
+            bot_clicks: self.click_repo.count_bot_clicks(scope, start, end).await?,
+            ..Default::default()
+        };
+        let mut devices = HashMap::new();
+        let mut browsers = HashMap::new();
+        let mut systems = HashMap::new();
+        for row in rows {
+            // Clicks from before classification
+            let name = |value: Option<String>| value.unwrap_or_else(|| "unknown".to_string());
+            *devices.entry(name(row.device_class)).or_insert(0) += row.clicks;
+            *browsers.entry(name(row.browser)).or_insert(0) += row.clicks;
+            *systems.entry(name(row.os)).or_insert(0) += row.clicks;
+        }
+        let ranked = |counts: HashMap<String, i64>| {
+            let mut counts: Vec<(String, i64)> = counts.into_iter().collect();
+            counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            counts
+        };
+        breakdown.devices = ranked(devices);
+        breakdown.browsers = ranked(browsers);
+        breakdown.operating_systems = ranked(systems);
+        Ok(breakdown)
+    }
+
+    pub async fn get_clicks_by_link(&self, frogol_id: &str, include_bots: bool) -> Result<std::collections::HashMap<String, i64>, AppError> {
+        let pairs = self.click_repo.get_clicks_by_link(frogol_id, include_bots).await?;
         Ok(pairs.into_iter().collect())
     }
 
-    pub async fn get_user_analytics(&self, user_id: &str) -> Result<UserAnalytics, AppError> {
-        self.frogol_repo.get_user_analytics(user_id).await
+    /// One page of a link's clicks, newest first, with the total count.
+    pub async fn get_link_clicks(
+        &self,
+        link_id: &str,
+        include_bots: bool,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<Click>, i64), AppError> {
+        let clicks = self.click_repo.list_link_clicks(link_id, include_bots, limit, offset).await?;
+        let total = self.click_repo.count_link_clicks(link_id, include_bots).await?;
+        Ok((clicks, total))
+    }
+
+    pub async fn get_user_analytics(&self, user_id: &str, include_bots: bool) -> Result<UserAnalytics, AppError> {
+        self.frogol_repo.get_user_analytics(user_id, include_bots).await
+    }
+
+    fn ensure_owner(user_id: &str, frogol: Frogol) -> Result<Frogol, AppError> {
+        record_frogol_id(&frogol.id);
+        if frogol.user_id != user_id {
+            return Err(AppError::Forbidden("You do not have access to this frogol".to_string()));
+        }
+        Ok(frogol)
     }
 
     fn normalize_url(url: &str) -> String {
@@ -197,6 +367,7 @@ impl FrogolService {
         // Disallow reserved paths
         const RESERVED: &[&str] = &[
             "login", "logout", "register", "dashboard", "api", "static", "favicon.ico",
+            "forgot-password", "reset-password", "verify-email", "healthz", "readyz", "metrics",
         ];
         if RESERVED.contains(&slug.as_str()) {
             return Err(AppError::InvalidInput("Slug is reserved".to_string()));
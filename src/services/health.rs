@@ -0,0 +1,128 @@
+//! Readiness checks behind `/readyz`: can we reach the database, is its schema
+//! current, and can we store uploads. Also tracks whether the server is
+//! draining for shutdown, in which case it reports not ready so the load
+//! balancer stops sending new traffic.
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+/// Outcome of one readiness check.
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub ok: bool,
+    /// Why the check failed, or what it found.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub draining: bool,
+    pub checks: BTreeMap<&'static str, Check>,
+}
+
+pub struct HealthService {
+    pool: SqlitePool,
+    avatar_dir: PathBuf,
+    draining: AtomicBool,
+}
+
+impl HealthService {
+    pub fn new(pool: SqlitePool, avatar_dir: PathBuf) -> Self {
+        Self {
+            pool,
+            avatar_dir,
+            draining: AtomicBool::new(false),
+        }
+    }
+
+    /// Marks the server as shutting down; `/readyz` fails from now on.
+    pub fn begin_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub async fn readiness(&self) -> Readiness {
+        let draining = self.draining.load(Ordering::SeqCst);
+        let mut checks = BTreeMap::new();
+        checks.insert("database", timed(self.check_database()).await);
+        checks.insert("migrations", timed(self.check_migrations()).await);
+        checks.insert("avatar_dir", timed(self.check_avatar_dir()).await);
+
+        Readiness {
+            ready: !draining && checks.values().all(|c| c.ok),
+            draining,
+            checks,
+        }
+    }
+
+    // `/readyz` is unauthenticated, so failures are logged in full and
+    // answered with a generic detail.
+    async fn check_database(&self) -> Result<Option<String>, String> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map(|_| None)
+            .map_err(|e| {
+                tracing::warn!("Readiness: database unreachable: {}", e);
+                "unreachable".to_string()
+            })
+    }
+
+    /// Every migration built into the binary has been applied successfully.
+    async fn check_migrations(&self) -> Result<Option<String>, String> {
+        let applied: HashSet<i64> =
+            sqlx::query_scalar::<_, i64>("SELECT version FROM _sqlx_migrations WHERE success = 1")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| {
+                    tracing::warn!("Readiness: cannot read applied migrations: {}", e);
+                    "cannot read applied migrations".to_string()
+                })?
+                .into_iter()
+                .collect();
+
+        let migrator = sqlx::migrate!("./migrations");
+        let pending: Vec<String> = migrator
+            .iter()
+            .filter(|m| !applied.contains(&m.version))
+            .map(|m| m.version.to_string())
+            .collect();
+        if pending.is_empty() {
+            Ok(Some(format!("{} applied", applied.len())))
+        } else {
+            Err(format!("pending: {}", pending.join(", ")))
+        }
+    }
+
+    /// Writes and removes a scratch file where avatars are stored.
+    async fn check_avatar_dir(&self) -> Result<Option<String>, String> {
+        let dir = self.avatar_dir.clone();
+        tokio::task::spawn_blocking(move || {
+            tempfile::NamedTempFile::new_in(&dir).map(|_| None).map_err(|e| {
+                tracing::warn!("Readiness: {} is not writable: {}", dir.display(), e);
+                "not writable".to_string()
+            })
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("Readiness: avatar directory check panicked: {}", e);
+            "check failed".to_string()
+        })?
+    }
+}
+
+async fn timed(check: impl std::future::Future<Output = Result<Option<String>, String>>) -> Check {
+    let started = Instant::now();
+    let result = check.await;
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(detail) => Check { ok: true, detail, elapsed_ms },
+        Err(detail) => Check { ok: false, detail: Some(detail), elapsed_ms },
+    }
+}
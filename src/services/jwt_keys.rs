@@ -0,0 +1,193 @@
+//! HMAC keys for session JWTs. Every token names its signing key in the `kid`
+//! header, so the secret can be rotated by adding a new active key and keeping
+//! the old one for verification until the sessions it signed have expired.
+
+use hmac::{Hmac, Mac};
+use jsonwebtoken::{decode, decode_header, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::errors::AppError;
+
+/// Key id given to a lone `JWT_SECRET`, and assumed for tokens issued before
+/// key ids were introduced.
+pub const LEGACY_KEY_ID: &str = "default";
+
+#[derive(Clone)]
+pub struct JwtKeyring {
+    active_kid: String,
+    /// Secrets by key id, including the active one.
+    keys: HashMap<String, String>,
+}
+
+impl JwtKeyring {
+    /// A keyring holding only `secret`, under `LEGACY_KEY_ID`.
+    pub fn single(secret: String) -> Self {
+        Self {
+            active_kid: LEGACY_KEY_ID.to_string(),
+            keys: HashMap::from([(LEGACY_KEY_ID.to_string(), secret)]),
+        }
+    }
+
+    /// Builds a keyring from `(kid, secret)` pairs, signing with `active_kid`.
+    pub fn new(keys: Vec<(String, String)>, active_kid: &str) -> Result<Self, String> {
+        let mut by_kid = HashMap::new();
+        for (kid, secret) in keys {
+            if kid.is_empty() || secret.is_empty() {
+                return Err("key ids and secrets must not be empty".to_string());
+            }
+            if by_kid.insert(kid.clone(), secret).is_some() {
+                return Err(format!("key id {:?} is listed twice", kid));
+            }
+        }
+        if !by_kid.contains_key(active_kid) {
+            return Err(format!("active key id {:?} is not in the keyring", active_kid));
+        }
+
+        Ok(Self {
+            active_kid: active_kid.to_string(),
+            keys: by_kid,
+        })
+    }
+
+    /// Parses a comma-separated list of `kid:secret` entries.
+    pub fn parse(spec: &str, active_kid: &str) -> Result<Self, String> {
+        let keys = spec
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                entry
+                    .split_once(':')
+                    .map(|(kid, secret)| (kid.trim().to_string(), secret.trim().to_string()))
+                    .ok_or_else(|| "entries must look like kid:secret".to_string())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(keys, active_kid)
+    }
+
+    pub fn active_kid(&self) -> &str {
+        &self.active_kid
+    }
+
+    /// A key for `purpose` derived from the active secret, for signing that
+    /// should rotate along with session tokens.
+    pub fn derive_key(&self, purpose: &str) -> Vec<u8> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.keys[&self.active_kid].as_bytes())
+            .expect("HMAC accepts any key length");
+        mac.update(purpose.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    /// Signs `claims` with the active key, naming it in the header.
+    pub fn encode<T: Serialize>(&self, claims: &T) -> Result<String, AppError> {
+        let header = Header {
+            kid: Some(self.active_kid.clone()),
+            ..Header::default()
+        };
+        encode(&header, claims, &EncodingKey::from_secret(self.keys[&self.active_kid].as_ref()))
+            .map_err(|_| AppError::Internal("Failed to generate JWT".to_string()))
+    }
+
+    /// Checks a token against the key its header names. Tokens without a `kid`
+    /// are checked against `LEGACY_KEY_ID`.
+    pub fn decode<T: DeserializeOwned>(&self, token: &str) -> Result<T, AppError> {
+        let invalid = || AppError::Unauthorized("Invalid token".to_string());
+
+        let header = decode_header(token).map_err(|_| invalid())?;
+        let kid = header.kid.as_deref().unwrap_or(LEGACY_KEY_ID);
+        let secret = self.keys.get(kid).ok_or_else(invalid)?;
+
+        decode::<T>(token, &DecodingKey::from_secret(secret.as_ref()), &Validation::default())
+            .map(|data| data.claims)
+            .map_err(|_| invalid())
+    }
+}
+
+/// Lists key ids only, never secrets.
+impl fmt::Debug for JwtKeyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut kids: Vec<_> = self.keys.keys().collect();
+        kids.sort();
+        f.debug_struct("JwtKeyring")
+            .field("active_kid", &self.active_kid)
+            .field("kids", &kids)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct TestClaims {
+        sub: String,
+        exp: u64,
+    }
+
+    fn claims() -> TestClaims {
+        TestClaims {
+            sub: "user-1".to_string(),
+            exp: (chrono::Utc::now().timestamp() + 3600) as u64,
+        }
+    }
+
+    #[test]
+    fn tokens_name_their_key() {
+        let keyring = JwtKeyring::parse("old:first-secret, new:second-secret", "new").unwrap();
+        let token = keyring.encode(&claims()).unwrap();
+        assert_eq!(decode_header(&token).unwrap().kid.as_deref(), Some("new"));
+        assert_eq!(keyring.decode::<TestClaims>(&token).unwrap(), claims());
+    }
+
+    #[test]
+    fn retired_keys_still_verify() {
+        let before = JwtKeyring::parse("old:first-secret", "old").unwrap();
+        let token = before.encode(&claims()).unwrap();
+
+        let rotated = JwtKeyring::parse("old:first-secret,new:second-secret", "new").unwrap();
+        assert!(rotated.decode::<TestClaims>(&token).is_ok());
+    }
+
+    #[test]
+    fn removed_keys_are_rejected() {
+        let before = JwtKeyring::parse("old:first-secret", "old").unwrap();
+        let token = before.encode(&claims()).unwrap();
+
+        let after = JwtKeyring::parse("new:second-secret", "new").unwrap();
+        assert!(after.decode::<TestClaims>(&token).is_err());
+
+        // A key id reused with a different secret does not verify either
+        let reused = JwtKeyring::parse("old:other-secret", "old").unwrap();
+        assert!(reused.decode::<TestClaims>(&token).is_err());
+    }
+
+    #[test]
+    fn tokens_without_kid_use_the_legacy_key() {
+        let token = encode(&Header::default(), &claims(), &EncodingKey::from_secret(b"legacy")).unwrap();
+
+        assert!(JwtKeyring::single("legacy".to_string()).decode::<TestClaims>(&token).is_ok());
+        let rotated = JwtKeyring::parse("default:legacy,new:second-secret", "new").unwrap();
+        assert!(rotated.decode::<TestClaims>(&token).is_ok());
+        let removed = JwtKeyring::parse("new:second-secret", "new").unwrap();
+        assert!(removed.decode::<TestClaims>(&token).is_err());
+    }
+
+    #[test]
+    fn parse_rejects_bad_specs() {
+        assert!(JwtKeyring::parse("a:one,b:two", "c").is_err());
+        assert!(JwtKeyring::parse("a:one,a:two", "a").is_err());
+        assert!(JwtKeyring::parse("a", "a").is_err());
+        assert!(JwtKeyring::parse("a:", "a").is_err());
+    }
+
+    #[test]
+    fn debug_output_hides_secrets() {
+        let keyring = JwtKeyring::parse("a:top-secret", "a").unwrap();
+        assert!(!format!("{:?}", keyring).contains("top-secret"));
+    }
+}
@@ -1,16 +1,18 @@
 use crate::repo::lead_repo::{LeadRepo, NewLead, Lead, LeadSummary};
 use crate::errors::AppError;
+use crate::metrics::Metrics;
+use crate::middleware::request_id::record_frogol_id;
 use std::sync::Arc;
 use uuid::Uuid;
 
-#[derive(Debug)]
 pub struct LeadService {
     repo: Arc<LeadRepo>,
+    metrics: Arc<Metrics>,
 }
 
 impl LeadService {
-    pub fn new(repo: Arc<LeadRepo>) -> Self {
-        Self { repo }
+    pub fn new(repo: Arc<LeadRepo>, metrics: Arc<Metrics>) -> Self {
+        Self { repo, metrics }
     }
 
     pub async fn capture_lead(
@@ -19,10 +21,12 @@ impl LeadService {
         email: &str,
         source: Option<&str>,
         message: Option<&str>,
+        ip_address: Option<String>,
     ) -> Result<Lead, AppError> {
         if !email.contains('@') {
             return Err(AppError::InvalidInput("Invalid email format".to_string()));
         }
+        record_frogol_id(frogol_id);
         let score = self.calculate_lead_score(source);
         let new_lead = NewLead {
             id: Uuid::new_v4().to_string(),
@@ -31,14 +35,28 @@ impl LeadService {
             source: source.map(|s| s.to_string()),
             score: Some(score),
             message: message.map(|m| m.to_string()),
+            ip_address,
         };
-        self.repo.create_lead(new_lead).await
+        let lead = self.repo.create_lead(new_lead).await?;
+        self.metrics.leads_captured.inc();
+        Ok(lead)
     }
 
     pub async fn get_frogol_leads(&self, frogol_id: &str) -> Result<Vec<LeadSummary>, AppError> {
         self.repo.get_frogol_leads(frogol_id).await
     }
 
+    /// One page of a frogol's leads, newest first, with the total count.
+    pub async fn get_frogol_leads_page(&self, frogol_id: &str, limit: i64, offset: i64) -> Result<(Vec<Lead>, i64), AppError> {
+        let leads = self.repo.list_frogol_leads(frogol_id, limit, offset).await?;
+        let total = self.repo.count_frogol_leads(frogol_id).await?;
+        Ok((leads, total))
+    }
+
+    pub async fn count_frogol_leads(&self, frogol_id: &str) -> Result<i64, AppError> {
+        self.repo.count_frogol_leads(frogol_id).await
+    }
+
     pub async fn get_user_total_leads(&self, user_id: &str) -> Result<i64, AppError> {
         self.repo.get_user_total_leads(user_id).await
     }
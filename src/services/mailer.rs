@@ -0,0 +1,52 @@
+use axum::async_trait;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::errors::AppError;
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Outbound email transport used by the auth flows.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), AppError>;
+}
+
+/// Local transport that writes each message to an outbox directory as a
+/// `.eml` file and logs it, so mail can be read during development and tests.
+pub struct FileMailer {
+    outbox_dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(outbox_dir: PathBuf) -> Self {
+        Self { outbox_dir }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        tokio::fs::create_dir_all(&self.outbox_dir).await.map_err(|e| {
+            tracing::error!("Failed to create outbox directory {:?}: {}", self.outbox_dir, e);
+            AppError::Internal("Failed to send email".to_string())
+        })?;
+
+        let filename = format!("{}-{}.eml", chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f"), Uuid::new_v4());
+        let path = self.outbox_dir.join(filename);
+        let contents = format!("To: {}\nSubject: {}\n\n{}\n", email.to, email.subject, email.body);
+
+        tokio::fs::write(&path, contents).await.map_err(|e| {
+            tracing::error!("Failed to write email to {:?}: {}", path, e);
+            AppError::Internal("Failed to send email".to_string())
+        })?;
+
+        tracing::info!(to = %email.to, subject = %email.subject, path = %path.display(), "Email written to outbox");
+        Ok(())
+    }
+}
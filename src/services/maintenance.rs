@@ -0,0 +1,167 @@
+//! Periodic cleanup of rows and files that are no longer needed: expired
+//! sessions and tokens, old sign-in attempts, past days' visitor salts and
+//! unreferenced avatar uploads.
+
+use chrono::Duration;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use crate::errors::AppError;
+use crate::repo::{login_attempt_repo::LoginAttemptRepo, user_repo::UserRepo};
+use crate::services::{
+    auth_service::{timestamp, RECENT_FAILURES_DAYS},
+    avatar_service::AvatarService,
+    clock::Clock,
+    visitor_service::VisitorService,
+};
+
+/// How often each sweep runs.
+#[derive(Debug, Clone)]
+pub struct MaintenanceConfig {
+    /// Expired sessions, tokens and sign-in challenges, old sign-in attempts
+    /// and past days' visitor salts.
+    pub purge_interval: std::time::Duration,
+    /// Avatar files no frogol references.
+    pub avatar_sweep_interval: std::time::Duration,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            purge_interval: std::time::Duration::from_secs(60 * 60),
+            avatar_sweep_interval: std::time::Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+/// Avatar files younger than this are never swept, so an upload whose row has
+/// not been written yet is safe.
+const AVATAR_GRACE_PERIOD: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Rows removed by one purge.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PurgeReport {
+    pub sessions: u64,
+    pub password_reset_tokens: u64,
+    pub email_verification_tokens: u64,
+    pub login_challenges: u64,
+    pub login_attempts: u64,
+    pub visitor_salts: u64,
+}
+
+pub struct MaintenanceService {
+    user_repo: UserRepo,
+    login_attempts: LoginAttemptRepo,
+    avatar: Arc<AvatarService>,
+    visitors: Arc<VisitorService>,
+    clock: Arc<dyn Clock>,
+}
+
+impl MaintenanceService {
+    pub fn new(
+        user_repo: UserRepo,
+        login_attempts: LoginAttemptRepo,
+        avatar: Arc<AvatarService>,
+        visitors: Arc<VisitorService>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            user_repo,
+            login_attempts,
+            avatar,
+            visitors,
+            clock,
+        }
+    }
+
+    /// Deletes everything that has expired as of now.
+    pub async fn purge_expired(&self) -> Result<PurgeReport, AppError> {
+        let now = self.clock.now();
+        let now_str = now.to_rfc3339();
+        let attempts_before = timestamp(now - Duration::days(RECENT_FAILURES_DAYS));
+
+        Ok(PurgeReport {
+            sessions: self.user_repo.delete_expired_sessions(&now_str).await?,
+            password_reset_tokens: self.user_repo.delete_expired_password_reset_tokens(&now_str).await?,
+            email_verification_tokens: self.user_repo.delete_expired_email_verification_tokens(&now_str).await?,
+            login_challenges: self.user_repo.delete_expired_login_challenges(&now_str).await?,
+            login_attempts: self.login_attempts.delete_before(&attempts_before).await?,
+            visitor_salts: self.visitors.delete_old_salts().await?,
+        })
+    }
+
+    /// Deletes avatar files that no frogol references, returning how many went.
+    pub async fn purge_orphaned_avatars(&self) -> Result<u64, AppError> {
+        let cutoff = SystemTime::now()
+            .checked_sub(AVATAR_GRACE_PERIOD)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        self.avatar.delete_orphaned_files(cutoff).await
+    }
+
+    /// Runs both sweeps on their intervals until `shutdown` flips to `true` or
+    /// its sender is dropped. A sweep already under way is allowed to finish.
+    pub fn spawn(
+        self: Arc<Self>,
+        config: MaintenanceConfig,
+        mut shutdown: watch::Receiver<bool>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut purge = tokio::time::interval(config.purge_interval);
+            purge.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let mut avatar_sweep = tokio::time::interval(config.avatar_sweep_interval);
+            avatar_sweep.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            tracing::info!(
+                purge_interval_secs = config.purge_interval.as_secs(),
+                avatar_sweep_interval_secs = config.avatar_sweep_interval.as_secs(),
+                "Maintenance task started"
+            );
+
+            loop {
+                tokio::select! {
+                    _ = purge.tick() => self.run_purge().await,
+                    _ = avatar_sweep.tick() => self.run_avatar_sweep().await,
+                    changed = shutdown.changed() => {
+                        if changed.is_err() || *shutdown.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            tracing::info!("Maintenance task stopped");
+        })
+    }
+
+    async fn run_purge(&self) {
+        let started = std::time::Instant::now();
+        match self.purge_expired().await {
+            Ok(report) => tracing::info!(
+                sessions = report.sessions,
+                password_reset_tokens = report.password_reset_tokens,
+                email_verification_tokens = report.email_verification_tokens,
+                login_challenges = report.login_challenges,
+                login_attempts = report.login_attempts,
+                visitor_salts = report.visitor_salts,
+                elapsed_ms = started.elapsed().as_millis() as u64,
+                "Purged expired rows"
+            ),
+            Err(e) => tracing::error!("Purging expired rows failed: {}", e),
+        }
+    }
+
+    async fn run_avatar_sweep(&self) {
+        let started = std::time::Instant::now();
+        match self.purge_orphaned_avatars().await {
+            Ok(files) => tracing::info!(
+                avatar_files = files,
+                elapsed_ms = started.elapsed().as_millis() as u64,
+                "Swept orphaned avatar files"
+            ),
+            Err(e) => tracing::error!("Sweeping orphaned avatar files failed: {}", e),
+        }
+    }
+}
@@ -2,3 +2,15 @@ pub mod frogol_service;
 pub mod lead_service;
 pub mod auth_service;
 pub mod avatar_service;
+pub mod mailer;
+pub mod token;
+pub mod clock;
+pub mod totp;
+pub mod maintenance;
+pub mod jwt_keys;
+pub mod api_token_service;
+pub mod health;
+pub mod visitor_service;
+pub mod click_series;
+pub mod user_agent;
+pub mod bot_detection;
@@ -0,0 +1,147 @@
+//! RFC 6238 time-based one-time passwords (HMAC-SHA1, 30 second steps), the
+//! variant every common authenticator app supports.
+
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::Sha1;
+
+/// Seconds per time step.
+pub const STEP_SECS: i64 = 30;
+/// Digits in the codes shown by authenticator apps.
+pub const DIGITS: u32 = 6;
+/// Steps either side of the current one that are still accepted, for clock drift.
+const ALLOWED_DRIFT_STEPS: i64 = 1;
+
+/// 160-bit shared secret, the size RFC 4226 recommends for HMAC-SHA1.
+pub fn generate_secret() -> Vec<u8> {
+    let mut secret = vec![0u8; 20];
+    rand::thread_rng().fill_bytes(&mut secret);
+    secret
+}
+
+pub fn encode_secret(secret: &[u8]) -> String {
+    BASE32_NOPAD.encode(secret)
+}
+
+pub fn decode_secret(encoded: &str) -> Option<Vec<u8>> {
+    BASE32_NOPAD.decode(encoded.as_bytes()).ok()
+}
+
+/// `otpauth://` URI that authenticator apps import, usually via a QR code.
+pub fn otpauth_uri(issuer: &str, account: &str, encoded_secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        urlencoding::encode(issuer),
+        urlencoding::encode(account),
+        encoded_secret,
+        urlencoding::encode(issuer),
+        DIGITS,
+        STEP_SECS
+    )
+}
+
+/// RFC 4226 HOTP value for `counter`, truncated to `digits`.
+fn hotp(secret: &[u8], counter: u64, digits: u32) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    binary % 10u32.pow(digits)
+}
+
+/// Time step containing `unix_time`.
+pub fn step_at(unix_time: i64) -> i64 {
+    unix_time.div_euclid(STEP_SECS)
+}
+
+/// Zero-padded code for the step containing `unix_time`.
+pub fn code_at(secret: &[u8], unix_time: i64, digits: u32) -> String {
+    let code = hotp(secret, step_at(unix_time) as u64, digits);
+    format!("{:0width$}", code, width = digits as usize)
+}
+
+/// Checks a user-entered code against the current step and its neighbours.
+/// Returns the matching step so callers can refuse to accept it twice.
+pub fn verify(secret: &[u8], code: &str, unix_time: i64) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let current = step_at(unix_time);
+    (current - ALLOWED_DRIFT_STEPS..=current + ALLOWED_DRIFT_STEPS)
+        .filter(|step| *step >= 0)
+        .find(|step| code_at(secret, step * STEP_SECS, DIGITS) == code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Shared secret used by the SHA-1 vectors in RFC 6238 Appendix B.
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn rfc6238_sha1_test_vectors() {
+        let vectors = [
+            (59, "94287082"),
+            (1111111109, "07081804"),
+            (1111111111, "14050471"),
+            (1234567890, "89005924"),
+            (2000000000, "69279037"),
+            (20000000000, "65353130"),
+        ];
+        for (time, expected) in vectors {
+            assert_eq!(code_at(RFC_SECRET, time, 8), expected, "T = {}", time);
+        }
+    }
+
+    #[test]
+    fn six_digit_codes_are_the_low_digits() {
+        assert_eq!(code_at(RFC_SECRET, 59, 6), "287082");
+        assert_eq!(code_at(RFC_SECRET, 1111111109, 6), "081804");
+    }
+
+    #[test]
+    fn verify_accepts_adjacent_steps_only() {
+        let now = 1234567890;
+        let code = code_at(RFC_SECRET, now, DIGITS);
+        assert_eq!(verify(RFC_SECRET, &code, now), Some(step_at(now)));
+        assert_eq!(verify(RFC_SECRET, &code, now + STEP_SECS), Some(step_at(now)));
+        assert_eq!(verify(RFC_SECRET, &code, now - STEP_SECS), Some(step_at(now)));
+        assert_eq!(verify(RFC_SECRET, &code, now + 2 * STEP_SECS), None);
+    }
+
+    #[test]
+    fn verify_rejects_malformed_codes() {
+        let now = 1234567890;
+        assert_eq!(verify(RFC_SECRET, "12345", now), None);
+        assert_eq!(verify(RFC_SECRET, "abcdef", now), None);
+        assert_eq!(verify(RFC_SECRET, "", now), None);
+    }
+
+    #[test]
+    fn secrets_round_trip_through_base32() {
+        let secret = generate_secret();
+        let encoded = encode_secret(&secret);
+        assert_eq!(decode_secret(&encoded), Some(secret));
+        assert_eq!(encode_secret(RFC_SECRET), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+    }
+
+    #[test]
+    fn otpauth_uri_escapes_labels() {
+        let uri = otpauth_uri("Frogolio", "a+b@example.com", "ABC");
+        assert_eq!(
+            uri,
+            "otpauth://totp/Frogolio:a%2Bb%40example.com?secret=ABC&issuer=Frogolio&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
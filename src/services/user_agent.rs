@@ -0,0 +1,107 @@
+//! Rough classification of `User-Agent` strings into device class, browser
+//! family and OS family, for click breakdowns.
+//!
+//! This matches well-known tokens rather than parsing every agent there is:
+//! it is right for the browsers people use and says `Other` for the rest.
+//! Agents naming a crawler, link previewer, HTTP library or headless browser
+//! are bots.
+
+/// Substrings, lowercase, that only automated agents send.
+const BOT_SIGNATURES: &[&str] = &[
+    "bot", "crawl", "spider", "slurp", "archiver", "facebookexternalhit", "embedly", "preview", "whatsapp",
+    "headless", "phantomjs", "lighthouse", "pingdom", "uptime", "monitor", "curl/", "wget/", "httpie",
+    "python-requests", "python-urllib", "aiohttp", "go-http-client", "java/", "okhttp", "axios/", "node-fetch",
+    "undici", "libwww", "httpclient", "scrapy",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceClass {
+    Mobile,
+    Tablet,
+    Desktop,
+    Bot,
+    /// No user agent was sent.
+    Unknown,
+}
+
+impl DeviceClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeviceClass::Mobile => "mobile",
+            DeviceClass::Tablet => "tablet",
+            DeviceClass::Desktop => "desktop",
+            DeviceClass::Bot => "bot",
+            DeviceClass::Unknown => "unknown",
+        }
+    }
+}
+
+/// What a user agent says about the visitor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserAgentInfo {
+    pub device: DeviceClass,
+    pub browser: &'static str,
+    pub os: &'static str,
+}
+
+impl UserAgentInfo {
+    pub fn is_bot(&self) -> bool {
+        self.device == DeviceClass::Bot
+    }
+}
+
+pub fn classify(user_agent: Option<&str>) -> UserAgentInfo {
+    let Some(ua) = user_agent.map(str::trim).filter(|ua| !ua.is_empty()) else {
+        return UserAgentInfo { device: DeviceClass::Unknown, browser: "Other", os: "Other" };
+    };
+    let lower = ua.to_ascii_lowercase();
+    let has = |token: &str| ua.contains(token);
+
+    let os = if has("Windows") {
+        "Windows"
+    } else if has("iPhone") || has("iPad") || has("iPod") {
+        // Checked before macOS: iOS agents say "like Mac OS X"
+        "iOS"
+    } else if has("Android") {
+        "Android"
+    } else if has("CrOS") {
+        "Chrome OS"
+    } else if has("Macintosh") || has("Mac OS X") {
+        "macOS"
+    } else if has("Linux") {
+        "Linux"
+    } else {
+        "Other"
+    };
+
+    // Most agents name several engines, so the more specific ones go first
+    let browser = if has("Edg/") || has("EdgA/") || has("EdgiOS/") || has("Edge/") {
+        "Edge"
+    } else if has("OPR/") || has("Opera") {
+        "Opera"
+    } else if has("SamsungBrowser/") {
+        "Samsung Internet"
+    } else if has("Firefox/") || has("FxiOS/") {
+        "Firefox"
+    } else if has("Chrome/") || has("CriOS/") || has("Chromium/") {
+        "Chrome"
+    } else if has("Safari/") {
+        "Safari"
+    } else if has("MSIE ") || has("Trident/") {
+        "Internet Explorer"
+    } else {
+        "Other"
+    };
+
+    let device = if BOT_SIGNATURES.iter().any(|sig| lower.contains(sig)) {
+        DeviceClass::Bot
+    } else if has("iPad") || has("Tablet") || (has("Android") && !has("Mobile")) {
+        DeviceClass::Tablet
+    } else if has("Mobi") || has("iPhone") || has("iPod") {
+        DeviceClass::Mobile
+    } else {
+        DeviceClass::Desktop
+    };
+
+    UserAgentInfo { device, browser, os }
+}
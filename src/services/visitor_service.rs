@@ -0,0 +1,96 @@
+//! What we keep about the visitors who click links and leave their email.
+//!
+//! Addresses are cut to their /24 (IPv4) or /48 (IPv6) network before they
+//! are stored. Clicks also carry a visitor id: a keyed hash of the full
+//! address and user agent under a salt that changes every UTC day, so unique
+//! visitors can be counted per day. Old salts are deleted by maintenance, after
+//! which nobody can tell which address an id came from.
+//!
+//! Visitors who send `DNT: 1` or `Sec-GPC: 1` get neither.
+
+use chrono::NaiveDate;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::{Arc, Mutex};
+
+use crate::errors::AppError;
+use crate::repo::visitor_salt_repo::VisitorSaltRepo;
+use crate::services::{clock::Clock, token};
+
+/// What may be stored about one visitor.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Visitor {
+    /// The visitor's network, not their address.
+    pub ip_address: Option<String>,
+    /// Same for the same address and user agent until the day is over.
+    pub visitor_id: Option<String>,
+}
+
+/// The network an address belongs to: its /24 for IPv4, its /48 for IPv6.
+pub fn truncate_ip(ip: IpAddr) -> IpAddr {
+    match ip.to_canonical() {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            IpAddr::V4(Ipv4Addr::new(a, b, c, 0))
+        }
+        IpAddr::V6(v6) => {
+            let s = v6.segments();
+            IpAddr::V6(Ipv6Addr::new(s[0], s[1], s[2], 0, 0, 0, 0, 0))
+        }
+    }
+}
+
+pub struct VisitorService {
+    salts: VisitorSaltRepo,
+    clock: Arc<dyn Clock>,
+    /// The current day's salt, so most clicks skip the database round trip.
+    current: Mutex<Option<(NaiveDate, String)>>,
+}
+
+impl VisitorService {
+    pub fn new(salts: VisitorSaltRepo, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            salts,
+            clock,
+            current: Mutex::new(None),
+        }
+    }
+
+    /// What to store about the visitor at `ip` using `user_agent`. Nothing is
+    /// kept when they have `opted_out` of tracking, and there is no visitor id
+    /// without an address to tell visitors apart.
+    pub async fn identify(&self, ip: Option<IpAddr>, user_agent: Option<&str>, opted_out: bool) -> Result<Visitor, AppError> {
+        let Some(ip) = ip.filter(|_| !opted_out) else {
+            return Ok(Visitor::default());
+        };
+        let salt = self.salt_for(self.clock.now().date_naive()).await?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(salt.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(ip.to_canonical().to_string().as_bytes());
+        mac.update(b"\n");
+        mac.update(user_agent.unwrap_or_default().as_bytes());
+        let digest = mac.finalize().into_bytes();
+
+        Ok(Visitor {
+            ip_address: Some(truncate_ip(ip).to_string()),
+            visitor_id: Some(hex::encode(&digest[..16])),
+        })
+    }
+
+    /// Deletes the salts of days before today, returning how many went.
+    pub async fn delete_old_salts(&self) -> Result<u64, AppError> {
+        let today = self.clock.now().date_naive();
+        self.salts.delete_before(&today.to_string()).await
+    }
+
+    async fn salt_for(&self, day: NaiveDate) -> Result<String, AppError> {
+        if let Some((cached_day, salt)) = self.current.lock().unwrap().as_ref() {
+            if *cached_day == day {
+                return Ok(salt.clone());
+            }
+        }
+        let salt = self.salts.get_or_create(&day.to_string(), &token::generate()).await?;
+        *self.current.lock().unwrap() = Some((day, salt.clone()));
+        Ok(salt)
+    }
+}
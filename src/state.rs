@@ -1,8 +1,15 @@
 use crate::{
+    config::Config,
+    metrics::Metrics,
     repo::{
-        frogol_repo::FrogolRepo, lead_repo::LeadRepo, link_repo::LinkRepo, user_repo::UserRepo,
+        api_token_repo::ApiTokenRepo, click_repo::ClickRepo, frogol_repo::FrogolRepo, lead_repo::LeadRepo, link_repo::LinkRepo,
+        login_attempt_repo::LoginAttemptRepo, user_repo::UserRepo, visitor_salt_repo::VisitorSaltRepo,
+    },
+    services::{
+        api_token_service::ApiTokenService, frogol_service::FrogolService, lead_service::LeadService, auth_service::AuthService,
+        avatar_service::AvatarService, clock::Clock, health::HealthService, mailer::Mailer, maintenance::MaintenanceService,
+        visitor_service::VisitorService, bot_detection::BotDetector,
     },
-    services::{frogol_service::FrogolService, lead_service::LeadService, auth_service::AuthService},
 };
 use sqlx::SqlitePool;
 use std::sync::Arc;
@@ -12,33 +19,76 @@ pub struct Services {
     pub frogol: Arc<FrogolService>,
     pub lead: Arc<LeadService>,
     pub auth: Arc<AuthService>,
-
+    pub avatar: Arc<AvatarService>,
+    pub maintenance: Arc<MaintenanceService>,
+    pub api_tokens: Arc<ApiTokenService>,
+    pub health: Arc<HealthService>,
+    pub visitors: Arc<VisitorService>,
+    pub bots: Arc<BotDetector>,
 }
 
 #[derive(Clone)]
 pub struct AppState {
     pub services: Arc<Services>,
+    pub config: Arc<Config>,
+    pub metrics: Arc<Metrics>,
 }
 
 impl AppState {
-    pub fn new(pool: SqlitePool, jwt_secret: String) -> Self {
+    pub fn new(
+        pool: SqlitePool,
+        config: Config,
+        mailer: Arc<dyn Mailer>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        let metrics = Arc::new(Metrics::new(pool.clone()));
+
         // Initialize repositories
         let frogol_repo = Arc::new(FrogolRepo::new(pool.clone()));
         let lead_repo = Arc::new(LeadRepo::new(pool.clone()));
         let link_repo = Arc::new(LinkRepo::new(pool.clone()));
         let user_repo = UserRepo::new(pool.clone());
-
-
+        let login_attempt_repo = LoginAttemptRepo::new(pool.clone());
+        let avatar = Arc::new(AvatarService::new(pool.clone(), config.avatars.clone(), metrics.clone()));
+        let visitors = Arc::new(VisitorService::new(VisitorSaltRepo::new(pool.clone()), clock.clone()));
+        let maintenance = Arc::new(MaintenanceService::new(
+            UserRepo::new(pool.clone()),
+            LoginAttemptRepo::new(pool.clone()),
+            avatar.clone(),
+            visitors.clone(),
+            clock.clone(),
+        ));
+        let api_tokens = Arc::new(ApiTokenService::new(
+            ApiTokenRepo::new(pool.clone()),
+            UserRepo::new(pool.clone()),
+            clock.clone(),
+        ));
+        let health = Arc::new(HealthService::new(pool.clone(), config.avatars.dir.clone()));
+        let bots = Arc::new(BotDetector::new(ClickRepo::new(pool.clone()), clock.clone()));
 
         // Initialize services
         let services = Arc::new(Services {
-            frogol: Arc::new(FrogolService::new(frogol_repo, link_repo)),
-            lead: Arc::new(LeadService::new(lead_repo)),
-            auth: Arc::new(AuthService::new(user_repo, jwt_secret)),
+            frogol: Arc::new(FrogolService::new(frogol_repo, link_repo, metrics.clone(), clock.clone())),
+            lead: Arc::new(LeadService::new(lead_repo, metrics.clone())),
+            auth: Arc::new(AuthService::new(
+                user_repo,
+                login_attempt_repo,
+                config.auth.clone(),
+                mailer,
+                clock,
+            )),
+            avatar,
+            maintenance,
+            api_tokens,
+            health,
+            visitors,
+            bots,
         });
 
         Self {
             services,
+            config: Arc::new(config),
+            metrics,
         }
     }
 }
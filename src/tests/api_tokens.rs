@@ -0,0 +1,121 @@
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+};
+
+use super::{body_text, request, send, sign_in, test_app, TestApp};
+use crate::services::{
+    api_token_service::{ApiScope, TOKEN_PREFIX},
+    auth_service::timestamp,
+    clock::Clock,
+};
+
+/// A request authenticated only by `Authorization`: no cookies, no CSRF header.
+fn bearer(method: Method, uri: &str, authorization: &str, form: &str) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, authorization)
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(form.to_string()))
+        .expect("valid request")
+}
+
+/// Signs up a user with one frogol and link, returning `(session_token, link_id)`.
+async fn owner_with_link(app: &TestApp, email: &str, slug: &str) -> (String, String) {
+    let (user_id, token) = sign_in(&app.state, email).await;
+    let frogol = app.state.services.frogol.create_frogol(&user_id, slug, "Frog").await.unwrap();
+    let link = app.state.services.frogol.add_link(&frogol.id, "https://example.com", "Site").await.unwrap();
+    (token, link.id)
+}
+
+#[tokio::test]
+async fn token_created_in_settings_can_manage_links() {
+    let app = test_app().await;
+    let (session, link_id) = owner_with_link(&app, "a@example.com", "alice").await;
+
+    let res = send(
+        &app.state,
+        request(Method::POST, "/dashboard/settings/api-tokens", Some(&session), "name=Sync+script&scope=links%3Awrite"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let page = body_text(res).await;
+    let start = page.find(TOKEN_PREFIX).expect("new token is shown");
+    let secret: String = page[start..].chars().take_while(|c| c.is_ascii_alphanumeric() || *c == '_').collect();
+
+    let res = send(
+        &app.state,
+        bearer(Method::PUT, &format!("/api/links/{}", link_id), &format!("Bearer {}", secret), "label=Renamed"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(app.state.services.frogol.get_link(&link_id).await.unwrap().label, "Renamed");
+    // Stamped like every other time, so they compare as strings
+    let last_used_at: String = sqlx::query_scalar("SELECT last_used_at FROM api_tokens").fetch_one(&app.pool).await.unwrap();
+    assert_eq!(last_used_at, timestamp(app.clock.now()));
+
+    // Shown as used, and the secret is not displayed again
+    let res = send(&app.state, request(Method::GET, "/dashboard/settings", Some(&session), "")).await;
+    let page = body_text(res).await;
+    assert!(page.contains("Sync script"));
+    assert!(page.contains("Last used"));
+    assert!(!page.contains(&secret));
+}
+
+#[tokio::test]
+async fn scopes_and_ownership_are_enforced() {
+    let app = test_app().await;
+    let (session, link_id) = owner_with_link(&app, "a@example.com", "alice").await;
+    let (_, other_link) = owner_with_link(&app, "b@example.com", "bob").await;
+    let owner = app.state.services.auth.validate_token(&session).await.unwrap();
+    let analytics_only = app.state.services.api_tokens.create(&owner, "Stats", &[ApiScope::AnalyticsRead]).await.unwrap();
+    let links = app.state.services.api_tokens.create(&owner, "Links", &[ApiScope::LinksWrite]).await.unwrap();
+
+    let res = send(
+        &app.state,
+        bearer(Method::DELETE, &format!("/api/links/{}", link_id), &format!("Bearer {}", analytics_only.secret), ""),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let res = send(
+        &app.state,
+        bearer(Method::DELETE, &format!("/api/links/{}", other_link), &format!("Bearer {}", links.secret), ""),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert!(app.state.services.frogol.get_link(&other_link).await.is_ok());
+
+    let res = send(
+        &app.state,
+        bearer(Method::DELETE, &format!("/api/links/{}", link_id), &format!("bearer {}", links.secret), ""),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn revoked_and_malformed_tokens_are_rejected() {
+    let app = test_app().await;
+    let (session, link_id) = owner_with_link(&app, "a@example.com", "alice").await;
+    let owner = app.state.services.auth.validate_token(&session).await.unwrap();
+    let created = app.state.services.api_tokens.create(&owner, "Temp", &[ApiScope::LinksWrite]).await.unwrap();
+
+    let res = send(
+        &app.state,
+        request(Method::POST, &format!("/dashboard/settings/api-tokens/{}/revoke", created.token.id), Some(&session), ""),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+
+    let uri = format!("/api/links/{}", link_id);
+    for authorization in [format!("Bearer {}", created.secret), "Bearer frg_not-a-real-token".to_string()] {
+        let res = send(&app.state, bearer(Method::PUT, &uri, &authorization, "label=x")).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{}", authorization);
+    }
+
+    // Other schemes are neither accepted nor exempt from CSRF checks
+    let res = send(&app.state, bearer(Method::PUT, &uri, &format!("Basic {}", created.secret), "label=x")).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
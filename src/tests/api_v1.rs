@@ -0,0 +1,324 @@
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+};
+use serde_json::{json, Value};
+
+use super::{
+    bearer_json, get_json, json_body, owner_with_links, send, session_json, sign_in, test_app, token_with, TestApp, FIREFOX,
+};
+use crate::services::{api_token_service::ApiScope, bot_detection::BotSignal, user_agent, visitor_service::Visitor};
+
+/// Signs in a user whose email is verified, so they may publish frogols.
+async fn verified_user(app: &TestApp, email: &str) -> (String, String) {
+    let (user_id, token) = sign_in(&app.state, email).await;
+    app.state.services.auth.verify_email(&app.verification_token()).await.unwrap();
+    (user_id, token)
+}
+
+#[tokio::test]
+async fn frogol_lifecycle() {
+    let app = test_app().await;
+    let (_, session) = verified_user(&app, "a@example.com").await;
+
+    let res = send(
+        &app.state,
+        session_json(Method::POST, "/api/v1/frogols", &session, Some(json!({"slug": "alice", "display_name": "Alice"}))),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let frogol = json_body(res).await;
+    assert_eq!(frogol["slug"], "alice");
+    assert_eq!(frogol["theme"], "default");
+    assert!(frogol.get("user_id").is_none());
+    let id = frogol["id"].as_str().unwrap().to_string();
+
+    let res = send(
+        &app.state,
+        session_json(Method::PATCH, &format!("/api/v1/frogols/{}", id), &session, Some(json!({"bio": "Hello"}))),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let frogol = json_body(res).await;
+    assert_eq!(frogol["bio"], "Hello");
+    assert_eq!(frogol["display_name"], "Alice");
+
+    // Leaving a field out keeps it, sending null clears it
+    for (body, bio) in [(json!({"display_name": "Al"}), json!("Hello")), (json!({"bio": null}), Value::Null)] {
+        let res = send(&app.state, session_json(Method::PATCH, &format!("/api/v1/frogols/{}", id), &session, Some(body))).await;
+        assert_eq!(json_body(res).await["bio"], bio);
+    }
+
+    let res = send(&app.state, session_json(Method::GET, "/api/v1/frogols", &session, None)).await;
+    let list = json_body(res).await;
+    assert_eq!(list["pagination"], json!({"page": 1, "per_page": 20, "total": 1}));
+    assert_eq!(list["data"][0]["id"], id.as_str());
+
+    // Frogols with links, clicks and leads delete cleanly
+    let link = app.state.services.frogol.add_link(&id, "example.com", "Site").await.unwrap();
+    app.state.services.frogol.track_click(&link.id, Visitor::default(), user_agent::classify(None), None).await.unwrap();
+    app.state.services.lead.capture_lead(&id, "fan@example.com", None, None, None).await.unwrap();
+    let res = send(&app.state, session_json(Method::DELETE, &format!("/api/v1/frogols/{}", id), &session, None)).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let res = send(&app.state, session_json(Method::GET, &format!("/api/v1/frogols/{}", id), &session, None)).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(json_body(res).await, json!({"code": "not_found", "error": "Frogol not found"}));
+}
+
+#[tokio::test]
+async fn links_and_clicks_are_paginated() {
+    let app = test_app().await;
+    let (user_id, session) = verified_user(&app, "a@example.com").await;
+    let frogol = app.state.services.frogol.create_frogol(&user_id, "alice", "Alice").await.unwrap();
+    let secret = token_with(&app, &session, &[ApiScope::LinksWrite, ApiScope::AnalyticsRead]).await;
+
+    let mut ids = Vec::new();
+    for label in ["One", "Two", "Three"] {
+        let res = send(
+            &app.state,
+            bearer_json(
+                Method::POST,
+                &format!("/api/v1/frogols/{}/links", frogol.id),
+                &secret,
+                Some(json!({"url": "example.com", "label": label})),
+            ),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let link = json_body(res).await;
+        assert_eq!(link["url"], "https://example.com");
+        ids.push(link["id"].as_str().unwrap().to_string());
+    }
+
+    let res = send(
+        &app.state,
+        bearer_json(Method::GET, &format!("/api/v1/frogols/{}/links?page=2&per_page=2", frogol.id), &secret, None),
+    )
+    .await;
+    let page = json_body(res).await;
+    assert_eq!(page["pagination"], json!({"page": 2, "per_page": 2, "total": 3}));
+    assert_eq!(page["data"].as_array().unwrap().len(), 1);
+    assert_eq!(page["data"][0]["label"], "Three");
+
+    let res = send(
+        &app.state,
+        bearer_json(
+            Method::PATCH,
+            &format!("/api/v1/links/{}", ids[0]),
+            &secret,
+            Some(json!({"label": "First", "is_active": false})),
+        ),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let link = json_body(res).await;
+    assert_eq!(link["label"], "First");
+    assert_eq!(link["is_active"], false);
+
+    for ua in [FIREFOX, FIREFOX, FIREFOX, "curl/8.5.0"] {
+        let visitor = app.state.services.visitors.identify(Some("10.0.0.1".parse().unwrap()), Some(ua), false).await.unwrap();
+        let agent = user_agent::classify(Some(ua));
+        let bot = agent.is_bot().then_some(BotSignal::UserAgent);
+        app.state.services.frogol.track_click(&ids[1], visitor, agent, bot).await.unwrap();
+    }
+    let res = send(
+        &app.state,
+        bearer_json(Method::GET, &format!("/api/v1/links/{}/clicks?per_page=2", ids[1]), &secret, None),
+    )
+    .await;
+    let clicks = json_body(res).await;
+    assert_eq!(clicks["pagination"]["total"], 3);
+    assert_eq!(clicks["data"].as_array().unwrap().len(), 2);
+    assert_eq!(clicks["data"][1]["browser"], "Firefox");
+    assert!(clicks["data"][0].get("user_agent").is_none());
+    assert!(clicks["data"][0].get("ip_address").is_none());
+    // Bot clicks are listed when asked, flagged
+    let res = send(
+        &app.state,
+        bearer_json(Method::GET, &format!("/api/v1/links/{}/clicks?include_bots=true", ids[1]), &secret, None),
+    )
+    .await;
+    let clicks = json_body(res).await;
+    assert_eq!(clicks["pagination"]["total"], 4);
+    assert_eq!(clicks["data"].as_array().unwrap().iter().filter(|c| c["is_bot"] == true).count(), 1);
+
+    let res = send(
+        &app.state,
+        bearer_json(Method::GET, &format!("/api/v1/frogols/{}/analytics", frogol.id), &secret, None),
+    )
+    .await;
+    let analytics = json_body(res).await;
+    // but never counted unless asked
+    assert_eq!(analytics["total_clicks"], 3);
+    assert_eq!(analytics["bot_clicks"], 1);
+    assert_eq!(analytics["clicks_by_link"][0], json!({"link_id": ids[1], "clicks": 3}));
+    assert_eq!(analytics["devices"], json!([{"name": "desktop", "clicks": 3}]));
+    assert_eq!(analytics["operating_systems"], json!([{"name": "Linux", "clicks": 3}]));
+
+    let res = send(&app.state, bearer_json(Method::DELETE, &format!("/api/v1/links/{}", ids[2]), &secret, None)).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert!(app.state.services.frogol.get_link(&ids[2]).await.is_err());
+}
+
+#[tokio::test]
+async fn reads_need_the_write_scope_of_what_they_read() {
+    let app = test_app().await;
+    let f = owner_with_links(&app, "scoped", 3, &[ApiScope::AnalyticsRead]).await;
+    for extra in ["second", "third"] {
+        let user = app.state.services.auth.validate_token(&f.session).await.unwrap();
+        app.state.services.frogol.create_frogol(&user.id, extra, "Frog").await.unwrap();
+    }
+    let frogol_uri = format!("/api/v1/frogols/{}", f.frogol_id);
+    let links_uri = format!("/api/v1/frogols/{}/links", f.frogol_id);
+    let link_uri = format!("/api/v1/links/{}", f.link_ids[0]);
+
+    for uri in ["/api/v1/frogols", &frogol_uri, &links_uri, &link_uri] {
+        let (status, body) = get_json(&app, &f.secret, uri).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{}", uri);
+        assert_eq!(body["code"], "forbidden");
+    }
+
+    let frogols = token_with(&app, &f.session, &[ApiScope::FrogolsWrite]).await;
+    let (status, page) = get_json(&app, &frogols, "/api/v1/frogols?page=2&per_page=2").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(page["pagination"], json!({"page": 2, "per_page": 2, "total": 3}));
+    assert_eq!(page["data"].as_array().unwrap().len(), 1);
+    assert_eq!(get_json(&app, &frogols, &frogol_uri).await.0, StatusCode::OK);
+    assert_eq!(get_json(&app, &frogols, &links_uri).await.0, StatusCode::FORBIDDEN);
+
+    let links = token_with(&app, &f.session, &[ApiScope::LinksWrite]).await;
+    let (_, page) = get_json(&app, &links, &format!("{}?per_page=2", links_uri)).await;
+    assert_eq!(page["pagination"]["total"], 3);
+    assert_eq!(page["data"][1]["label"], "Link 2");
+    assert_eq!(get_json(&app, &links, &link_uri).await.0, StatusCode::OK);
+    assert_eq!(get_json(&app, &links, "/api/v1/frogols").await.0, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn lead_lifecycle() {
+    let app = test_app().await;
+    let (user_id, session) = verified_user(&app, "a@example.com").await;
+    let frogol = app.state.services.frogol.create_frogol(&user_id, "alice", "Alice").await.unwrap();
+
+    let res = send(
+        &app.state,
+        session_json(
+            Method::POST,
+            &format!("/api/v1/frogols/{}/leads", frogol.id),
+            &session,
+            Some(json!({"email": "fan@example.com", "source": "social"})),
+        ),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let lead = json_body(res).await;
+    assert_eq!(lead["score"], 80);
+    let id = lead["id"].as_str().unwrap().to_string();
+
+    let res = send(
+        &app.state,
+        session_json(Method::PATCH, &format!("/api/v1/leads/{}", id), &session, Some(json!({"score": 5}))),
+    )
+    .await;
+    let lead = json_body(res).await;
+    assert_eq!(lead["score"], 5);
+    assert_eq!(lead["source"], "social");
+
+    let res = send(
+        &app.state,
+        session_json(Method::PATCH, &format!("/api/v1/leads/{}", id), &session, Some(json!({"source": null}))),
+    )
+    .await;
+    let lead = json_body(res).await;
+    assert_eq!((&lead["source"], &lead["score"]), (&Value::Null, &json!(5)));
+
+    let res = send(
+        &app.state,
+        session_json(Method::GET, &format!("/api/v1/frogols/{}/leads", frogol.id), &session, None),
+    )
+    .await;
+    let leads = json_body(res).await;
+    assert_eq!(leads["pagination"]["total"], 1);
+    assert_eq!(leads["data"][0]["email"], "fan@example.com");
+
+    let res = send(&app.state, session_json(Method::DELETE, &format!("/api/v1/leads/{}", id), &session, None)).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = send(&app.state, session_json(Method::GET, &format!("/api/v1/leads/{}", id), &session, None)).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn errors_are_json() {
+    let app = test_app().await;
+    let (alice_id, alice) = verified_user(&app, "a@example.com").await;
+    let (_, bob) = verified_user(&app, "b@example.com").await;
+    let frogol = app.state.services.frogol.create_frogol(&alice_id, "alice", "Alice").await.unwrap();
+
+    // Someone else's frogol
+    let res = send(&app.state, session_json(Method::GET, &format!("/api/v1/frogols/{}", frogol.id), &bob, None)).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert!(json_body(res).await["error"].is_string());
+
+    // Missing token scope
+    let secret = token_with(&app, &alice, &[ApiScope::AnalyticsRead]).await;
+    let res = send(
+        &app.state,
+        bearer_json(
+            Method::POST,
+            &format!("/api/v1/frogols/{}/links", frogol.id),
+            &secret,
+            Some(json!({"url": "example.com", "label": "Site"})),
+        ),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_eq!(json_body(res).await, json!({"code": "forbidden", "error": "This token lacks the links:write scope"}));
+
+    // Malformed body and query
+    let res = send(
+        &app.state,
+        session_json(Method::POST, &format!("/api/v1/frogols/{}/links", frogol.id), &alice, Some(json!({"url": 1}))),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(json_body(res).await["error"].is_string());
+
+    // Well-formed bodies that fail field checks
+    let res = send(
+        &app.state,
+        session_json(
+            Method::POST,
+            &format!("/api/v1/frogols/{}/links", frogol.id),
+            &alice,
+            Some(json!({"url": " ", "label": ""})),
+        ),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = json_body(res).await;
+    assert_eq!(body["code"], "validation_failed");
+    assert_eq!(body["fields"]["url"][0]["code"], "blank");
+    assert_eq!(body["fields"]["label"][0]["code"], "blank");
+
+    let res = send(
+        &app.state,
+        session_json(Method::POST, &format!("/api/v1/frogols/{}/leads", frogol.id), &alice, Some(json!({"email": "nope"}))),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json_body(res).await["fields"]["email"][0]["code"], "email");
+
+    let res = send(&app.state, session_json(Method::GET, "/api/v1/frogols?per_page=500", &alice, None)).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(json_body(res).await, json!({"code": "invalid_input", "error": "per_page must be between 1 and 100"}));
+
+    // No credentials at all
+    let res = send(
+        &app.state,
+        Request::builder().uri("/api/v1/frogols").body(Body::empty()).unwrap(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    assert!(json_body(res).await["error"].is_string());
+}
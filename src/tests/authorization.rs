@@ -0,0 +1,164 @@
+use axum::http::{Method, StatusCode};
+
+use super::{request, send, sign_in, test_app, TestApp};
+
+struct Fixture {
+    app: TestApp,
+    owner_id: String,
+    owner_token: String,
+    intruder_token: String,
+    frogol_id: String,
+    slug: String,
+    link_id: String,
+    lead_id: String,
+}
+
+/// User A owns a frogol with one link and one lead; user B owns nothing.
+async fn fixture() -> Fixture {
+    let app = test_app().await;
+    let state = &app.state;
+    let (owner_id, owner_token) = sign_in(state, "a@example.com").await;
+    let (_, intruder_token) = sign_in(state, "b@example.com").await;
+
+    let frogol = state.services.frogol.create_frogol(&owner_id, "alice", "Alice").await.unwrap();
+    let link = state.services.frogol.add_link(&frogol.id, "https://example.com", "Site").await.unwrap();
+    let lead = state
+        .services
+        .lead
+        .capture_lead(&frogol.id, "fan@example.com", Some("direct"), None, None)
+        .await
+        .unwrap();
+
+    Fixture {
+        app,
+        owner_id,
+        owner_token,
+        intruder_token,
+        frogol_id: frogol.id,
+        slug: frogol.slug,
+        link_id: link.id,
+        lead_id: lead.id,
+    }
+}
+
+#[tokio::test]
+async fn anonymous_requests_are_unauthorized() {
+    let f = fixture().await;
+
+    let cases = [
+        (Method::GET, format!("/dashboard/frogol/{}", f.frogol_id)),
+        (Method::PUT, format!("/dashboard/frogol/{}/edit", f.frogol_id)),
+        (Method::DELETE, format!("/dashboard/frogol/{}/delete", f.frogol_id)),
+        (Method::POST, format!("/api/frogol/{}/links", f.slug)),
+        (Method::PUT, format!("/api/links/{}", f.link_id)),
+        (Method::DELETE, format!("/api/links/{}", f.link_id)),
+        (Method::PUT, format!("/api/leads/{}", f.lead_id)),
+        (Method::DELETE, format!("/api/leads/{}", f.lead_id)),
+    ];
+    for (method, uri) in cases {
+        let res = send(&f.app.state, request(method.clone(), &uri, None, "")).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{} {}", method, uri);
+    }
+}
+
+#[tokio::test]
+async fn other_user_cannot_touch_frogol() {
+    let f = fixture().await;
+    let token = Some(f.intruder_token.as_str());
+
+    let cases = [
+        (Method::GET, format!("/dashboard/frogol/{}", f.frogol_id), ""),
+        (Method::GET, format!("/dashboard/frogol/{}/edit", f.frogol_id), ""),
+        (Method::PUT, format!("/dashboard/frogol/{}/edit", f.frogol_id), "display_name=Hijacked&theme=dark"),
+        (Method::DELETE, format!("/dashboard/frogol/{}/delete", f.frogol_id), ""),
+    ];
+    for (method, uri, form) in cases {
+        let res = send(&f.app.state, request(method.clone(), &uri, token, form)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{} {}", method, uri);
+    }
+
+    let frogol = f.app.state.services.frogol.get_owned_frogol(&f.owner_id, &f.frogol_id).await.unwrap();
+    assert_eq!(frogol.display_name.as_deref(), Some("Alice"));
+}
+
+#[tokio::test]
+async fn other_user_cannot_touch_links() {
+    let f = fixture().await;
+    let token = Some(f.intruder_token.as_str());
+
+    let cases = [
+        (Method::POST, format!("/api/frogol/{}/links", f.slug), "url=https://evil.example&label=Evil".to_string()),
+        (Method::GET, format!("/api/links/{}/edit", f.link_id), String::new()),
+        (Method::PUT, format!("/api/links/{}", f.link_id), "url=https://evil.example&label=Evil".to_string()),
+        (Method::DELETE, format!("/api/links/{}", f.link_id), String::new()),
+        (Method::PUT, "/api/links/order".to_string(), format!("id={}", f.link_id)),
+    ];
+    for (method, uri, form) in cases {
+        let res = send(&f.app.state, request(method.clone(), &uri, token, &form)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{} {}", method, uri);
+    }
+
+    let links = f.app.state.services.frogol.get_links_all(&f.frogol_id).await.unwrap();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].label, "Site");
+}
+
+#[tokio::test]
+async fn other_user_cannot_touch_leads() {
+    let f = fixture().await;
+    let token = Some(f.intruder_token.as_str());
+
+    let cases = [
+        (Method::GET, format!("/api/leads/{}", f.lead_id), ""),
+        (Method::GET, format!("/api/leads/{}/edit", f.lead_id), ""),
+        (Method::PUT, format!("/api/leads/{}", f.lead_id), "email=evil@example.com"),
+        (Method::DELETE, format!("/api/leads/{}", f.lead_id), ""),
+    ];
+    for (method, uri, form) in cases {
+        let res = send(&f.app.state, request(method.clone(), &uri, token, form)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{} {}", method, uri);
+    }
+
+    let lead = f.app.state.services.lead.get_lead(&f.lead_id).await.unwrap();
+    assert_eq!(lead.email, "fan@example.com");
+}
+
+#[tokio::test]
+async fn missing_resources_are_not_found() {
+    let f = fixture().await;
+    let token = Some(f.owner_token.as_str());
+
+    let cases = [
+        (Method::GET, "/dashboard/frogol/missing"),
+        (Method::DELETE, "/api/links/missing"),
+        (Method::DELETE, "/api/leads/missing"),
+        (Method::POST, "/api/frogol/missing/links"),
+    ];
+    for (method, uri) in cases {
+        let res = send(&f.app.state, request(method.clone(), uri, token, "url=https://x.example&label=X")).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND, "{} {}", method, uri);
+    }
+}
+
+#[tokio::test]
+async fn owner_can_manage_own_data() {
+    let f = fixture().await;
+    let token = Some(f.owner_token.as_str());
+
+    let res = send(
+        &f.app.state,
+        request(Method::PUT, &format!("/dashboard/frogol/{}/edit", f.frogol_id), token, "display_name=Alice+B&theme=dark"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+
+    let res = send(&f.app.state, request(Method::DELETE, &format!("/api/leads/{}", f.lead_id), token, "")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = send(&f.app.state, request(Method::DELETE, &format!("/api/links/{}", f.link_id), token, "")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let frogol = f.app.state.services.frogol.get_owned_frogol(&f.owner_id, &f.frogol_id).await.unwrap();
+    assert_eq!(frogol.display_name.as_deref(), Some("Alice B"));
+    assert!(f.app.state.services.frogol.get_links_all(&f.frogol_id).await.unwrap().is_empty());
+}
@@ -0,0 +1,96 @@
+use axum::http::{header, HeaderMap, Method};
+use chrono::Duration;
+
+use super::{click, get_json, owner_with_links, test_app, FIREFOX};
+use crate::services::{
+    api_token_service::ApiScope,
+    bot_detection::{is_prefetch, BURST_LIMIT, BURST_WINDOW_SECONDS},
+};
+
+const SLACKBOT: &str = "Slackbot-LinkExpanding 1.0 (+https://api.slack.com/robots)";
+
+#[test]
+fn look_ahead_requests_are_prefetches() {
+    let headers = |name: &str, value: &str| {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HeaderName::from_bytes(name.as_bytes()).unwrap(), value.parse().unwrap());
+        headers
+    };
+    assert!(is_prefetch(&Method::HEAD, &HeaderMap::new()));
+    assert!(is_prefetch(&Method::GET, &headers("sec-purpose", "prefetch;prerender")));
+    assert!(is_prefetch(&Method::GET, &headers("purpose", "prefetch")));
+    assert!(is_prefetch(&Method::GET, &headers("x-purpose", "preview")));
+    assert!(is_prefetch(&Method::GET, &headers("x-moz", "prefetch")));
+    assert!(!is_prefetch(&Method::GET, &HeaderMap::new()));
+    assert!(!is_prefetch(&Method::GET, &headers("sec-fetch-mode", "navigate")));
+}
+
+#[tokio::test]
+async fn bot_clicks_are_flagged_and_counted_only_when_asked() {
+    let app = test_app().await;
+    let owner = owner_with_links(&app, "bots", 1, &[ApiScope::AnalyticsRead]).await;
+    let (frogol_id, link_id, secret) = (&owner.frogol_id, &owner.link_ids[0], &owner.secret);
+
+    click(&app, Method::GET, link_id, "203.0.113.1", FIREFOX, &[]).await;
+    click(&app, Method::GET, link_id, "203.0.113.2", SLACKBOT, &[]).await;
+    click(&app, Method::HEAD, link_id, "203.0.113.3", FIREFOX, &[]).await;
+    click(&app, Method::GET, link_id, "203.0.113.4", FIREFOX, &[("sec-purpose", "prefetch")]).await;
+    // One visitor clicking on and on is a script after the first few
+    for _ in 0..BURST_LIMIT + 2 {
+        click(&app, Method::GET, link_id, "203.0.113.5", FIREFOX, &[]).await;
+    }
+    // and a visitor again once the window has passed
+    app.clock.advance(Duration::seconds(BURST_WINDOW_SECONDS + 1));
+    click(&app, Method::GET, link_id, "203.0.113.5", FIREFOX, &[]).await;
+    let humans = 2 + BURST_LIMIT;
+    let everyone = humans + 5;
+
+    let stored: (i64, i64) = sqlx::query_as("SELECT COUNT(*), SUM(is_bot) FROM clicks").fetch_one(&app.pool).await.unwrap();
+    assert_eq!(stored, (everyone, 5));
+    let rollup: (i64, i64) = sqlx::query_as("SELECT clicks, bot_clicks FROM click_daily_rollups").fetch_one(&app.pool).await.unwrap();
+    assert_eq!(rollup, (humans, 5));
+    let rollup: (i64, i64) = sqlx::query_as("SELECT SUM(clicks), SUM(bot_clicks) FROM click_hourly_rollups")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(rollup, (humans, 5));
+
+    let stats = app.state.services.frogol.get_click_stats(frogol_id, false).await.unwrap();
+    assert_eq!((stats.total_clicks, stats.unique_clicks), (humans, 2));
+    let stats = app.state.services.frogol.get_click_stats(frogol_id, true).await.unwrap();
+    assert_eq!((stats.total_clicks, stats.unique_clicks), (everyone, 5));
+
+    for (query, clicks) in [("", humans), ("?include_bots=true", everyone)] {
+        let (_, analytics) = get_json(&app, secret, &format!("/api/v1/analytics{}", query)).await;
+        assert_eq!(analytics["total_clicks"], clicks, "{}", query);
+        assert_eq!(analytics["top_frogols"][0]["total_clicks"], clicks, "{}", query);
+        let (_, analytics) = get_json(&app, secret, &format!("/api/v1/frogols/{}/analytics{}", frogol_id, query)).await;
+        assert_eq!(analytics["clicks_by_link"][0]["clicks"], clicks, "{}", query);
+        assert_eq!(analytics["bot_clicks"], 5);
+        let devices: i64 = analytics["devices"].as_array().unwrap().iter().map(|d| d["clicks"].as_i64().unwrap()).sum();
+        assert_eq!(devices, clicks, "{}", query);
+        let (_, list) = get_json(&app, secret, &format!("/api/v1/links/{}/clicks{}", link_id, query)).await;
+        assert_eq!(list["pagination"]["total"], clicks, "{}", query);
+    }
+
+    // Series count bots when asked, from the rollups and from the clicks alike
+    for query in ["", "&tz_offset=60", "&bucket=hour", "&tz_offset=-30"] {
+        let uri = format!("/api/v1/frogols/{}/analytics/clicks?include_bots=false{}", frogol_id, query);
+        let (_, series) = get_json(&app, secret, &uri).await;
+        let total: i64 = series["data"].as_array().unwrap().iter().map(|b| b["clicks"].as_i64().unwrap()).sum();
+        assert_eq!(total, humans, "{}", query);
+        let (_, series) = get_json(&app, secret, &uri.replace("include_bots=false", "include_bots=true")).await;
+        assert_eq!(series["include_bots"], true);
+        let total: i64 = series["data"].as_array().unwrap().iter().map(|b| b["clicks"].as_i64().unwrap()).sum();
+        assert_eq!(total, everyone, "{}", query);
+    }
+
+    let metrics = app.state.metrics.render();
+    for line in [
+        r#"frogolio_bot_clicks_total{signal="user_agent"} 1"#,
+        r#"frogolio_bot_clicks_total{signal="prefetch"} 2"#,
+        r#"frogolio_bot_clicks_total{signal="burst"} 2"#,
+    ] {
+        assert!(metrics.lines().any(|l| l == line), "{}", line);
+    }
+}
@@ -0,0 +1,174 @@
+use axum::http::{Method, StatusCode};
+use serde_json::Value;
+
+use super::{body_text, get_json, owner_with_links, request, send, test_app, Owner, TestApp};
+use crate::services::{api_token_service::ApiScope, clock::Clock, user_agent, visitor_service::Visitor};
+
+/// An owner with a frogol of two links, and an analytics token.
+async fn fixture(app: &TestApp) -> Owner {
+    owner_with_links(app, "charted", 2, &[ApiScope::AnalyticsRead]).await
+}
+
+fn clicks(series: &Value) -> Vec<i64> {
+    series["data"].as_array().unwrap().iter().map(|b| b["clicks"].as_i64().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn recording_a_click_updates_its_days_rollup() {
+    let app = test_app().await;
+    let f = fixture(&app).await;
+    for link in [&f.link_ids[0], &f.link_ids[0], &f.link_ids[1]] {
+        app.state.services.frogol.track_click(link, Visitor::default(), user_agent::classify(None), None).await.unwrap();
+    }
+
+    let rollups: Vec<(String, i64)> = sqlx::query_as("SELECT link_id, clicks FROM click_daily_rollups ORDER BY clicks DESC")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(rollups, [(f.link_ids[0].clone(), 2), (f.link_ids[1].clone(), 1)]);
+    let hourly: i64 = sqlx::query_scalar("SELECT SUM(clicks) FROM click_hourly_rollups").fetch_one(&app.pool).await.unwrap();
+    assert_eq!(hourly, 3);
+
+    // The default series ends today, in UTC from the rollups and shifted from the clicks
+    let (status, series) = get_json(&app, &f.secret, &format!("/api/v1/frogols/{}/analytics/clicks", f.frogol_id)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(series["bucket"], "day");
+    assert_eq!(clicks(&series).len(), 30);
+    assert_eq!(clicks(&series).iter().sum::<i64>(), 3);
+    let (_, series) = get_json(&app, &f.secret, &format!("/api/v1/links/{}/analytics/clicks?tz_offset=-300", f.link_ids[1])).await;
+    assert_eq!(clicks(&series).iter().sum::<i64>(), 1);
+
+    // Deleting the frogol takes its rollups along
+    app.state.services.frogol.delete_frogol(&f.frogol_id).await.unwrap();
+    let left: i64 = sqlx::query_scalar("SELECT (SELECT COUNT(*) FROM click_daily_rollups) + (SELECT COUNT(*) FROM click_hourly_rollups)")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(left, 0);
+}
+
+#[tokio::test]
+async fn whole_hour_time_zones_read_the_hourly_rollups() {
+    let app = test_app().await;
+    let f = fixture(&app).await;
+    // Rollups without the clicks behind them, so only the rollups can answer
+    sqlx::query("INSERT INTO click_hourly_rollups (link_id, hour, clicks, bot_clicks) VALUES (?1, '2026-03-01T23', 2, 1)")
+        .bind(&f.link_ids[0])
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    let frogol_series = |query: &str| format!("/api/v1/frogols/{}/analytics/clicks?from=2026-03-01&to=2026-03-02&{}", f.frogol_id, query);
+
+    let (_, series) = get_json(&app, &f.secret, &frogol_series("tz_offset=60")).await;
+    assert_eq!(clicks(&series), [0, 2]);
+    let (_, series) = get_json(&app, &f.secret, &frogol_series("tz_offset=-300&include_bots=true")).await;
+    assert_eq!(clicks(&series), [3, 0]);
+    let (_, series) = get_json(&app, &f.secret, &frogol_series("bucket=hour&tz_offset=60")).await;
+    assert_eq!(clicks(&series)[24], 2);
+    let uri = format!("/api/v1/links/{}/analytics/clicks?from=2026-03-01&to=2026-03-01&bucket=hour", f.link_ids[0]);
+    let (_, series) = get_json(&app, &f.secret, &uri).await;
+    assert_eq!(clicks(&series)[23], 2);
+
+    // Half-hour time zones count the clicks themselves, over a capped range
+    let (_, series) = get_json(&app, &f.secret, &frogol_series("tz_offset=330")).await;
+    assert_eq!(clicks(&series), [0, 0]);
+    let long_range = format!("/api/v1/frogols/{}/analytics/clicks?from=2026-01-01&to=2026-04-30", f.frogol_id);
+    let (status, body) = get_json(&app, &f.secret, &format!("{}&tz_offset=330", long_range)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    let (status, _) = get_json(&app, &f.secret, &format!("{}&tz_offset=60", long_range)).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn default_range_ends_on_the_clocks_today() {
+    let app = test_app().await;
+    let f = fixture(&app).await;
+    app.clock.advance(chrono::Duration::days(10));
+    app.state.services.frogol.track_click(&f.link_ids[0], Visitor::default(), user_agent::classify(None), None).await.unwrap();
+
+    let (_, series) = get_json(&app, &f.secret, &format!("/api/v1/frogols/{}/analytics/clicks", f.frogol_id)).await;
+    assert_eq!(series["to"], app.clock.now().date_naive().to_string());
+    assert_eq!(clicks(&series).iter().sum::<i64>(), 1);
+}
+
+#[tokio::test]
+async fn buckets_follow_the_requested_time_zone() {
+    let app = test_app().await;
+    let f = fixture(&app).await;
+    for (link, at) in [
+        (&f.link_ids[0], "2026-03-01T23:30:00.000Z"), // a Sunday
+        (&f.link_ids[0], "2026-03-02T00:15:00.000Z"),
+        (&f.link_ids[1], "2026-03-02T10:00:00.000Z"),
+        (&f.link_ids[1], "2026-03-09T12:00:00.000Z"),
+    ] {
+        sqlx::query("INSERT INTO clicks (id, link_id, created_at) VALUES (?1, ?2, ?3)")
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(link)
+            .bind(at)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+    }
+    for rollup in [
+        "INSERT INTO click_daily_rollups (link_id, day, clicks)
+         SELECT link_id, substr(created_at, 1, 10), COUNT(*) FROM clicks GROUP BY 1, 2",
+        "INSERT INTO click_hourly_rollups (link_id, hour, clicks)
+         SELECT link_id, substr(created_at, 1, 13), COUNT(*) FROM clicks GROUP BY 1, 2",
+    ] {
+        sqlx::query(rollup).execute(&app.pool).await.unwrap();
+    }
+    let frogol_series = |query: &str| format!("/api/v1/frogols/{}/analytics/clicks?{}", f.frogol_id, query);
+
+    let (_, series) = get_json(&app, &f.secret, &frogol_series("from=2026-03-01&to=2026-03-02")).await;
+    assert_eq!(clicks(&series), [1, 2]);
+    assert_eq!(series["data"][0]["start"], "2026-03-01T00:00:00+00:00");
+
+    // An hour east, the late Sunday click lands on Monday
+    let (_, series) = get_json(&app, &f.secret, &frogol_series("from=2026-03-01&to=2026-03-02&tz_offset=60")).await;
+    assert_eq!(clicks(&series), [0, 3]);
+    assert_eq!(series["data"][1]["start"], "2026-03-02T00:00:00+01:00");
+
+    // Half an hour west, the quarter past midnight click stays on Sunday
+    let (_, series) = get_json(&app, &f.secret, &frogol_series("from=2026-03-01&to=2026-03-02&tz_offset=-30")).await;
+    assert_eq!(clicks(&series), [2, 1]);
+
+    let (_, series) = get_json(&app, &f.secret, &frogol_series("bucket=hour&from=2026-03-02&to=2026-03-02")).await;
+    let hours = clicks(&series);
+    assert_eq!(hours.len(), 24);
+    assert_eq!((hours[0], hours[10], hours.iter().sum::<i64>()), (1, 1, 2));
+
+    // Weeks start on Monday, the first one before the range does
+    let (_, series) = get_json(&app, &f.secret, &frogol_series("bucket=week&from=2026-03-01&to=2026-03-10")).await;
+    assert_eq!(clicks(&series), [1, 2, 1]);
+    assert_eq!(series["data"][0]["start"], "2026-02-23T00:00:00+00:00");
+
+    let (_, series) = get_json(
+        &app,
+        &f.secret,
+        &format!("/api/v1/links/{}/analytics/clicks?from=2026-03-01&to=2026-03-31&bucket=week", f.link_ids[1]),
+    )
+    .await;
+    assert_eq!(clicks(&series), [0, 1, 1, 0, 0, 0]);
+
+    for bad in ["from=2026-03-02&to=2026-03-01", "bucket=hour&from=2026-01-01&to=2026-03-01", "tz_offset=900", "from=March"] {
+        let (status, body) = get_json(&app, &f.secret, &frogol_series(bad)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}: {}", bad, body);
+    }
+
+    // The dashboard draws the same series
+    let res = send(
+        &app.state,
+        request(Method::GET, &format!("/dashboard/frogol/{}?from=2026-03-01&to=2026-03-02", f.frogol_id), Some(&f.session), ""),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let page = body_text(res).await;
+    assert!(page.contains("3 in range, UTC"));
+    assert!(page.contains(r#"title="Mar 02: 2""#));
+
+    let res = send(&app.state, request(Method::GET, "/dashboard/analytics?bucket=week&from=2026-03-01&to=2026-03-10", Some(&f.session), "")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let page = body_text(res).await;
+    assert!(page.contains("4 in range, UTC"));
+    assert!(page.contains(r#"title="Week of Mar 09: 1""#));
+}
@@ -0,0 +1,106 @@
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header, HeaderMap, Method, Request, StatusCode},
+};
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+
+use super::{send, sign_in, test_app_with, test_config};
+use crate::{
+    config::{Config, ProxyConfig},
+    middleware::client_ip::client_ip,
+};
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in pairs {
+        headers.append(*name, value.parse().unwrap());
+    }
+    headers
+}
+
+fn trusted() -> Vec<IpNet> {
+    vec!["10.0.0.0/8".parse().unwrap(), "2001:db8:ffff::/48".parse().unwrap()]
+}
+
+#[test]
+fn forwarding_headers_count_only_from_trusted_proxies() {
+    let spoofed = headers(&[("x-forwarded-for", "203.0.113.9")]);
+    assert_eq!(client_ip(Some(ip("198.51.100.7")), &spoofed, &trusted()), Some(ip("198.51.100.7")));
+    assert_eq!(client_ip(Some(ip("10.0.0.2")), &spoofed, &trusted()), Some(ip("203.0.113.9")));
+    // Without connect info there is nobody to trust
+    assert_eq!(client_ip(None, &spoofed, &trusted()), None);
+
+    // A client that forges the start of the chain is still found at its end
+    let chain = headers(&[("x-forwarded-for", "1.2.3.4, 203.0.113.9:5120, 10.1.1.1")]);
+    assert_eq!(client_ip(Some(ip("10.0.0.2")), &chain, &trusted()), Some(ip("203.0.113.9")));
+
+    // A dual-stack listener's view of a trusted IPv4 proxy
+    assert_eq!(client_ip(Some(ip("::ffff:10.0.0.2")), &chain, &trusted()), Some(ip("203.0.113.9")));
+}
+
+#[test]
+fn forwarded_is_preferred_over_the_older_headers() {
+    let both = headers(&[
+        ("forwarded", r#"for=192.0.2.60;proto=https, for="[2001:db8:cafe::17]:4711";by=10.0.0.1"#),
+        ("forwarded", "for=10.2.2.2"),
+        ("x-forwarded-for", "203.0.113.9"),
+        ("x-real-ip", "203.0.113.10"),
+    ]);
+    assert_eq!(client_ip(Some(ip("10.0.0.2")), &both, &trusted()), Some(ip("2001:db8:cafe::17")));
+
+    let real_ip = headers(&[("x-real-ip", "203.0.113.10")]);
+    assert_eq!(client_ip(Some(ip("10.0.0.2")), &real_ip, &trusted()), Some(ip("203.0.113.10")));
+
+    // A hop the proxy would not name leaves that proxy as the best we know
+    let hidden = headers(&[("forwarded", "for=_hidden, for=10.3.3.3")]);
+    assert_eq!(client_ip(Some(ip("10.0.0.2")), &hidden, &trusted()), Some(ip("10.3.3.3")));
+}
+
+/// A request arriving from `peer`, as if the server had been given connect info.
+fn from_peer(mut req: Request<Body>, peer: &str) -> Request<Body> {
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::new(ip(peer), 40000)));
+    req
+}
+
+#[tokio::test]
+async fn clicks_and_leads_record_the_client_behind_the_proxy() {
+    let app = test_app_with(Config { proxy: ProxyConfig { trusted_proxies: trusted() }, ..test_config() }).await;
+    let (user_id, _) = sign_in(&app.state, "a@example.com").await;
+    let frogol = app.state.services.frogol.create_frogol(&user_id, "proxied", "Proxied").await.unwrap();
+    let link = app.state.services.frogol.add_link(&frogol.id, "https://example.com", "Example").await.unwrap();
+
+    for visitor in ["203.0.113.1", "203.0.113.2", "203.0.113.2"] {
+        let req = Request::builder()
+            .uri(format!("/api/links/{}/click", link.id))
+            .header("x-forwarded-for", visitor)
+            .body(Body::empty())
+            .unwrap();
+        let res = send(&app.state, from_peer(req, "10.0.0.2")).await;
+        assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    }
+    let stats = app.state.services.frogol.get_click_stats(&frogol.id, false).await.unwrap();
+    assert_eq!((stats.total_clicks, stats.unique_clicks), (3, 2));
+
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(format!("/api/lead/{}", frogol.id))
+        .header("x-forwarded-for", "203.0.113.1")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from("email=fan%40example.com"))
+        .unwrap();
+    let res = send(&app.state, from_peer(req, "198.51.100.7")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let stored: Option<String> = sqlx::query_scalar("SELECT ip_address FROM leads WHERE frogol_id = ?1")
+        .bind(&frogol.id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    // That peer is not a trusted proxy, so its header is ignored
+    assert_eq!(stored.as_deref(), Some("198.51.100.0"));
+}
@@ -0,0 +1,128 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+use super::{multipart_upload, send, sign_in, test_app, test_config};
+use crate::{
+    config::{AvatarConfig, Config, ConfigError},
+    logging::LogFormat,
+    services::{auth_service::EmailVerificationPolicy, mailer::FileMailer},
+    state::AppState,
+};
+
+fn load(file: Option<&str>, vars: &[(&str, &str)]) -> Result<Config, ConfigError> {
+    let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+    Config::from_sources(file.map(|contents| (Path::new("frogolio.toml"), contents)), &|name| vars.get(name).cloned())
+}
+
+const REQUIRED: [(&str, &str); 2] = [("DATABASE_URL", "sqlite::memory:"), ("JWT_SECRET", "secret")];
+
+#[test]
+fn defaults_apply_when_nothing_is_set() {
+    let config = load(None, &REQUIRED).unwrap();
+    assert_eq!(config.server.bind.to_string(), "0.0.0.0:3000");
+    assert_eq!(config.avatars.max_upload_bytes, 5 * 1024 * 1024);
+    assert_eq!(config.auth.session_ttl, chrono::Duration::hours(24));
+    assert_eq!(config.auth.verification_policy, EmailVerificationPolicy::RequiredForPublish);
+    assert_eq!(config.maintenance.purge_interval.as_secs(), 3600);
+    assert_eq!(config.server.drain_delay.as_secs(), 5);
+}
+
+#[test]
+fn environment_overrides_the_file() {
+    let file = r#"
+        [server]
+        bind = "127.0.0.1:8080"
+
+        [auth]
+        session_ttl_hours = 12
+        email_verification = "login"
+
+        [avatars]
+        max_upload_bytes = 1048576
+    "#;
+    let config = load(
+        Some(file),
+        &[REQUIRED[0], REQUIRED[1], ("SESSION_TTL_HOURS", "6"), ("LOG_FORMAT", "json"), ("DRAIN_DELAY_SECS", "0")],
+    )
+    .unwrap();
+    assert_eq!(config.server.bind.to_string(), "127.0.0.1:8080");
+    assert_eq!(config.auth.session_ttl, chrono::Duration::hours(6));
+    assert_eq!(config.auth.verification_policy, EmailVerificationPolicy::RequiredForLogin);
+    assert_eq!(config.avatars.max_upload_bytes, 1024 * 1024);
+    assert_eq!(config.logging.format, LogFormat::Json);
+    assert!(config.server.drain_delay.is_zero());
+}
+
+#[test]
+fn every_problem_is_reported() {
+    let err = load(
+        None,
+        &[
+            ("SESSION_TTL_HOURS", "soon"),
+            ("EMAIL_VERIFICATION_POLICY", "never"),
+            ("MAINTENANCE_INTERVAL_SECS", "0"),
+            ("LOG_FORMAT", "xml"),
+            ("PUBLIC_FRAME_ANCESTORS", ""),
+            ("TRUSTED_PROXIES", "10.0.0.0/8, proxy.internal"),
+        ],
+    )
+    .unwrap_err();
+    let ConfigError::Invalid(problems) = &err else { panic!("expected Invalid, got {}", err) };
+    for expected in [
+        "SESSION_TTL_HOURS",
+        "DATABASE_URL",
+        "JWT_SECRET",
+        "EMAIL_VERIFICATION_POLICY",
+        "MAINTENANCE_INTERVAL_SECS",
+        "LOG_FORMAT",
+        "security_headers.public.frame_ancestors",
+        "\"proxy.internal\" is not an address",
+    ] {
+        assert!(problems.iter().any(|p| p.contains(expected)), "{} not reported in {:?}", expected, problems);
+    }
+
+    let err = load(Some("[server]\nport = 80\n"), &REQUIRED).unwrap_err();
+    assert!(matches!(err, ConfigError::Parse { .. }), "{}", err);
+
+    let err = load(None, &[REQUIRED[0], ("JWT_KEYS", "a:one,b:two")]).unwrap_err();
+    assert!(err.to_string().contains("JWT_ACTIVE_KEY_ID"), "{}", err);
+}
+
+#[tokio::test]
+async fn avatar_uploads_follow_the_avatar_config() {
+    let app = test_app().await;
+    let dir = TempDir::new().unwrap();
+    let config = Config {
+        avatars: AvatarConfig { dir: dir.path().to_path_buf(), max_upload_bytes: 1024 },
+        ..test_config()
+    };
+    let mailer = Arc::new(FileMailer::new(app.outbox.path().to_path_buf()));
+    let state = AppState::new(app.pool.clone(), config, mailer, app.clock.clone());
+
+    let (user_id, token) = sign_in(&state, "a@example.com").await;
+    let frogol = state.services.frogol.create_frogol(&user_id, "frog", "Frog").await.unwrap();
+    let uri = format!("/api/frogol/{}/avatar", frogol.id);
+
+    let res = send(&state, multipart_upload(&uri, &token, &[0u8; 2048])).await;
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let res = send(&state, multipart_upload(&uri, &token, b"small png")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    let avatar_url = serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()["avatar_url"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let filename = avatar_url.strip_prefix("/static/avatars/").unwrap();
+    assert!(dir.path().join(filename).exists());
+
+    // Served from the configured directory, not static/avatars
+    let res = send(&state, Request::builder().uri(&avatar_url).body(Body::empty()).unwrap()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
@@ -0,0 +1,127 @@
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, Method, Request, StatusCode},
+};
+use serde_json::json;
+
+use super::{
+    bearer_json, body_text, browser_cookies, json_body, owner_with_links, request, send, sign_in, test_app, test_app_with,
+    test_config, Owner, TEST_CSRF_SEED, TEST_PASSWORD,
+};
+use crate::{
+    config::{Config, CsrfConfig},
+    middleware::csrf::token_for,
+    services::api_token_service::ApiScope,
+};
+
+/// A form post carrying `cookie` and nothing else: no CSRF header.
+fn form_post(uri: &str, cookie: &str, form: &str) -> Request<Body> {
+    Request::builder()
+        .method(Method::POST)
+        .uri(uri)
+        .header(header::COOKIE, cookie)
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(form.to_string()))
+        .unwrap()
+}
+
+/// `name=value` pairs from every `Set-Cookie` header.
+fn set_cookies(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .map(|v| v.to_str().unwrap().split(';').next().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn unsafe_requests_need_the_sessions_token() {
+    let app = test_app().await;
+    let (_, token) = sign_in(&app.state, "a@example.com").await;
+    let (_, other) = sign_in(&app.state, "b@example.com").await;
+    let create = "name=Script&scope=analytics:read";
+
+    // No token at all
+    let (cookie, _) = browser_cookies(Some(&token));
+    let res = send(&app.state, form_post("/dashboard/settings/api-tokens", &cookie, create)).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // A token issued to someone else's session, or to the same browser before it signed in
+    for wrong in [browser_cookies(Some(&other)).1, browser_cookies(None).1] {
+        let mut req = request(Method::POST, "/dashboard/settings/api-tokens", Some(&token), create);
+        req.headers_mut().insert("x-csrf-token", wrong.parse().unwrap());
+        let res = send(&app.state, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert_eq!(json_body(res).await["error"], "Missing or invalid CSRF token");
+    }
+
+    let res = send(&app.state, request(Method::POST, "/dashboard/settings/api-tokens", Some(&token), create)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(body_text(res).await.contains("Created API token"));
+}
+
+#[tokio::test]
+async fn plain_forms_submit_the_token_as_a_field() {
+    let app = test_app().await;
+    app.state.services.auth.register("a@example.com", TEST_PASSWORD).await.unwrap();
+
+    // A first visit is given a seed and the token bound to it
+    let res = send(&app.state, Request::builder().uri("/login").body(Body::empty()).unwrap()).await;
+    let cookies = set_cookies(res.headers());
+    let csrf = cookies
+        .iter()
+        .find_map(|c| c.strip_prefix("csrf_token="))
+        .expect("token cookie is set")
+        .to_string();
+    assert!(cookies.iter().any(|c| c.starts_with("csrf_seed=")));
+    let page = body_text(res).await;
+    assert!(page.contains(&format!(r#"name="csrf_token" value="{}""#, csrf)));
+
+    let form = format!("email=a%40example.com&password={}&csrf_token={}", TEST_PASSWORD, csrf);
+    let res = send(&app.state, form_post("/login", &cookies.join("; "), &form)).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers()[header::LOCATION], "/dashboard");
+
+    // Signed in, the page's forms carry the token for the new session
+    let auth = set_cookies(res.headers())
+        .into_iter()
+        .find_map(|c| c.strip_prefix("auth_token=").map(str::to_string))
+        .unwrap();
+    let session_csrf = token_for(&test_config().csrf.secret, Some(&auth), TEST_CSRF_SEED);
+    assert_ne!(session_csrf, browser_cookies(None).1);
+    let res = send(&app.state, request(Method::GET, "/dashboard/settings", Some(&auth), "")).await;
+    assert!(body_text(res).await.contains(&format!(r#"value="{}""#, session_csrf)));
+}
+
+#[tokio::test]
+async fn lead_capture_and_bearer_tokens_are_exempt_by_default() {
+    let app = test_app().await;
+    let Owner { frogol_id, secret, .. } = owner_with_links(&app, "exempt", 0, &[ApiScope::AnalyticsRead]).await;
+
+    let res = send(&app.state, form_post(&format!("/api/lead/{}", frogol_id), "", "email=fan%40example.com")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(app.state.services.lead.count_frogol_leads(&frogol_id).await.unwrap(), 1);
+
+    // Judged on the token's scopes, not on CSRF
+    let rename = Some(json!({"display_name": "Renamed"}));
+    let res = send(&app.state, bearer_json(Method::PATCH, &format!("/api/v1/frogols/{}", frogol_id), &secret, rename)).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_eq!(json_body(res).await["error"], "This token lacks the frogols:write scope");
+}
+
+#[tokio::test]
+async fn exemptions_can_be_withdrawn() {
+    let app = test_app_with(Config {
+        csrf: CsrfConfig { exempt_routes: Vec::new(), exempt_bearer_tokens: false, ..test_config().csrf },
+        ..test_config()
+    })
+    .await;
+    let Owner { frogol_id, secret, .. } = owner_with_links(&app, "exempt", 0, &[ApiScope::AnalyticsRead]).await;
+
+    let res = send(&app.state, form_post(&format!("/api/lead/{}", frogol_id), "", "email=fan%40example.com")).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let rename = Some(json!({"display_name": "Renamed"}));
+    let res = send(&app.state, bearer_json(Method::PATCH, &format!("/api/v1/frogols/{}", frogol_id), &secret, rename)).await;
+    assert_eq!(json_body(res).await["error"], "Missing or invalid CSRF token");
+}
@@ -0,0 +1,118 @@
+use axum::http::{Method, StatusCode};
+use chrono::Duration;
+use serde_json::json;
+
+use super::{body_text, request, send, session_json, sign_in, test_app, test_app_with, test_auth_config, test_config, TestApp, TEST_PASSWORD};
+use crate::{
+    config::Config,
+    errors::AppError,
+    services::auth_service::{AuthConfig, ClientInfo, EmailVerificationPolicy, LoginOutcome},
+};
+
+async fn open_link(app: &TestApp, token: &str) -> String {
+    let res = send(&app.state, request(Method::GET, &format!("/verify-email/{}", token), None, "")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    body_text(res).await
+}
+
+async fn is_verified(app: &TestApp, email: &str) -> bool {
+    let (verified,): (bool,) = sqlx::query_as("SELECT email_verified_at IS NOT NULL FROM users WHERE email = ?")
+        .bind(email)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    verified
+}
+
+#[tokio::test]
+async fn link_from_the_signup_email_verifies_the_account() {
+    let app = test_app().await;
+    sign_in(&app.state, "a@example.com").await;
+    assert!(!is_verified(&app, "a@example.com").await);
+
+    let email = app.sent_emails().pop().unwrap();
+    assert!(email.starts_with("To: a@example.com\n"));
+    assert!(email.contains("http://frogolio.test/verify-email/"));
+
+    let body = open_link(&app, &app.verification_token()).await;
+    assert!(body.contains("Your email address is now a@example.com"));
+    assert!(is_verified(&app, "a@example.com").await);
+}
+
+#[tokio::test]
+async fn used_and_expired_links_are_rejected() {
+    let app = test_app().await;
+    sign_in(&app.state, "a@example.com").await;
+    let token = app.verification_token();
+    assert!(open_link(&app, &token).await.contains("Your email address is now"));
+    assert!(open_link(&app, &token).await.contains("Invalid or expired verification link"));
+
+    sign_in(&app.state, "b@example.com").await;
+    let token = app.verification_token();
+    app.clock.advance(Duration::hours(25));
+    assert!(open_link(&app, &token).await.contains("Invalid or expired verification link"));
+    assert!(matches!(app.state.services.auth.verify_email(&token).await, Err(AppError::InvalidInput(_))));
+    assert!(is_verified(&app, "a@example.com").await);
+    assert!(!is_verified(&app, "b@example.com").await);
+}
+
+#[tokio::test]
+async fn resend_issues_a_fresh_link() {
+    let app = test_app().await;
+    sign_in(&app.state, "a@example.com").await;
+    let first = app.verification_token();
+    app.clock.advance(Duration::hours(25));
+
+    let res = send(&app.state, request(Method::POST, "/verify-email/resend", None, "email=a%40example.com")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(app.sent_emails().len(), 2);
+    let second = app.verification_token();
+    assert_ne!(first, second);
+
+    assert!(open_link(&app, &second).await.contains("Your email address is now a@example.com"));
+    assert!(is_verified(&app, "a@example.com").await);
+
+    // Nothing more is sent once the address is verified
+    send(&app.state, request(Method::POST, "/verify-email/resend", None, "email=a%40example.com")).await;
+    assert_eq!(app.sent_emails().len(), 2);
+}
+
+#[tokio::test]
+async fn required_for_login_blocks_sign_in_until_verified() {
+    let app = test_app_with(Config {
+        auth: AuthConfig { verification_policy: EmailVerificationPolicy::RequiredForLogin, ..test_auth_config() },
+        ..test_config()
+    })
+    .await;
+    let auth = &app.state.services.auth;
+    auth.register("a@example.com", TEST_PASSWORD).await.unwrap();
+
+    assert!(matches!(
+        auth.login("a@example.com", TEST_PASSWORD, false, &ClientInfo::default()).await,
+        Err(AppError::Forbidden(_))
+    ));
+    let res = send(&app.state, request(Method::POST, "/login", None, "email=a%40example.com&password=password123")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(body_text(res).await.contains("Please verify your email address before signing in"));
+
+    auth.verify_email(&app.verification_token()).await.unwrap();
+    assert!(matches!(
+        auth.login("a@example.com", TEST_PASSWORD, false, &ClientInfo::default()).await,
+        Ok(LoginOutcome::Authenticated(_))
+    ));
+}
+
+#[tokio::test]
+async fn unverified_users_cannot_publish() {
+    let app = test_app().await;
+    let (user_id, session) = sign_in(&app.state, "a@example.com").await;
+
+    let res = send(&app.state, request(Method::POST, "/dashboard/frogol/new", Some(&session), "slug=alice&display_name=Alice")).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let body = json!({"slug": "alice", "display_name": "Alice"});
+    let res = send(&app.state, session_json(Method::POST, "/api/v1/frogols", &session, Some(body))).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    assert!(app.state.services.frogol.get_user_frogols(&user_id).await.unwrap().is_empty());
+}
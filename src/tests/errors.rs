@@ -0,0 +1,134 @@
+use axum::{
+    http::{header, Method, StatusCode},
+    response::IntoResponse,
+};
+use serde_json::{json, Value};
+
+use super::{body_text, request, send, sign_in, test_app};
+use crate::errors::AppError;
+
+#[tokio::test]
+async fn missing_rows_are_not_found() {
+    let res = AppError::Database(sqlx::Error::RowNotFound).into_response();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let body: Value = serde_json::from_str(&body_text(res).await).unwrap();
+    assert_eq!(body, json!({"code": "not_found", "error": "Not found"}));
+}
+
+#[tokio::test]
+async fn validator_failures_list_each_field() {
+    let app = test_app().await;
+    let res = send(&app.state, request(Method::POST, "/register", None, "email=not-an-email&password=123")).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let body: Value = serde_json::from_str(&body_text(res).await).unwrap();
+    assert_eq!(body["code"], "validation_failed");
+    assert_eq!(body["fields"]["email"][0]["code"], "email");
+    assert_eq!(body["fields"]["password"][0]["code"], "length");
+}
+
+#[tokio::test]
+async fn taken_slug_is_a_conflict() {
+    let app = test_app().await;
+    let (user_id, _) = sign_in(&app.state, "a@example.com").await;
+    let frogol = &app.state.services.frogol;
+    frogol.create_frogol(&user_id, "taken", "Taken").await.unwrap();
+
+    let err = frogol.create_frogol(&user_id, "taken", "Again").await.unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+    assert_eq!(err.code(), "conflict");
+}
+
+#[tokio::test]
+async fn errors_are_rendered_for_the_client() {
+    let app = test_app().await;
+    let (_, token) = sign_in(&app.state, "a@example.com").await;
+
+    // Browsers get a full page
+    let mut req = request(Method::GET, "/dashboard/frogol/missing", Some(&token), "");
+    req.headers_mut().insert(header::ACCEPT, "text/html,application/xhtml+xml".parse().unwrap());
+    let res = send(&app.state, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert!(res.headers()[header::CONTENT_TYPE].to_str().unwrap().starts_with("text/html"));
+    let page = body_text(res).await;
+    assert!(page.contains("<!DOCTYPE html>") && page.contains("Page not found"));
+
+    // HTMX gets a fragment aimed at the page's error slot
+    let mut req = request(Method::DELETE, "/api/links/missing", Some(&token), "");
+    req.headers_mut().insert("hx-request", "true".parse().unwrap());
+    let res = send(&app.state, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(res.headers()["hx-retarget"], "#htmx-errors");
+    let fragment = body_text(res).await;
+    assert!(fragment.contains("data-error-code=\"not_found\"") && !fragment.contains("<html"));
+
+    // The versioned API keeps JSON whatever the client accepts
+    let mut req = request(Method::GET, "/api/v1/frogols/missing", Some(&token), "");
+    req.headers_mut().insert(header::ACCEPT, "text/html".parse().unwrap());
+    let res = send(&app.state, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let body: Value = serde_json::from_str(&body_text(res).await).unwrap();
+    assert_eq!(body["code"], "not_found");
+}
+
+/// A browser GET for `uri`.
+fn page(uri: &str) -> axum::http::Request<axum::body::Body> {
+    let mut req = request(Method::GET, uri, None, "");
+    req.headers_mut().insert(header::ACCEPT, "text/html".parse().unwrap());
+    req
+}
+
+#[tokio::test]
+async fn deleted_slugs_are_gone_and_can_be_reclaimed() {
+    let app = test_app().await;
+    let (user_id, _) = sign_in(&app.state, "a@example.com").await;
+    let frogols = &app.state.services.frogol;
+
+    let res = send(&app.state, page("/never-used")).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert!(body_text(res).await.contains("Page not found"));
+
+    let frogol = frogols.create_frogol(&user_id, "hopper", "Hopper").await.unwrap();
+    assert_eq!(send(&app.state, page("/hopper")).await.status(), StatusCode::OK);
+    frogols.delete_frogol(&frogol.id).await.unwrap();
+
+    let res = send(&app.state, page("/hopper")).await;
+    assert_eq!(res.status(), StatusCode::GONE);
+    assert!(body_text(res).await.contains("This page has been deleted"));
+
+    frogols.create_frogol(&user_id, "hopper", "Hopper again").await.unwrap();
+    assert_eq!(send(&app.state, page("/hopper")).await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn unknown_paths_fall_back_to_not_found() {
+    let app = test_app().await;
+    let res = send(&app.state, page("/no/such/page")).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert!(body_text(res).await.contains("Page not found"));
+
+    let res = send(&app.state, request(Method::GET, "/no/such/page", None, "")).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let body: Value = serde_json::from_str(&body_text(res).await).unwrap();
+    assert_eq!(body["code"], "not_found");
+}
+
+#[tokio::test]
+async fn server_error_page_shows_the_request_id() {
+    let app = test_app().await;
+    app.pool.close().await;
+
+    let mut req = page("/hopper");
+    req.headers_mut().insert("x-request-id", "support-1234".parse().unwrap());
+    let res = send(&app.state, req).await;
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(res.headers()["x-request-id"], "support-1234");
+    let html = body_text(res).await;
+    assert!(html.contains("Something went wrong") && html.contains("support-1234"));
+
+    // Ids that are unsafe to echo are replaced with a fresh one
+    let mut req = page("/hopper");
+    req.headers_mut().insert("x-request-id", "<script>".parse().unwrap());
+    let res = send(&app.state, req).await;
+    assert_ne!(res.headers()["x-request-id"], "<script>");
+}
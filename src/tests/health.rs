@@ -0,0 +1,98 @@
+use axum::http::{Method, StatusCode};
+use serde_json::Value;
+use tempfile::TempDir;
+
+use super::{json_body, request, send, sign_in, test_app, test_app_with, test_config, TestApp};
+use crate::{
+    config::{AvatarConfig, Config},
+    errors::AppError,
+    state::AppState,
+};
+
+async fn probe(state: &AppState, uri: &str) -> (StatusCode, Value) {
+    let res = send(state, request(Method::GET, uri, None, "")).await;
+    (res.status(), json_body(res).await)
+}
+
+/// The test app with avatars stored in `dir`.
+async fn app_with_avatar_dir(dir: std::path::PathBuf) -> TestApp {
+    test_app_with(Config { avatars: AvatarConfig { dir, ..Default::default() }, ..test_config() }).await
+}
+
+#[tokio::test]
+async fn ready_when_every_dependency_is() {
+    let avatars = TempDir::new().unwrap();
+    let app = app_with_avatar_dir(avatars.path().to_path_buf()).await;
+
+    let (status, body) = probe(&app.state, "/healthz").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "ok");
+
+    let (status, body) = probe(&app.state, "/readyz").await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["ready"], true);
+    for check in ["database", "migrations", "avatar_dir"] {
+        assert_eq!(body["checks"][check]["ok"], true, "{}", check);
+    }
+    // The scratch file is cleaned up
+    assert_eq!(std::fs::read_dir(avatars.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn not_ready_reports_the_failing_checks() {
+    let avatars = TempDir::new().unwrap();
+    let app = app_with_avatar_dir(avatars.path().join("missing")).await;
+
+    let (status, body) = probe(&app.state, "/readyz").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["checks"]["database"]["ok"], true);
+    assert_eq!(body["checks"]["avatar_dir"]["ok"], false);
+    assert_eq!(body["checks"]["avatar_dir"]["detail"], "not writable");
+
+    app.pool.close().await;
+    let (status, body) = probe(&app.state, "/readyz").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["checks"]["database"]["ok"], false);
+    // Neither driver errors nor server paths reach the unauthenticated probe
+    assert_eq!(body["checks"]["database"]["detail"], "unreachable");
+    assert!(!body.to_string().contains(&avatars.path().display().to_string()));
+
+    // Liveness does not depend on anything else
+    assert_eq!(probe(&app.state, "/healthz").await.0, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn draining_fails_readiness() {
+    let avatars = TempDir::new().unwrap();
+    let app = app_with_avatar_dir(avatars.path().to_path_buf()).await;
+
+    app.state.services.health.begin_draining();
+    let (status, body) = probe(&app.state, "/readyz").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["draining"], true);
+    assert_eq!(body["checks"]["database"]["ok"], true);
+}
+
+#[tokio::test]
+async fn pending_migrations_fail_readiness() {
+    let avatars = TempDir::new().unwrap();
+    let app = app_with_avatar_dir(avatars.path().to_path_buf()).await;
+
+    sqlx::query("DELETE FROM _sqlx_migrations WHERE version = (SELECT MAX(version) FROM _sqlx_migrations)")
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    let (status, body) = probe(&app.state, "/readyz").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(body["checks"]["migrations"]["detail"].as_str().unwrap().starts_with("pending: "));
+}
+
+#[tokio::test]
+async fn probe_paths_cannot_be_claimed_as_slugs() {
+    let app = test_app().await;
+    let (user_id, _) = sign_in(&app.state, "a@example.com").await;
+    for slug in ["healthz", "readyz"] {
+        let result = app.state.services.frogol.create_frogol(&user_id, slug, "Probe").await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))), "{}", slug);
+    }
+}
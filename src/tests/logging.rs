@@ -0,0 +1,78 @@
+use axum::http::{header, Method};
+use serde_json::Value;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::layer::SubscriberExt;
+
+use super::{request, send, sign_in, test_app};
+use crate::logging::{fmt_layer, LogFormat};
+
+/// Collects everything the JSON layer writes.
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Captured {
+    /// Logged lines whose request span has id `request_id`.
+    fn lines_for(&self, request_id: &str) -> Vec<Value> {
+        let bytes = self.0.lock().unwrap().clone();
+        String::from_utf8(bytes)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).expect("every line is JSON"))
+            .filter(|line| line["span"]["request_id"] == request_id)
+            .collect()
+    }
+}
+
+#[tokio::test]
+async fn json_lines_carry_the_request_context() {
+    let captured = Captured::default();
+    let writer = captured.clone();
+    let subscriber = tracing_subscriber::registry().with(fmt_layer(LogFormat::Json, move || writer.clone()));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let app = test_app().await;
+    let (user_id, token) = sign_in(&app.state, "a@example.com").await;
+    let frogol = app.state.services.frogol.create_frogol(&user_id, "logged", "Logged").await.unwrap();
+
+    let mut req = request(Method::GET, &format!("/api/v1/frogols/{}", frogol.id), Some(&token), "");
+    req.headers_mut().insert("x-request-id", "log-test-1".parse().unwrap());
+    let res = send(&app.state, req).await;
+    assert_eq!(res.headers()["x-request-id"], "log-test-1");
+
+    let lines = captured.lines_for("log-test-1");
+    let finished = lines
+        .iter()
+        .find(|line| line["fields"]["message"] == "Finished request")
+        .expect("the request is logged when it finishes");
+    assert_eq!(finished["fields"]["status"], 200);
+    assert_eq!(finished["span"]["method"], "GET");
+    assert_eq!(finished["span"]["path"], "/api/v1/frogols/:id");
+    assert_eq!(finished["span"]["user_id"], user_id.as_str());
+    assert_eq!(finished["span"]["frogol_id"], frogol.id.as_str());
+
+    // Server errors are logged inside the request span too
+    app.pool.close().await;
+    let mut req = request(Method::GET, "/logged", None, "");
+    req.headers_mut().insert("x-request-id", "log-test-2".parse().unwrap());
+    req.headers_mut().insert(header::ACCEPT, "text/html".parse().unwrap());
+    send(&app.state, req).await;
+
+    let lines = captured.lines_for("log-test-2");
+    let error = lines
+        .iter()
+        .find(|line| line["level"] == "ERROR")
+        .expect("the database failure is logged");
+    assert!(error["fields"]["message"].as_str().unwrap().starts_with("Database error"));
+    assert_eq!(error["span"]["path"], "/:slug");
+}
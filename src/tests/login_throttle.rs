@@ -0,0 +1,108 @@
+use axum::http::{header, Method, StatusCode};
+use chrono::Duration;
+
+use super::{request, send, sign_in, test_app, TestApp, TEST_PASSWORD};
+use crate::{
+    errors::AppError,
+    services::auth_service::{ClientInfo, DUMMY_PASSWORD_HASH},
+};
+
+fn from_ip(ip: &str) -> ClientInfo {
+    ClientInfo { ip_address: Some(ip.to_string()), user_agent: None }
+}
+
+async fn fail(app: &TestApp, email: &str, ip: &str) -> AppError {
+    app.state.services.auth.login(email, "wrong-password", false, &from_ip(ip)).await.unwrap_err()
+}
+
+fn retry_after(err: AppError) -> u64 {
+    match err {
+        AppError::RateLimited { retry_after_secs, .. } => retry_after_secs,
+        other => panic!("expected RateLimited, got {:?}", other),
+    }
+}
+
+#[test]
+fn unknown_accounts_cost_a_real_password_check() {
+    // Same cost as stored passwords, so both failures take as long
+    assert!(DUMMY_PASSWORD_HASH.starts_with(&format!("$2b${}$", bcrypt::DEFAULT_COST)));
+    assert!(!bcrypt::verify("wrong-password", DUMMY_PASSWORD_HASH).unwrap());
+}
+
+#[tokio::test]
+async fn account_locks_out_with_exponential_backoff() {
+    let app = test_app().await;
+    sign_in(&app.state, "a@example.com").await;
+    app.clock.advance(Duration::seconds(1));
+
+    for _ in 0..5 {
+        assert!(matches!(fail(&app, "a@example.com", "10.0.0.1").await, AppError::Unauthorized(_)));
+    }
+
+    // Locked even with the right password, and from another address
+    let err = app.state.services.auth.login("a@example.com", TEST_PASSWORD, false, &from_ip("10.0.0.2")).await.unwrap_err();
+    assert_eq!(retry_after(err), 30);
+
+    app.clock.advance(Duration::seconds(30));
+    assert!(matches!(fail(&app, "a@example.com", "10.0.0.1").await, AppError::Unauthorized(_)));
+    assert_eq!(retry_after(fail(&app, "a@example.com", "10.0.0.1").await), 60);
+
+    app.clock.advance(Duration::seconds(60));
+    assert!(app.state.services.auth.login("a@example.com", TEST_PASSWORD, false, &from_ip("10.0.0.1")).await.is_ok());
+    app.clock.advance(Duration::seconds(1));
+
+    // A successful sign-in resets the account's allowance
+    for _ in 0..5 {
+        assert!(matches!(fail(&app, "a@example.com", "10.0.0.1").await, AppError::Unauthorized(_)));
+    }
+}
+
+#[tokio::test]
+async fn ip_locks_out_across_accounts() {
+    let app = test_app().await;
+
+    for i in 0..20 {
+        let err = fail(&app, &format!("user{}@example.com", i), "10.0.0.9").await;
+        assert!(matches!(err, AppError::Unauthorized(_)));
+    }
+    assert_eq!(retry_after(fail(&app, "someone@example.com", "10.0.0.9").await), 30);
+    assert!(matches!(fail(&app, "someone@example.com", "10.0.0.10").await, AppError::Unauthorized(_)));
+}
+
+#[tokio::test]
+async fn old_failures_stop_counting() {
+    let app = test_app().await;
+
+    for _ in 0..5 {
+        fail(&app, "a@example.com", "10.0.0.1").await;
+    }
+    app.clock.advance(Duration::hours(25));
+    assert!(matches!(fail(&app, "a@example.com", "10.0.0.1").await, AppError::Unauthorized(_)));
+}
+
+#[tokio::test]
+async fn rate_limited_login_returns_429_with_retry_after() {
+    let app = test_app().await;
+    for _ in 0..5 {
+        fail(&app, "a@example.com", "10.0.0.1").await;
+    }
+
+    let res = send(&app.state, request(Method::POST, "/login", None, "email=a@example.com&password=wrong-password")).await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(res.headers()[header::RETRY_AFTER], "30");
+}
+
+#[tokio::test]
+async fn failed_sign_ins_are_listed_in_settings() {
+    let app = test_app().await;
+    let (user_id, token) = sign_in(&app.state, "a@example.com").await;
+    fail(&app, "a@example.com", "203.0.113.7").await;
+
+    let failures = app.state.services.auth.recent_failed_logins(&user_id).await.unwrap();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].ip_address.as_deref(), Some("203.0.113.7"));
+
+    let res = send(&app.state, request(Method::GET, "/dashboard/settings", Some(&token), "")).await;
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    assert!(String::from_utf8_lossy(&body).contains("203.0.113.7"));
+}
@@ -0,0 +1,86 @@
+use chrono::Duration;
+use std::time::SystemTime;
+use tempfile::TempDir;
+
+use super::{sign_in, test_app, TEST_PASSWORD};
+use crate::config::AvatarConfig;
+use crate::services::{
+    auth_service::{ClientInfo, LoginOutcome},
+    avatar_service::AvatarService,
+    maintenance::PurgeReport,
+};
+
+#[tokio::test]
+async fn purge_removes_only_expired_rows() {
+    let app = test_app().await;
+    let auth = &app.state.services.auth;
+    let maintenance = &app.state.services.maintenance;
+    let (_, old_token) = sign_in(&app.state, "a@example.com").await;
+    auth.request_password_reset("a@example.com").await.unwrap();
+
+    // Nothing has expired yet
+    assert_eq!(maintenance.purge_expired().await.unwrap(), PurgeReport::default());
+
+    app.clock.advance(Duration::hours(25));
+    let live = auth.login("a@example.com", TEST_PASSWORD, false, &ClientInfo::default()).await.unwrap();
+
+    let report = maintenance.purge_expired().await.unwrap();
+    assert_eq!(
+        report,
+        PurgeReport {
+            sessions: 1,
+            password_reset_tokens: 1,
+            email_verification_tokens: 1,
+            login_challenges: 0,
+            login_attempts: 0,
+            visitor_salts: 0,
+        }
+    );
+    assert!(auth.validate_token(&old_token).await.is_err());
+    match live {
+        LoginOutcome::Authenticated(session) => {
+            assert!(auth.validate_token(&session.token).await.is_ok());
+        }
+        other => panic!("expected a session, got {:?}", other),
+    }
+
+    // Sign-in attempts outlive their sessions until the settings page stops listing them
+    app.clock.advance(Duration::days(31));
+    let report = maintenance.purge_expired().await.unwrap();
+    assert_eq!(report.sessions, 1);
+    assert_eq!(report.login_attempts, 2);
+}
+
+#[tokio::test]
+async fn avatar_sweep_keeps_referenced_and_foreign_files() {
+    let app = test_app().await;
+    let (user_id, _) = sign_in(&app.state, "a@example.com").await;
+    let dir = TempDir::new().unwrap();
+    let avatars = AvatarService::new(
+        app.pool.clone(),
+        AvatarConfig { dir: dir.path().to_path_buf(), ..Default::default() },
+        app.state.metrics.clone(),
+    );
+
+    let used = format!("{}.png", uuid::Uuid::new_v4());
+    let orphan = format!("{}.png", uuid::Uuid::new_v4());
+    for name in [used.as_str(), orphan.as_str(), "README.txt"] {
+        std::fs::write(dir.path().join(name), b"x").unwrap();
+    }
+    sqlx::query("INSERT INTO frogols (id, user_id, slug, display_name, theme, avatar_url) VALUES ('f1', ?, 'frog', 'Frog', 'default', ?)")
+        .bind(&user_id)
+        .bind(format!("/static/avatars/{}", used))
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    // Too recent to touch
+    let an_hour_ago = SystemTime::now() - std::time::Duration::from_secs(3600);
+    assert_eq!(avatars.delete_orphaned_files(an_hour_ago).await.unwrap(), 0);
+
+    let future = SystemTime::now() + std::time::Duration::from_secs(60);
+    assert_eq!(avatars.delete_orphaned_files(future).await.unwrap(), 1);
+    assert!(dir.path().join(&used).exists());
+    assert!(!dir.path().join(&orphan).exists());
+    assert!(dir.path().join("README.txt").exists());
+}
@@ -0,0 +1,120 @@
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+};
+use tempfile::TempDir;
+
+use super::{multipart_upload, request, send, sign_in, test_app, test_app_with, test_config};
+use crate::{
+    config::{AvatarConfig, Config, MetricsConfig},
+    errors::AppError,
+    state::AppState,
+};
+
+async fn scrape(state: &AppState, token: Option<&str>) -> (StatusCode, String) {
+    let mut req = Request::builder().uri("/metrics");
+    if let Some(token) = token {
+        req = req.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let res = send(state, req.body(Body::empty()).unwrap()).await;
+    let status = res.status();
+    let bytes = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+/// The value of the sample whose name and labels are exactly `series`.
+fn sample(metrics: &str, series: &str) -> Option<f64> {
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .map(|value| value.parse().unwrap())
+}
+
+#[tokio::test]
+async fn requests_are_counted_by_route_template() {
+    let app = test_app().await;
+    let (_, token) = sign_in(&app.state, "a@example.com").await;
+
+    for id in ["one", "two"] {
+        let uri = format!("/api/v1/frogols/{}", id);
+        send(&app.state, request(Method::GET, &uri, Some(&token), "")).await;
+    }
+    send(&app.state, request(Method::GET, "/no/such/page", None, "")).await;
+    for method in ["BREW", "WHEN"] {
+        send(&app.state, request(Method::from_bytes(method.as_bytes()).unwrap(), "/healthz", None, "")).await;
+    }
+
+    let (status, body) = scrape(&app.state, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        sample(&body, r#"frogolio_http_requests_total{method="GET",route="/api/v1/frogols/:id",status="404"}"#),
+        Some(2.0)
+    );
+    assert_eq!(sample(&body, r#"frogolio_http_requests_total{method="GET",route="unmatched",status="404"}"#), Some(1.0));
+    // Made-up methods share one label
+    assert!(!body.contains("BREW") && !body.contains("WHEN"));
+    assert_eq!(
+        sample(&body, r#"frogolio_http_request_duration_seconds_count{method="other",route="/healthz"}"#),
+        Some(2.0)
+    );
+    assert_eq!(
+        sample(&body, r#"frogolio_http_request_duration_seconds_count{method="GET",route="/api/v1/frogols/:id"}"#),
+        Some(2.0)
+    );
+    assert_eq!(sample(&body, "frogolio_db_pool_max_connections"), Some(1.0));
+}
+
+#[tokio::test]
+async fn business_events_are_counted() {
+    let avatars = TempDir::new().unwrap();
+    let app = test_app_with(Config {
+        avatars: AvatarConfig { dir: avatars.path().to_path_buf(), ..Default::default() },
+        ..test_config()
+    })
+    .await;
+    let (user_id, session) = sign_in(&app.state, "a@example.com").await;
+    let frogols = &app.state.services.frogol;
+    let frogol = frogols.create_frogol(&user_id, "counted", "Counted").await.unwrap();
+    let link = frogols.add_link(&frogol.id, "https://example.com", "Example").await.unwrap();
+
+    let res = send(&app.state, request(Method::GET, &format!("/api/links/{}/click", link.id), None, "")).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    let lead_uri = format!("/api/lead/{}", frogol.id);
+    send(&app.state, request(Method::POST, &lead_uri, None, "email=fan%40example.com")).await;
+    // Rejected before reaching the service, so not counted
+    send(&app.state, request(Method::POST, &lead_uri, None, "email=nobody")).await;
+    let res = send(&app.state, multipart_upload(&format!("/api/frogol/{}/avatar", frogol.id), &session, b"png")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let (_, body) = scrape(&app.state, None).await;
+    assert_eq!(sample(&body, "frogolio_frogols_created_total"), Some(1.0));
+    assert_eq!(sample(&body, "frogolio_link_clicks_total"), Some(1.0));
+    assert_eq!(sample(&body, "frogolio_leads_captured_total"), Some(1.0));
+    assert_eq!(sample(&body, "frogolio_avatar_uploads_total"), Some(1.0));
+
+    // The scrape path cannot be claimed as a slug
+    assert!(matches!(frogols.create_frogol(&user_id, "metrics", "Metrics").await, Err(AppError::InvalidInput(_))));
+}
+
+#[tokio::test]
+async fn configured_token_is_required() {
+    let app = test_app_with(Config {
+        metrics: MetricsConfig { bearer_token: Some("scrape-me".to_string()) },
+        ..test_config()
+    })
+    .await;
+
+    assert_eq!(scrape(&app.state, None).await.0, StatusCode::UNAUTHORIZED);
+    assert_eq!(scrape(&app.state, Some("guess")).await.0, StatusCode::UNAUTHORIZED);
+    let (status, body) = scrape(&app.state, Some("scrape-me")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("# TYPE frogolio_http_requests_total counter"));
+
+    // The scheme is read as it is for API tokens
+    let req = Request::builder()
+        .uri("/metrics")
+        .header(header::AUTHORIZATION, "bearer  scrape-me")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(&app.state, req).await.status(), StatusCode::OK);
+}
@@ -0,0 +1,317 @@
+//! Router-level integration tests run against an in-memory SQLite database.
+
+mod api_tokens;
+mod api_v1;
+mod authorization;
+mod bots;
+mod click_series;
+mod client_ip;
+mod config;
+mod csrf;
+mod email_verification;
+mod errors;
+mod health;
+mod logging;
+mod login_throttle;
+mod maintenance;
+mod metrics;
+mod openapi;
+mod password_reset;
+mod security_headers;
+mod sessions;
+mod settings;
+mod two_factor;
+mod user_agents;
+mod visitors;
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header, Method, Request, Response, StatusCode},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde_json::Value;
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+use crate::{
+    build_router,
+    config::{Config, CsrfConfig, DEFAULT_CSRF_EXEMPT_ROUTES},
+    middleware::csrf::token_for,
+    services::{
+        api_token_service::ApiScope,
+        auth_service::{AuthConfig, ClientInfo, EmailVerificationPolicy, LoginOutcome},
+        clock::Clock,
+        jwt_keys::JwtKeyring,
+        mailer::FileMailer,
+    },
+    state::AppState,
+};
+
+pub const TEST_PASSWORD: &str = "password123";
+pub const FIREFOX: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:131.0) Gecko/20100101 Firefox/131.0";
+const TEST_CSRF_SEED: &str = "test-csrf-seed";
+
+/// Clock that only moves when a test advances it.
+pub struct ManualClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl ManualClock {
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
+
+pub struct TestApp {
+    pub state: AppState,
+    pub pool: SqlitePool,
+    pub outbox: TempDir,
+    pub clock: Arc<ManualClock>,
+}
+
+impl TestApp {
+    /// Every message the file mailer has written, oldest first.
+    pub fn sent_emails(&self) -> Vec<String> {
+        let mut paths: Vec<_> = std::fs::read_dir(self.outbox.path())
+            .map(|dir| dir.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+            .unwrap_or_default();
+        paths.sort();
+        paths
+            .into_iter()
+            .map(|p| std::fs::read_to_string(p).expect("readable email"))
+            .collect()
+    }
+
+    /// Every message once the outbox holds `count`, for mail sent from a
+    /// background task. Panics if that takes more than a few seconds.
+    pub async fn wait_for_emails(&self, count: usize) -> Vec<String> {
+        for _ in 0..300 {
+            let emails = self.sent_emails();
+            if emails.len() >= count {
+                return emails;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("expected {} emails, found {}", count, self.sent_emails().len());
+    }
+
+    /// The token from the `/verify-email/` link in the newest email.
+    pub fn verification_token(&self) -> String {
+        let email = self.sent_emails().pop().expect("a verification email was sent");
+        let start = email.find("/verify-email/").expect("email contains verification link") + "/verify-email/".len();
+        email[start..].chars().take_while(|c| c.is_ascii_hexdigit()).collect()
+    }
+}
+
+/// Fresh application backed by a migrated in-memory database and a temporary outbox.
+pub async fn test_app() -> TestApp {
+    test_app_with(test_config()).await
+}
+
+/// Like `test_app`, with settings other than `test_config`.
+pub async fn test_app_with(config: Config) -> TestApp {
+    // A single connection keeps every query on the same in-memory database
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .expect("in-memory database should open");
+    sqlx::migrate!("./migrations")
+        .run(&pool)
+        .await
+        .expect("migrations should apply");
+    let outbox = TempDir::new().expect("temporary outbox");
+    let mailer = Arc::new(FileMailer::new(outbox.path().to_path_buf()));
+    let clock = Arc::new(ManualClock { now: Mutex::new(Utc::now()) });
+    let state = AppState::new(pool.clone(), config, mailer, clock.clone());
+    TestApp { state, pool, outbox, clock }
+}
+
+/// Defaults everywhere, with a fixed JWT secret and short session lifetimes.
+pub fn test_config() -> Config {
+    Config {
+        server: Default::default(),
+        database_url: "sqlite::memory:".to_string(),
+        auth: test_auth_config(),
+        mail: Default::default(),
+        avatars: Default::default(),
+        compression: Default::default(),
+        maintenance: Default::default(),
+        metrics: Default::default(),
+        logging: Default::default(),
+        csrf: CsrfConfig {
+            secret: b"test-csrf-secret".to_vec(),
+            exempt_routes: DEFAULT_CSRF_EXEMPT_ROUTES.iter().map(|r| r.to_string()).collect(),
+            exempt_bearer_tokens: true,
+        },
+        security_headers: Default::default(),
+        proxy: Default::default(),
+    }
+}
+
+pub fn test_auth_config() -> AuthConfig {
+    AuthConfig {
+        jwt_keys: JwtKeyring::single("test-secret".to_string()),
+        base_url: "http://frogolio.test".to_string(),
+        verification_policy: EmailVerificationPolicy::RequiredForPublish,
+        session_ttl: Duration::hours(24),
+        remember_me_ttl: Duration::days(30),
+        session_max_age: Duration::days(90),
+    }
+}
+
+/// Registers `email` and signs in, returning `(user_id, auth_token)`.
+pub async fn sign_in(state: &AppState, email: &str) -> (String, String) {
+    let user = state.services.auth.register(email, TEST_PASSWORD).await.expect("register");
+    match state.services.auth.login(email, TEST_PASSWORD, false, &ClientInfo::default()).await.expect("login") {
+        LoginOutcome::Authenticated(session) => (user.id, session.token),
+        other => panic!("expected a session, got {:?}", other),
+    }
+}
+
+/// The `Cookie` header of a browser signed in with `token` (if any), and the
+/// CSRF token its requests must carry.
+pub fn browser_cookies(token: Option<&str>) -> (String, String) {
+    let csrf = token_for(&test_config().csrf.secret, token, TEST_CSRF_SEED);
+    let mut cookie = format!("csrf_seed={}; csrf_token={}", TEST_CSRF_SEED, csrf);
+    if let Some(token) = token {
+        cookie.push_str(&format!("; auth_token={}", token));
+    }
+    (cookie, csrf)
+}
+
+/// A signed-in user with one frogol, its links and an API token.
+pub struct Owner {
+    pub session: String,
+    pub secret: String,
+    pub frogol_id: String,
+    pub link_ids: Vec<String>,
+}
+
+/// Signs in `a@example.com` with a frogol at `slug` holding `links` links,
+/// and mints them a token with `scopes`.
+pub async fn owner_with_links(app: &TestApp, slug: &str, links: usize, scopes: &[ApiScope]) -> Owner {
+    let (user_id, session) = sign_in(&app.state, "a@example.com").await;
+    let frogol = app.state.services.frogol.create_frogol(&user_id, slug, "Frog").await.unwrap();
+    let mut link_ids = Vec::new();
+    for n in 1..=links {
+        let url = format!("https://example.com/{}", n);
+        let link = app.state.services.frogol.add_link(&frogol.id, &url, &format!("Link {}", n)).await.unwrap();
+        link_ids.push(link.id);
+    }
+    let secret = token_with(app, &session, scopes).await;
+    Owner { session, secret, frogol_id: frogol.id, link_ids }
+}
+
+/// Mints an API token with `scopes` for the user signed in as `session`.
+pub async fn token_with(app: &TestApp, session: &str, scopes: &[ApiScope]) -> String {
+    let user = app.state.services.auth.validate_token(session).await.unwrap();
+    app.state.services.api_tokens.create(&user, "test", scopes).await.unwrap().secret
+}
+
+/// Builds a request carrying the auth cookie (if any) and a valid CSRF token.
+pub fn request(method: Method, uri: &str, token: Option<&str>, form: &str) -> Request<Body> {
+    let (cookie, csrf) = browser_cookies(token);
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::COOKIE, cookie)
+        .header("x-csrf-token", csrf)
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(form.to_string()))
+        .expect("valid request")
+}
+
+pub async fn send(state: &AppState, req: Request<Body>) -> Response<Body> {
+    build_router(state.clone())
+        .oneshot(req)
+        .await
+        .expect("router is infallible")
+}
+
+/// A JSON request on the cookie session, with its CSRF token.
+pub fn session_json(method: Method, uri: &str, token: &str, body: Option<Value>) -> Request<Body> {
+    let (cookie, csrf) = browser_cookies(Some(token));
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::COOKIE, cookie)
+        .header("x-csrf-token", csrf)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty))
+        .expect("valid request")
+}
+
+/// A JSON request authenticated by a personal access token alone.
+pub fn bearer_json(method: Method, uri: &str, secret: &str, body: Option<Value>) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", secret))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty))
+        .expect("valid request")
+}
+
+/// A multipart avatar upload of `bytes` as a PNG, signed in with `token`.
+pub fn multipart_upload(uri: &str, token: &str, bytes: &[u8]) -> Request<Body> {
+    let boundary = "frogolio-test-boundary";
+    let mut body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"avatar\"; filename=\"frog.png\"\r\nContent-Type: image/png\r\n\r\n",
+        b = boundary
+    )
+    .into_bytes();
+    body.extend_from_slice(bytes);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    let (cookie, csrf) = browser_cookies(Some(token));
+    Request::builder()
+        .method(Method::POST)
+        .uri(uri)
+        .header(header::COOKIE, cookie)
+        .header("x-csrf-token", csrf)
+        .header(header::CONTENT_TYPE, format!("multipart/form-data; boundary={}", boundary))
+        .body(Body::from(body))
+        .unwrap()
+}
+
+/// GETs `uri` with a bearer token, returning the status and JSON body.
+pub async fn get_json(app: &TestApp, secret: &str, uri: &str) -> (StatusCode, Value) {
+    let res = send(&app.state, bearer_json(Method::GET, uri, secret, None)).await;
+    (res.status(), json_body(res).await)
+}
+
+pub async fn json_body(res: Response<Body>) -> Value {
+    let bytes = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&bytes).expect("JSON body")
+}
+
+/// The response body as text.
+pub async fn body_text(res: Response<Body>) -> String {
+    let bytes = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+/// A `method` request for `link_id`'s click endpoint from `peer` with
+/// `user_agent`, plus any `extra` headers. It must redirect.
+pub async fn click(app: &TestApp, method: Method, link_id: &str, peer: &str, user_agent: &str, extra: &[(&str, &str)]) {
+    let mut req = Request::builder()
+        .method(method)
+        .uri(format!("/api/links/{}/click", link_id))
+        .header(header::USER_AGENT, user_agent);
+    for (name, value) in extra {
+        req = req.header(*name, *value);
+    }
+    let mut req = req.body(Body::empty()).unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::new(peer.parse().unwrap(), 40000)));
+    assert_eq!(send(&app.state, req).await.status(), StatusCode::SEE_OTHER);
+}
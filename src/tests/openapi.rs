@@ -0,0 +1,140 @@
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+};
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+use super::{request, send, test_app, TestApp};
+use crate::build_router;
+
+const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+/// Every path the app's router serves, in axum's `:param` syntax. axum cannot
+/// list a router's routes, but its `Debug` output names each one as
+/// `RouteId(n): "/path"`. Wildcard paths (static files, the fallback) are
+/// left out.
+fn router_paths(app: &TestApp) -> BTreeSet<String> {
+    let debug = format!("{:?}", build_router(app.state.clone()));
+    debug
+        .split("RouteId(")
+        .skip(1)
+        .filter_map(|entry| {
+            let (id, rest) = entry.split_once(')')?;
+            id.parse::<u32>().ok()?;
+            let rest = rest.strip_prefix(": \"")?;
+            Some(rest[..rest.find('"')?].to_string())
+        })
+        .filter(|path| !path.contains('*'))
+        .collect()
+}
+
+/// Every `(method, path)` the app's router serves, with paths in OpenAPI's
+/// `{param}` syntax. Each path is sent an `OPTIONS` request, which no route
+/// handles, so axum answers 405 with the methods it does handle in `Allow`.
+async fn served_routes(app: &TestApp) -> BTreeSet<(String, String)> {
+    let mut routes = BTreeSet::new();
+    for path in router_paths(app) {
+        let segments: Vec<_> = path.split('/').collect();
+        let uri = segments
+            .iter()
+            .map(|segment| if segment.starts_with(':') { "probe" } else { segment })
+            .collect::<Vec<_>>()
+            .join("/");
+        let res = send(&app.state, request(Method::OPTIONS, &uri, None, "")).await;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED, "OPTIONS {}", uri);
+        let allow = res.headers().get(header::ALLOW).and_then(|v| v.to_str().ok()).unwrap_or_default();
+
+        let openapi_path = segments
+            .iter()
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(param) => format!("{{{}}}", param),
+                None => segment.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        for method in allow.split(',').map(|m| m.trim().to_ascii_lowercase()) {
+            if METHODS.contains(&method.as_str()) {
+                routes.insert((method, openapi_path.clone()));
+            }
+        }
+    }
+    routes
+}
+
+async fn served_spec(app: &TestApp) -> Value {
+    let res = send(&app.state, Request::builder().uri("/api/openapi.json").body(Body::empty()).unwrap()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+fn documented_operations(spec: &Value) -> BTreeSet<(String, String)> {
+    spec["paths"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .flat_map(|(path, item)| {
+            item.as_object()
+                .unwrap()
+                .keys()
+                .map(move |method| (method.clone(), path.clone()))
+        })
+        .collect()
+}
+
+/// Every `$ref` in `value`.
+fn refs(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                match (key.as_str(), value) {
+                    ("$ref", Value::String(target)) => out.push(target.clone()),
+                    _ => refs(value, out),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| refs(item, out)),
+        _ => {}
+    }
+}
+
+#[tokio::test]
+async fn spec_is_served_and_self_contained() {
+    let app = test_app().await;
+    let spec = served_spec(&app).await;
+
+    assert_eq!(spec["openapi"], "3.0.3");
+    let schemas = &spec["components"]["schemas"];
+    for name in ["AddLinkForm", "UpdateLinkForm", "LeadCaptureForm", "UpdateFrogolForm", "ErrorBody", "FrogolDto"] {
+        assert!(schemas.get(name).is_some(), "{} missing from components", name);
+    }
+    assert_eq!(schemas["UpdateFrogolForm"]["required"], serde_json::json!(["display_name", "theme"]));
+
+    let mut targets = Vec::new();
+    refs(&spec, &mut targets);
+    assert!(!targets.is_empty());
+    for target in targets {
+        let name = target.strip_prefix("#/components/schemas/").expect("local schema ref");
+        assert!(schemas.get(name).is_some(), "dangling $ref {}", target);
+    }
+}
+
+#[tokio::test]
+async fn document_matches_the_router() {
+    let app = test_app().await;
+    let served = served_routes(&app).await;
+    let documented = documented_operations(&served_spec(&app).await);
+    assert!(served.contains(&("get".to_string(), "/api/v1/frogols/{id}".to_string())));
+    assert!(served.contains(&("get".to_string(), "/dashboard".to_string())));
+
+    let undocumented: Vec<_> = served
+        .iter()
+        .filter(|(_, path)| path.starts_with("/api/"))
+        .filter(|route| !documented.contains(*route))
+        .collect();
+    assert!(undocumented.is_empty(), "routes missing from the OpenAPI document: {:?}", undocumented);
+
+    let stale: Vec<_> = documented.difference(&served).collect();
+    assert!(stale.is_empty(), "documented routes that are not served: {:?}", stale);
+}
@@ -0,0 +1,85 @@
+use axum::http::{header, Method, StatusCode};
+
+use super::{body_text, request, send, sign_in, test_app, TestApp, TEST_PASSWORD};
+use crate::services::auth_service::ClientInfo;
+
+/// Pulls the reset token out of the most recent email in the outbox.
+fn reset_token_from_outbox(app: &TestApp) -> String {
+    let email = app.sent_emails().pop().expect("a reset email was sent");
+    let start = email.find("/reset-password/").expect("email contains reset link") + "/reset-password/".len();
+    email[start..].chars().take_while(|c| c.is_ascii_hexdigit()).collect()
+}
+
+/// Asks for a reset through the form and waits for the email it sends.
+async fn request_reset(app: &TestApp, email: &str) -> Vec<String> {
+    let sent_before = app.sent_emails().len();
+    let res = send(&app.state, request(Method::POST, "/forgot-password", None, &format!("email={}", email))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    app.wait_for_emails(sent_before + 1).await
+}
+
+#[tokio::test]
+async fn reset_changes_password_and_revokes_sessions() {
+    let app = test_app().await;
+    let (_, old_token) = sign_in(&app.state, "a@example.com").await;
+    let sent_before = app.sent_emails().len();
+
+    let emails = request_reset(&app, "a@example.com").await;
+    assert_eq!(emails.len(), sent_before + 1);
+    let reset_email = emails.last().unwrap();
+    assert!(reset_email.starts_with("To: a@example.com\n"));
+    assert!(reset_email.contains("http://frogolio.test/reset-password/"));
+
+    let token = reset_token_from_outbox(&app);
+    let res = send(
+        &app.state,
+        request(Method::POST, &format!("/reset-password/{}", token), None, "password=new-secret&password_confirm=new-secret"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers()[header::LOCATION], "/login");
+
+    assert!(app.state.services.auth.validate_token(&old_token).await.is_err());
+    assert!(app.state.services.auth.login("a@example.com", TEST_PASSWORD, false, &ClientInfo::default()).await.is_err());
+    assert!(app.state.services.auth.login("a@example.com", "new-secret", false, &ClientInfo::default()).await.is_ok());
+}
+
+#[tokio::test]
+async fn reset_token_is_single_use() {
+    let app = test_app().await;
+    sign_in(&app.state, "a@example.com").await;
+    request_reset(&app, "a@example.com").await;
+    let token = reset_token_from_outbox(&app);
+
+    app.state.services.auth.reset_password(&token, "first-secret").await.unwrap();
+    let err = app.state.services.auth.reset_password(&token, "second-secret").await;
+    assert!(err.is_err());
+    assert!(app.state.services.auth.login("a@example.com", "first-secret", false, &ClientInfo::default()).await.is_ok());
+}
+
+#[tokio::test]
+async fn expired_reset_token_is_rejected() {
+    let app = test_app().await;
+    sign_in(&app.state, "a@example.com").await;
+    request_reset(&app, "a@example.com").await;
+    let token = reset_token_from_outbox(&app);
+
+    sqlx::query("UPDATE password_reset_tokens SET expires_at = '2000-01-01T00:00:00+00:00'")
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    assert!(app.state.services.auth.reset_password(&token, "new-secret").await.is_err());
+    assert!(app.state.services.auth.login("a@example.com", TEST_PASSWORD, false, &ClientInfo::default()).await.is_ok());
+}
+
+#[tokio::test]
+async fn unknown_email_gets_same_response_and_no_mail() {
+    let app = test_app().await;
+    let res = send(&app.state, request(Method::POST, "/forgot-password", None, "email=nobody@example.com")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(body_text(res).await.contains("If an account exists"));
+
+    app.state.services.auth.spawn_password_reset("nobody@example.com".to_string()).await.unwrap();
+    assert!(app.sent_emails().is_empty());
+}
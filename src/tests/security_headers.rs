@@ -0,0 +1,100 @@
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, Method, Request, StatusCode},
+};
+
+use super::{body_text, request, send, sign_in, test_app, test_app_with, test_auth_config, test_config};
+use crate::{
+    config::{Config, SecurityHeadersConfig},
+    services::auth_service::AuthConfig,
+};
+
+fn page(uri: &str) -> Request<Body> {
+    Request::builder()
+        .uri(uri)
+        .header(header::ACCEPT, "text/html")
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn csp(headers: &HeaderMap) -> &str {
+    headers[header::CONTENT_SECURITY_POLICY].to_str().unwrap()
+}
+
+/// The nonce the policy allows, from its `'nonce-…'` source.
+fn allowed_nonce(headers: &HeaderMap) -> String {
+    let start = csp(headers).find("'nonce-").expect("the policy has a nonce") + "'nonce-".len();
+    csp(headers)[start..].split('\'').next().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn inline_scripts_carry_the_requests_nonce() {
+    let app = test_app().await;
+    let (user_id, _) = sign_in(&app.state, "a@example.com").await;
+    app.state.services.frogol.create_frogol(&user_id, "noncey", "<script>alert(1)</script>").await.unwrap();
+
+    let res = send(&app.state, page("/noncey")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let nonce = allowed_nonce(res.headers());
+    let body = body_text(res).await;
+    // Every inline script, the theme snippet included, may run; the owner's markup is escaped
+    assert_eq!(body.matches("<script>").count(), 0);
+    assert_eq!(body.matches(&format!(r#"<script nonce="{}">"#, nonce)).count(), 2);
+    assert!(body.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+
+    let again = send(&app.state, page("/noncey")).await;
+    assert_ne!(allowed_nonce(again.headers()), nonce);
+
+    // Error pages extend the same layout
+    let res = send(&app.state, page("/nobody-here")).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let nonce = allowed_nonce(res.headers());
+    assert!(body_text(res).await.contains(&format!(r#"<script nonce="{}">"#, nonce)));
+}
+
+#[tokio::test]
+async fn public_pages_and_the_dashboard_get_their_own_policies() {
+    let app = test_app().await;
+    let (user_id, token) = sign_in(&app.state, "a@example.com").await;
+    app.state.services.frogol.create_frogol(&user_id, "framed", "Framed").await.unwrap();
+
+    let public = send(&app.state, page("/framed")).await;
+    let headers = public.headers();
+    assert!(csp(headers).ends_with("frame-ancestors 'self'"), "{}", csp(headers));
+    assert_eq!(headers[header::X_FRAME_OPTIONS], "SAMEORIGIN");
+    assert_eq!(headers[header::REFERRER_POLICY], "strict-origin-when-cross-origin");
+
+    let dashboard = send(&app.state, request(Method::GET, "/dashboard", Some(&token), "")).await;
+    let headers = dashboard.headers();
+    assert!(csp(headers).ends_with("frame-ancestors 'none'"), "{}", csp(headers));
+    assert_eq!(headers[header::X_FRAME_OPTIONS], "DENY");
+    assert_eq!(headers[header::REFERRER_POLICY], "same-origin");
+
+    for headers in [public.headers(), dashboard.headers()] {
+        assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert!(headers.contains_key("permissions-policy"));
+        // Served over plain http here
+        assert!(!headers.contains_key(header::STRICT_TRANSPORT_SECURITY));
+    }
+}
+
+#[tokio::test]
+async fn policies_follow_the_config() {
+    let mut security_headers = SecurityHeadersConfig { hsts_max_age_secs: 600, ..Default::default() };
+    security_headers.public.frame_ancestors = vec!["https://embed.example".to_string()];
+    let app = test_app_with(Config {
+        auth: AuthConfig { base_url: "https://frogolio.test".to_string(), ..test_auth_config() },
+        security_headers,
+        ..test_config()
+    })
+    .await;
+    let (user_id, _) = sign_in(&app.state, "a@example.com").await;
+    app.state.services.frogol.create_frogol(&user_id, "embedded", "Embedded").await.unwrap();
+
+    let res = send(&app.state, page("/embedded")).await;
+    let headers = res.headers();
+    assert!(csp(headers).ends_with("frame-ancestors https://embed.example"), "{}", csp(headers));
+    // X-Frame-Options cannot name another site, so it is left out
+    assert!(!headers.contains_key(header::X_FRAME_OPTIONS));
+    assert_eq!(headers[header::STRICT_TRANSPORT_SECURITY], "max-age=600");
+}
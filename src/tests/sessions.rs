@@ -0,0 +1,140 @@
+use axum::http::{header, Method, StatusCode};
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+
+use super::{request, send, sign_in, test_app, test_auth_config, test_config, TestApp, TEST_PASSWORD};
+use crate::{
+    config::Config,
+    services::{
+        auth_service::{AuthConfig, ClientInfo, LoginOutcome},
+        clock::Clock,
+        jwt_keys::JwtKeyring,
+        mailer::FileMailer,
+    },
+    state::AppState,
+};
+
+async fn expires_at(app: &TestApp, token: &str) -> DateTime<Utc> {
+    let (expires_at,): (String,) = sqlx::query_as("SELECT expires_at FROM sessions WHERE token = ?")
+        .bind(token)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    DateTime::parse_from_rfc3339(&expires_at).unwrap().with_timezone(&Utc)
+}
+
+async fn remembered_sign_in(app: &TestApp) -> String {
+    let client = ClientInfo {
+        ip_address: Some("198.51.100.4".to_string()),
+        user_agent: Some("Mozilla/5.0 (X11; Linux x86_64) Firefox/128.0".to_string()),
+    };
+    match app.state.services.auth.login("a@example.com", TEST_PASSWORD, true, &client).await.unwrap() {
+        LoginOutcome::Authenticated(session) => session.token,
+        other => panic!("expected a session, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn sessions_slide_while_in_use() {
+    let app = test_app().await;
+    let (_, token) = sign_in(&app.state, "a@example.com").await;
+    let auth = &app.state.services.auth;
+    assert_eq!(expires_at(&app, &token).await, app.clock.now() + Duration::hours(24));
+
+    // More than half the lifetime left: unchanged
+    app.clock.advance(Duration::hours(6));
+    auth.validate_token(&token).await.unwrap();
+    assert_eq!(expires_at(&app, &token).await, app.clock.now() + Duration::hours(18));
+
+    // Less than half left: pushed out to a full lifetime again
+    app.clock.advance(Duration::hours(8));
+    auth.validate_token(&token).await.unwrap();
+    assert_eq!(expires_at(&app, &token).await, app.clock.now() + Duration::hours(24));
+
+    // Idle for longer than the lifetime
+    app.clock.advance(Duration::hours(25));
+    assert!(auth.validate_token(&token).await.is_err());
+}
+
+#[tokio::test]
+async fn remembered_sessions_last_longer_but_not_forever() {
+    let app = test_app().await;
+    sign_in(&app.state, "a@example.com").await;
+    let token = remembered_sign_in(&app).await;
+    let auth = &app.state.services.auth;
+    assert_eq!(expires_at(&app, &token).await, app.clock.now() + Duration::days(30));
+
+    // Renewed every few weeks, it still ends at the absolute limit
+    for _ in 0..4 {
+        app.clock.advance(Duration::days(20));
+        auth.validate_token(&token).await.unwrap();
+    }
+    app.clock.advance(Duration::days(11));
+    assert!(auth.validate_token(&token).await.is_err());
+}
+
+#[tokio::test]
+async fn login_form_remember_me_sets_a_persistent_cookie() {
+    let app = test_app().await;
+    sign_in(&app.state, "a@example.com").await;
+
+    let form = format!("email=a%40example.com&password={}", TEST_PASSWORD);
+    let res = send(&app.state, request(Method::POST, "/login", None, &form)).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    let cookie = res.headers()[header::SET_COOKIE].to_str().unwrap();
+    assert!(cookie.starts_with("auth_token="));
+    assert!(!cookie.contains("Max-Age"));
+
+    let res = send(&app.state, request(Method::POST, "/login", None, &format!("{}&remember_me=on", form))).await;
+    let cookie = res.headers()[header::SET_COOKIE].to_str().unwrap();
+    assert!(cookie.contains(&format!("Max-Age={}", Duration::days(90).num_seconds())));
+}
+
+#[tokio::test]
+async fn settings_lists_session_details() {
+    let app = test_app().await;
+    sign_in(&app.state, "a@example.com").await;
+    let token = remembered_sign_in(&app).await;
+
+    let res = send(&app.state, request(Method::GET, "/dashboard/settings", Some(&token), "")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body.contains("Firefox/128.0"));
+    assert!(body.contains("198.51.100.4"));
+    assert!(body.contains("Remembered until"));
+}
+
+/// The same database and clock as `app`, with a different set of JWT keys.
+fn with_jwt_keys(app: &TestApp, spec: &str, active: &str) -> AppState {
+    let config = Config {
+        auth: AuthConfig {
+            jwt_keys: JwtKeyring::parse(spec, active).unwrap(),
+            ..test_auth_config()
+        },
+        ..test_config()
+    };
+    let mailer = Arc::new(FileMailer::new(app.outbox.path().to_path_buf()));
+    AppState::new(app.pool.clone(), config, mailer, app.clock.clone())
+}
+
+#[tokio::test]
+async fn sessions_survive_key_rotation_until_the_key_is_removed() {
+    let app = test_app().await;
+    // Signed by the lone test secret, under the legacy key id
+    let (_, old_token) = sign_in(&app.state, "a@example.com").await;
+
+    let rotated = with_jwt_keys(&app, "default:test-secret,next:new-secret", "next");
+    assert!(rotated.services.auth.validate_token(&old_token).await.is_ok());
+    let new_token = match rotated.services.auth.login("a@example.com", TEST_PASSWORD, false, &ClientInfo::default()).await.unwrap() {
+        LoginOutcome::Authenticated(session) => session.token,
+        other => panic!("expected a session, got {:?}", other),
+    };
+    assert!(rotated.services.auth.validate_token(&new_token).await.is_ok());
+    // Servers still on the old keyring cannot verify the new key yet
+    assert!(app.state.services.auth.validate_token(&new_token).await.is_err());
+
+    let retired = with_jwt_keys(&app, "next:new-secret", "next");
+    assert!(retired.services.auth.validate_token(&old_token).await.is_err());
+    assert!(retired.services.auth.validate_token(&new_token).await.is_ok());
+}
@@ -0,0 +1,134 @@
+use axum::http::{header, Method, StatusCode};
+use tempfile::TempDir;
+
+use super::{body_text, click, request, send, sign_in, test_app, test_app_with, test_config, TEST_PASSWORD};
+use crate::{
+    config::{AvatarConfig, Config},
+    services::auth_service::{ClientInfo, LoginOutcome},
+};
+
+async fn count(pool: &sqlx::SqlitePool, table: &str) -> i64 {
+    let (n,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {}", table)).fetch_one(pool).await.unwrap();
+    n
+}
+
+#[tokio::test]
+async fn wrong_current_password_is_rejected() {
+    let app = test_app().await;
+    let (user_id, token) = sign_in(&app.state, "a@example.com").await;
+
+    let cases = [
+        ("/dashboard/settings/email", "new_email=b%40example.com&current_password=wrong-password"),
+        (
+            "/dashboard/settings/password",
+            "current_password=wrong-password&new_password=new-secret&new_password_confirm=new-secret",
+        ),
+        ("/dashboard/settings/delete", "current_password=wrong-password"),
+    ];
+    for (uri, form) in cases {
+        let res = send(&app.state, request(Method::POST, uri, Some(&token), form)).await;
+        assert_eq!(res.status(), StatusCode::OK, "{}", uri);
+        assert!(body_text(res).await.contains("Current password is incorrect"), "{}", uri);
+    }
+
+    let user = app.state.services.auth.validate_token(&token).await.unwrap();
+    assert_eq!((user.id, user.email), (user_id, "a@example.com".to_string()));
+    assert!(app.state.services.auth.login("a@example.com", TEST_PASSWORD, false, &ClientInfo::default()).await.is_ok());
+}
+
+#[tokio::test]
+async fn password_change_signs_out_other_sessions() {
+    let app = test_app().await;
+    let (_, token) = sign_in(&app.state, "a@example.com").await;
+    let auth = &app.state.services.auth;
+    let other = match auth.login("a@example.com", TEST_PASSWORD, false, &ClientInfo::default()).await.unwrap() {
+        LoginOutcome::Authenticated(session) => session.token,
+        other => panic!("expected a session, got {:?}", other),
+    };
+
+    let form = "current_password=password123&new_password=new-secret&new_password_confirm=new-secret";
+    let res = send(&app.state, request(Method::POST, "/dashboard/settings/password", Some(&token), form)).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers()[header::LOCATION], "/dashboard/settings?updated=password");
+
+    assert!(auth.validate_token(&token).await.is_ok());
+    assert!(auth.validate_token(&other).await.is_err());
+    assert!(auth.login("a@example.com", TEST_PASSWORD, false, &ClientInfo::default()).await.is_err());
+    assert!(auth.login("a@example.com", "new-secret", false, &ClientInfo::default()).await.is_ok());
+}
+
+#[tokio::test]
+async fn revoking_the_current_session_clears_the_cookie() {
+    let app = test_app().await;
+    let (user_id, token) = sign_in(&app.state, "a@example.com").await;
+    let auth = &app.state.services.auth;
+    let session = auth.list_sessions(&user_id).await.unwrap().into_iter().find(|s| s.token == token).unwrap();
+
+    let uri = format!("/dashboard/settings/sessions/{}/revoke", session.id);
+    let res = send(&app.state, request(Method::POST, &uri, Some(&token), "")).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers()[header::LOCATION], "/login");
+    let cookie = res.headers()[header::SET_COOKIE].to_str().unwrap();
+    assert!(cookie.starts_with("auth_token=") && cookie.contains("Max-Age=0"), "{}", cookie);
+    assert!(auth.validate_token(&token).await.is_err());
+}
+
+#[tokio::test]
+async fn account_deletion_removes_everything_the_user_owns() {
+    let dir = TempDir::new().unwrap();
+    let app = test_app_with(Config {
+        avatars: AvatarConfig { dir: dir.path().to_path_buf(), ..Default::default() },
+        ..test_config()
+    })
+    .await;
+    let state = &app.state;
+    let (owner_id, token) = sign_in(state, "a@example.com").await;
+    let (bystander_id, _) = sign_in(state, "b@example.com").await;
+
+    let frogol = state.services.frogol.create_frogol(&owner_id, "alice", "Alice").await.unwrap();
+    let link = state.services.frogol.add_link(&frogol.id, "https://example.com", "Site").await.unwrap();
+    state.services.lead.capture_lead(&frogol.id, "fan@example.com", Some("direct"), None, None).await.unwrap();
+    click(&app, Method::GET, &link.id, "203.0.113.7", "Firefox", &[]).await;
+    let avatar = format!("{}.png", uuid::Uuid::new_v4());
+    std::fs::write(dir.path().join(&avatar), b"png").unwrap();
+    let avatar_url = format!("/static/avatars/{}", avatar);
+    state.services.frogol.update_frogol_avatar_url(&frogol.id, &avatar_url).await.unwrap();
+    // Someone else's frogol survives
+    state.services.frogol.create_frogol(&bystander_id, "bob", "Bob").await.unwrap();
+
+    let res = send(state, request(Method::POST, "/dashboard/settings/delete", Some(&token), "current_password=password123")).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers()[header::LOCATION], "/register");
+    assert!(res.headers()[header::SET_COOKIE].to_str().unwrap().contains("Max-Age=0"));
+
+    for table in ["links", "clicks", "click_daily_rollups", "leads"] {
+        assert_eq!(count(&app.pool, table).await, 0, "{}", table);
+    }
+    for table in ["users", "frogols", "sessions"] {
+        assert_eq!(count(&app.pool, table).await, 1, "{}", table);
+    }
+    assert!(!dir.path().join(&avatar).exists());
+    assert!(state.services.auth.validate_token(&token).await.is_err());
+}
+
+#[tokio::test]
+async fn email_change_waits_for_the_new_address_to_be_verified() {
+    let app = test_app().await;
+    let (_, token) = sign_in(&app.state, "a@example.com").await;
+    let auth = &app.state.services.auth;
+
+    let form = "new_email=new%40example.com&current_password=password123";
+    let res = send(&app.state, request(Method::POST, "/dashboard/settings/email", Some(&token), form)).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers()[header::LOCATION], "/dashboard/settings?updated=email");
+
+    assert!(app.sent_emails().last().unwrap().starts_with("To: new@example.com\n"));
+    assert_eq!(auth.validate_token(&token).await.unwrap().email, "a@example.com");
+    assert!(auth.login("new@example.com", TEST_PASSWORD, false, &ClientInfo::default()).await.is_err());
+
+    let res = send(&app.state, request(Method::GET, &format!("/verify-email/{}", app.verification_token()), None, "")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let user = auth.validate_token(&token).await.unwrap();
+    assert_eq!(user.email, "new@example.com");
+    assert!(user.is_email_verified());
+}
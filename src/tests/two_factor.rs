@@ -0,0 +1,199 @@
+use axum::http::{header, HeaderValue, Method, StatusCode};
+use chrono::Duration;
+
+use super::{browser_cookies, request, send, sign_in, test_app, TestApp, TEST_PASSWORD};
+use crate::{
+    errors::AppError,
+    repo::user_repo::User,
+    services::{
+        auth_service::{ClientInfo, LoginOutcome},
+        clock::Clock,
+        totp,
+    },
+};
+
+struct Enrolled {
+    app: TestApp,
+    user: User,
+    secret: Vec<u8>,
+    recovery_codes: Vec<String>,
+}
+
+/// Signs up a user and turns on two-factor for them.
+async fn enrolled() -> Enrolled {
+    let app = test_app().await;
+    let (_, token) = sign_in(&app.state, "a@example.com").await;
+    let auth = &app.state.services.auth;
+    let user = auth.validate_token(&token).await.unwrap();
+
+    let enrollment = auth.begin_totp_enrollment(&user).await.unwrap();
+    assert!(enrollment.otpauth_uri.starts_with("otpauth://totp/Frogolio:a%40example.com?secret="));
+    let secret = totp::decode_secret(&enrollment.secret).unwrap();
+
+    let code = current_code(&app, &secret);
+    let recovery_codes = auth.confirm_totp_enrollment(&user, &code).await.unwrap();
+    assert_eq!(recovery_codes.len(), 10);
+
+    // Step past the code used for confirmation
+    app.clock.advance(Duration::seconds(totp::STEP_SECS));
+    Enrolled { app, user, secret, recovery_codes }
+}
+
+fn current_code(app: &TestApp, secret: &[u8]) -> String {
+    totp::code_at(secret, app.clock.now().timestamp(), totp::DIGITS)
+}
+
+async fn challenge(app: &TestApp) -> String {
+    match app.state.services.auth.login("a@example.com", TEST_PASSWORD, false, &ClientInfo::default()).await.unwrap() {
+        LoginOutcome::TwoFactorRequired { challenge } => challenge,
+        other => panic!("expected a two-factor challenge, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn enrollment_needs_a_valid_code() {
+    let app = test_app().await;
+    let (_, token) = sign_in(&app.state, "a@example.com").await;
+    let auth = &app.state.services.auth;
+    let user = auth.validate_token(&token).await.unwrap();
+
+    auth.begin_totp_enrollment(&user).await.unwrap();
+    assert!(auth.confirm_totp_enrollment(&user, "000000").await.is_err());
+
+    let status = auth.two_factor_status(&user).await.unwrap();
+    assert!(!status.enabled);
+    assert!(status.pending.is_some());
+    assert!(matches!(
+        auth.login("a@example.com", TEST_PASSWORD, false, &ClientInfo::default()).await.unwrap(),
+        LoginOutcome::Authenticated(_)
+    ));
+}
+
+#[tokio::test]
+async fn login_requires_second_factor_and_rejects_replay() {
+    let e = enrolled().await;
+    let auth = &e.app.state.services.auth;
+
+    let challenge = challenge(&e.app).await;
+    assert!(matches!(
+        auth.complete_two_factor_login(&challenge, "not-a-code", &ClientInfo::default()).await,
+        Err(AppError::Unauthorized(_))
+    ));
+
+    let code = current_code(&e.app, &e.secret);
+    let session = auth.complete_two_factor_login(&challenge, &code, &ClientInfo::default()).await.unwrap();
+    assert_eq!(auth.validate_token(&session.token).await.unwrap().id, e.user.id);
+
+    // Challenge is spent, and the same code cannot open a second one
+    assert!(matches!(
+        auth.complete_two_factor_login(&challenge, &code, &ClientInfo::default()).await,
+        Err(AppError::Unauthorized(_))
+    ));
+    let second = self::challenge(&e.app).await;
+    assert!(auth.complete_two_factor_login(&second, &code, &ClientInfo::default()).await.is_err());
+}
+
+#[tokio::test]
+async fn recovery_codes_work_once() {
+    let e = enrolled().await;
+    let auth = &e.app.state.services.auth;
+    let recovery_code = e.recovery_codes[0].to_uppercase();
+
+    let first = challenge(&e.app).await;
+    assert!(auth.complete_two_factor_login(&first, &recovery_code, &ClientInfo::default()).await.is_ok());
+
+    let second = challenge(&e.app).await;
+    assert!(auth.complete_two_factor_login(&second, &recovery_code, &ClientInfo::default()).await.is_err());
+    assert_eq!(auth.two_factor_status(&e.user).await.unwrap().recovery_codes_left, 9);
+
+    let fresh = auth.regenerate_recovery_codes(&e.user, TEST_PASSWORD).await.unwrap();
+    assert!(auth.complete_two_factor_login(&second, &e.recovery_codes[1], &ClientInfo::default()).await.is_err());
+    assert!(auth.complete_two_factor_login(&second, &fresh[0], &ClientInfo::default()).await.is_ok());
+}
+
+#[tokio::test]
+async fn challenge_expires_after_repeated_wrong_codes() {
+    let e = enrolled().await;
+    let auth = &e.app.state.services.auth;
+
+    let challenge = challenge(&e.app).await;
+    for _ in 0..5 {
+        assert!(matches!(
+            auth.complete_two_factor_login(&challenge, "000000", &ClientInfo::default()).await,
+            Err(AppError::Unauthorized(_))
+        ));
+    }
+    let code = current_code(&e.app, &e.secret);
+    assert!(matches!(
+        auth.complete_two_factor_login(&challenge, &code, &ClientInfo::default()).await,
+        Err(AppError::Unauthorized(_))
+    ));
+}
+
+#[tokio::test]
+async fn wrong_codes_lock_the_account_across_challenges() {
+    let e = enrolled().await;
+    let auth = &e.app.state.services.auth;
+
+    // A fresh challenge after each pair of guesses must not reset the count
+    for _ in 0..2 {
+        let challenge = challenge(&e.app).await;
+        for _ in 0..2 {
+            assert!(matches!(
+                auth.complete_two_factor_login(&challenge, "000000", &ClientInfo::default()).await,
+                Err(AppError::Unauthorized(_))
+            ));
+        }
+    }
+    let last = challenge(&e.app).await;
+    assert!(auth.complete_two_factor_login(&last, "000000", &ClientInfo::default()).await.is_err());
+
+    let code = current_code(&e.app, &e.secret);
+    assert!(matches!(
+        auth.complete_two_factor_login(&last, &code, &ClientInfo::default()).await,
+        Err(AppError::RateLimited { .. })
+    ));
+    let res = send(&e.app.state, request(Method::POST, "/login", None, "email=a@example.com&password=password123")).await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn disabling_needs_password_and_code() {
+    let e = enrolled().await;
+    let auth = &e.app.state.services.auth;
+    let code = current_code(&e.app, &e.secret);
+
+    assert!(auth.disable_totp(&e.user, "wrong-password", &code).await.is_err());
+    assert!(auth.disable_totp(&e.user, TEST_PASSWORD, "000000").await.is_err());
+    auth.disable_totp(&e.user, TEST_PASSWORD, &code).await.unwrap();
+
+    assert!(matches!(
+        auth.login("a@example.com", TEST_PASSWORD, false, &ClientInfo::default()).await.unwrap(),
+        LoginOutcome::Authenticated(_)
+    ));
+}
+
+#[tokio::test]
+async fn login_form_redirects_through_second_step() {
+    let e = enrolled().await;
+
+    let res = send(&e.app.state, request(Method::POST, "/login", None, "email=a@example.com&password=password123")).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers()[header::LOCATION], "/login/2fa");
+    let set_cookie = res.headers()[header::SET_COOKIE].to_str().unwrap();
+    assert!(set_cookie.starts_with("login_challenge="));
+    let challenge_cookie = set_cookie.split(';').next().unwrap().to_string();
+
+    let code = current_code(&e.app, &e.secret);
+    let mut req = request(Method::POST, "/login/2fa", None, &format!("code={}", code));
+    let cookie = format!("{}; {}", browser_cookies(None).0, challenge_cookie);
+    req.headers_mut().insert(header::COOKIE, HeaderValue::from_str(&cookie).unwrap());
+    let res = send(&e.app.state, req).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers()[header::LOCATION], "/dashboard");
+    assert!(res
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .any(|c| c.to_str().unwrap().starts_with("auth_token=")));
+}
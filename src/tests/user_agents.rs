@@ -0,0 +1,80 @@
+use axum::http::{Method, StatusCode};
+
+use super::{body_text, click, request, send, sign_in, test_app};
+use crate::{
+    repo::click_repo::ClickScope,
+    services::user_agent::{classify, DeviceClass},
+};
+
+const IPHONE: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.5 Mobile/15E148 Safari/604.1";
+const IPAD: &str = "Mozilla/5.0 (iPad; CPU OS 17_5 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) CriOS/126.0.6478.54 Mobile/15E148 Safari/604.1";
+const ANDROID_PHONE: &str = "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Mobile Safari/537.36";
+const ANDROID_TABLET: &str = "Mozilla/5.0 (Linux; Android 13; SM-X700) AppleWebKit/537.36 (KHTML, like Gecko) SamsungBrowser/25.0 Chrome/121.0.0.0 Safari/537.36";
+const WINDOWS_CHROME: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36";
+const WINDOWS_EDGE: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 Edg/126.0.2592.87";
+const MAC_SAFARI: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.5 Safari/605.1.15";
+const GOOGLEBOT: &str = "Mozilla/5.0 (Linux; Android 6.0.1; Nexus 5X Build/MMB29P) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.6478.126 Mobile Safari/537.36 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+
+#[test]
+fn agents_are_sorted_into_devices_browsers_and_systems() {
+    for (ua, device, browser, os) in [
+        (IPHONE, DeviceClass::Mobile, "Safari", "iOS"),
+        (IPAD, DeviceClass::Tablet, "Chrome", "iOS"),
+        (ANDROID_PHONE, DeviceClass::Mobile, "Chrome", "Android"),
+        (ANDROID_TABLET, DeviceClass::Tablet, "Samsung Internet", "Android"),
+        (WINDOWS_CHROME, DeviceClass::Desktop, "Chrome", "Windows"),
+        (WINDOWS_EDGE, DeviceClass::Desktop, "Edge", "Windows"),
+        (MAC_SAFARI, DeviceClass::Desktop, "Safari", "macOS"),
+        (GOOGLEBOT, DeviceClass::Bot, "Chrome", "Android"),
+        ("curl/8.5.0", DeviceClass::Bot, "Other", "Other"),
+    ] {
+        let info = classify(Some(ua));
+        assert_eq!((info.device, info.browser, info.os), (device, browser, os), "{}", ua);
+    }
+    assert!(classify(Some(GOOGLEBOT)).is_bot());
+    assert_eq!(classify(None).device, DeviceClass::Unknown);
+    assert_eq!(classify(Some("  ")).device, DeviceClass::Unknown);
+}
+
+#[tokio::test]
+async fn bot_clicks_are_kept_but_not_counted() {
+    let app = test_app().await;
+    let (user_id, session) = sign_in(&app.state, "a@example.com").await;
+    let frogol = app.state.services.frogol.create_frogol(&user_id, "devices", "Devices").await.unwrap();
+    let link = app.state.services.frogol.add_link(&frogol.id, "https://example.com", "Example").await.unwrap();
+
+    for ua in [IPHONE, IPHONE, ANDROID_TABLET, WINDOWS_CHROME, GOOGLEBOT] {
+        click(&app, Method::GET, &link.id, "203.0.113.7", ua, &[]).await;
+    }
+
+    let stored: Vec<(String, String, String, bool)> =
+        sqlx::query_as("SELECT device_class, browser, os, is_bot FROM clicks WHERE device_class = 'bot'")
+            .fetch_all(&app.pool)
+            .await
+            .unwrap();
+    assert_eq!(stored, [("bot".to_string(), "Chrome".to_string(), "Android".to_string(), true)]);
+
+    let stats = app.state.services.frogol.get_click_stats(&frogol.id, false).await.unwrap();
+    assert_eq!(stats.total_clicks, 4);
+    let rollup: i64 = sqlx::query_scalar("SELECT clicks FROM click_daily_rollups").fetch_one(&app.pool).await.unwrap();
+    assert_eq!(rollup, 4);
+
+    let breakdown = app.state.services.frogol.get_click_breakdown(ClickScope::Frogol(&frogol.id), None, false).await.unwrap();
+    assert_eq!(breakdown.bot_clicks, 1);
+    assert_eq!(breakdown.devices, [("mobile".to_string(), 2), ("desktop".to_string(), 1), ("tablet".to_string(), 1)]);
+    assert_eq!(breakdown.operating_systems[0], ("iOS".to_string(), 2));
+    let breakdown = app.state.services.frogol.get_click_breakdown(ClickScope::Frogol(&frogol.id), None, true).await.unwrap();
+    assert_eq!(breakdown.bot_clicks, 1);
+    assert_eq!(breakdown.devices[1], ("bot".to_string(), 1));
+
+    let res = send(&app.state, request(Method::GET, &format!("/dashboard/frogol/{}", frogol.id), Some(&session), "")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let page = body_text(res).await;
+    assert!(page.contains("4 in range, UTC"));
+    assert!(page.contains("Samsung Internet"));
+    assert!(page.contains("1 bot clicks in range were not counted."));
+
+    let res = send(&app.state, request(Method::GET, &format!("/dashboard/frogol/{}?include_bots=true", frogol.id), Some(&session), "")).await;
+    let page = body_text(res).await;
+    assert!(page.contains("1 bot clicks in range are counted above."));
+}
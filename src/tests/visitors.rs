@@ -0,0 +1,139 @@
+use axum::http::Method;
+use chrono::Duration;
+use sqlx::sqlite::SqlitePoolOptions;
+use std::net::IpAddr;
+
+use super::{click, sign_in, test_app};
+use crate::{middleware::do_not_track::opted_out, services::visitor_service::truncate_ip};
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+#[test]
+fn addresses_are_cut_to_their_network() {
+    assert_eq!(truncate_ip(ip("203.0.113.77")), ip("203.0.113.0"));
+    assert_eq!(truncate_ip(ip("::ffff:203.0.113.77")), ip("203.0.113.0"));
+    assert_eq!(truncate_ip(ip("2001:db8:cafe:1234::17")), ip("2001:db8:cafe::"));
+
+    let mut headers = axum::http::HeaderMap::new();
+    assert!(!opted_out(&headers));
+    headers.insert("dnt", "0".parse().unwrap());
+    assert!(!opted_out(&headers));
+    headers.insert("sec-gpc", "1".parse().unwrap());
+    assert!(opted_out(&headers));
+}
+
+#[tokio::test]
+async fn clicks_keep_a_daily_visitor_id_instead_of_the_visitor() {
+    let app = test_app().await;
+    let (user_id, _) = sign_in(&app.state, "a@example.com").await;
+    let frogol = app.state.services.frogol.create_frogol(&user_id, "private", "Private").await.unwrap();
+    let link = app.state.services.frogol.add_link(&frogol.id, "https://example.com", "Example").await.unwrap();
+
+    click(&app, Method::GET, &link.id, "203.0.113.7", "Firefox", &[]).await;
+    click(&app, Method::GET, &link.id, "203.0.113.7", "Firefox", &[]).await;
+    // Same network, different visitors
+    click(&app, Method::GET, &link.id, "203.0.113.8", "Firefox", &[]).await;
+    click(&app, Method::GET, &link.id, "203.0.113.7", "Safari", &[]).await;
+    // Counted, but not identified
+    click(&app, Method::GET, &link.id, "203.0.113.9", "Firefox", &[("dnt", "1")]).await;
+    click(&app, Method::GET, &link.id, "203.0.113.9", "Firefox", &[("sec-gpc", "1")]).await;
+
+    let stats = app.state.services.frogol.get_click_stats(&frogol.id, false).await.unwrap();
+    assert_eq!((stats.total_clicks, stats.unique_clicks), (6, 3));
+
+    let stored: Vec<(Option<String>, Option<String>)> = sqlx::query_as("SELECT ip_address, user_agent FROM clicks")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+    for (ip_address, user_agent) in &stored {
+        assert!(ip_address.is_none() || ip_address.as_deref() == Some("203.0.113.0"), "{:?}", ip_address);
+        assert!(user_agent.is_none());
+    }
+    assert_eq!(stored.iter().filter(|(ip_address, _)| ip_address.is_none()).count(), 2);
+
+    // Tomorrow the same visitor counts again, and yesterday's salt can go
+    app.clock.advance(Duration::days(1));
+    click(&app, Method::GET, &link.id, "203.0.113.7", "Firefox", &[]).await;
+    let stats = app.state.services.frogol.get_click_stats(&frogol.id, false).await.unwrap();
+    assert_eq!((stats.total_clicks, stats.unique_clicks), (7, 4));
+    assert_eq!(app.state.services.maintenance.purge_expired().await.unwrap().visitor_salts, 1);
+}
+
+/// Addresses as earlier clicks may have stored them.
+const LEGACY_IPV6: &[&str] = &[
+    "2001:db8:cafe:1234::17",
+    "2001:db8:cafe:1234:5678:9abc:def0:1",
+    "2001:DB8:00AB:0001:0:0:0:1",
+    "2001:db8::1",
+    "2001:db8:0:1::",
+    "2001::7:8",
+    "2001:0:0:1::",
+    "2001:0:5:6::1",
+    "fe80::1:2",
+    "::1",
+    "::",
+    "1:0:2:3:4:5:6:7",
+    "::1:2:3:4:5:6",
+    "::1:2:3:4:5.6.7.8",
+    "::ffff:198.51.100.23",
+    "0:0:0:0:0:ffff:203.0.113.9",
+];
+
+#[tokio::test]
+async fn the_migration_anonymizes_existing_clicks() {
+    let pool = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+    let migrator = sqlx::migrate!("./migrations");
+    let (before, after): (Vec<_>, Vec<_>) = migrator.iter().partition(|m| m.version < 202610160010);
+    for migration in before {
+        sqlx::raw_sql(&migration.sql).execute(&pool).await.unwrap();
+    }
+    sqlx::raw_sql(
+        // Only the clicks matter here
+        "PRAGMA foreign_keys = OFF;
+         INSERT INTO clicks (id, link_id, ip_address, user_agent, created_at) VALUES
+             ('1', 'l', '198.51.100.23', 'Firefox', '2026-01-01T10:00:00.000Z'),
+             ('2', 'l', '198.51.100.23', 'Firefox', '2026-01-01T11:00:00.000Z'),
+             ('3', 'l', '198.51.100.23', 'Firefox', '2026-01-02T10:00:00.000Z'),
+             ('4', 'l', '2001:db8::1', NULL, '2026-01-02T10:00:00.000Z'),
+             ('5', 'l', NULL, 'Firefox', '2026-01-02T10:00:00.000Z');",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    for (n, address) in LEGACY_IPV6.iter().enumerate() {
+        sqlx::query("INSERT INTO clicks (id, link_id, ip_address, created_at) VALUES (?1, 'l', ?2, '2026-01-03T10:00:00.000Z')")
+            .bind(format!("v6-{}", n))
+            .bind(address)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+    for migration in after {
+        sqlx::raw_sql(&migration.sql).execute(&pool).await.unwrap();
+    }
+
+    let rows: Vec<(Option<String>, Option<String>, Option<String>)> =
+        sqlx::query_as("SELECT ip_address, user_agent, visitor_id FROM clicks WHERE id NOT LIKE 'v6-%' ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    let ips: Vec<_> = rows.iter().map(|r| r.0.as_deref()).collect();
+    assert_eq!(ips, [Some("198.51.100.0"), Some("198.51.100.0"), Some("198.51.100.0"), Some("2001:db8::"), None]);
+    assert!(rows.iter().all(|r| r.1.is_none()));
+    // One visitor a day, as before
+    assert_eq!(rows[0].2, rows[1].2);
+    assert_ne!(rows[1].2, rows[2].2);
+    assert!(rows[3].2.is_some() && rows[4].2.is_none());
+
+    // IPv6 addresses end up as new clicks' do, however they were written
+    for (n, address) in LEGACY_IPV6.iter().enumerate() {
+        let stored: Option<String> = sqlx::query_scalar("SELECT ip_address FROM clicks WHERE id = ?1")
+            .bind(format!("v6-{}", n))
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, Some(truncate_ip(ip(address)).to_string()), "{}", address);
+    }
+}